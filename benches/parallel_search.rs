@@ -0,0 +1,34 @@
+use criterion::{ criterion_group, criterion_main, BenchmarkId, Criterion };
+
+use kalah::ai::alpha_beta::{ AlphaBeta, SearchLimits };
+use kalah::ai::compact_board::CompactBoard;
+use kalah::ai::transposition_table::ReplacementPolicy;
+use kalah::game::game_config::GameConfig;
+
+const TABLE_SIZE: usize = 1 << 20;
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+
+/// Time to reach a fixed depth from the start of Kalah(6,4) and Kalah(6,6), by thread count.
+fn bench_time_to_depth(c: &mut Criterion) {
+    for (stone_nums_in_hole, depth) in [(4, 14), (6, 12)] {
+        let game_config = GameConfig::build(stone_nums_in_hole, 6, false).unwrap();
+        let board = CompactBoard::build(&game_config);
+
+        let mut group = c.benchmark_group(format!("kalah_6_{}_depth_{}", stone_nums_in_hole, depth));
+        group.sample_size(10);
+
+        for threads in THREADS {
+            group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, &threads| {
+                b.iter(|| {
+                    let mut alpha_beta = AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred).with_threads(threads);
+                    alpha_beta.iterative_deepening(&board, &SearchLimits::depth(depth), |_| {})
+                });
+            });
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_time_to_depth);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+pub mod alpha_beta;
+pub mod analysis;
+pub mod background_search;
+pub mod compact_board;
+pub mod difficulty;
+pub mod endgame;
+pub mod engine;
+pub mod evaluation;
+pub mod external_engine;
+pub mod match_runner;
+pub mod opening_book;
+pub mod protocol;
+pub mod solver;
+pub mod transposition_table;
+pub mod tuning;
+pub mod zobrist;
@@ -0,0 +1,489 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use super::compact_board::CompactBoard;
+use super::endgame::EndgameDatabase;
+use super::evaluation::{ Evaluator, StoreDiff };
+use super::transposition_table::{ Bound, ReplacementPolicy, TableEntry, TableStats, TranspositionTable };
+
+pub const DEFAULT_TABLE_SIZE: usize = 1 << 20;
+pub const MAX_DEPTH: u8 = 64;
+
+/// Table depth of values that did not depend on the depth limit, valid at any depth.
+pub const UNLIMITED_DEPTH: u8 = u8::MAX;
+
+/// How often (in nodes) the time budget and stop flag are checked.
+const STOP_CHECK_INTERVAL: u64 = 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    pub nodes: u64,
+    pub beta_cutoffs: u64,
+    pub endgame_hits: u64,
+    pub table: TableStats,
+}
+
+impl SearchStats {
+
+    pub fn add(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.beta_cutoffs += other.beta_cutoffs;
+        self.endgame_hits += other.endgame_hits;
+        self.table.add(&other.table);
+    }
+}
+
+impl fmt::Display for SearchStats {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "nodes {} beta cutoffs {} endgame hits {} {}", self.nodes, self.beta_cutoffs, self.endgame_hits, self.table)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<usize>,
+    /// Store difference from the point of view of the player to move.
+    pub value: i32,
+    pub depth: u8,
+    /// Every line reached the end of the game, so the value is exact.
+    pub is_exact: bool,
+    /// The move came from an opening book, not a search.
+    pub is_book_move: bool,
+    pub stats: SearchStats,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: u8,
+    pub move_time: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+
+    pub fn depth(depth: u8) -> SearchLimits {
+        SearchLimits { depth, move_time: None, nodes: None }
+    }
+
+    pub fn move_time(move_time: Duration) -> SearchLimits {
+        SearchLimits { depth: MAX_DEPTH, move_time: Some(move_time), nodes: None }
+    }
+
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        SearchLimits { depth: MAX_DEPTH, move_time: None, nodes: Some(nodes) }
+    }
+}
+
+/// Negamax alpha-beta over `CompactBoard` with a transposition table.
+/// Extra turns keep the side to move, so the value is only negated when the turn passes.
+/// With more than one thread, iterative deepening runs Lazy SMP: helper threads search the
+/// same position and share results through the table.
+pub struct AlphaBeta {
+    pub table: Arc<TranspositionTable>,
+    evaluator: Arc<dyn Evaluator>,
+    endgame: Option<Arc<EndgameDatabase>>,
+    threads: usize,
+    stats: SearchStats,
+    stop_flag: Arc<AtomicBool>,
+    /// False for the flag of another engine, which resets it itself.
+    owns_stop_flag: bool,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    is_aborted: bool,
+    is_depth_limited: bool,
+}
+
+impl AlphaBeta {
+
+    pub fn build(table_size: usize, replacement_policy: ReplacementPolicy) -> AlphaBeta {
+        Self::with_table(Arc::new(TranspositionTable::build(table_size, replacement_policy)))
+    }
+
+    pub fn with_table(table: Arc<TranspositionTable>) -> AlphaBeta {
+        AlphaBeta {
+            table,
+            evaluator: Arc::new(StoreDiff),
+            endgame: None,
+            threads: 1,
+            stats: SearchStats::default(),
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            owns_stop_flag: true,
+            deadline: None,
+            node_limit: None,
+            is_aborted: false,
+            is_depth_limited: false,
+        }
+    }
+
+    /// Scores positions at the depth limit, store difference by default.
+    pub fn with_evaluator(mut self, evaluator: Arc<dyn Evaluator>) -> AlphaBeta {
+        self.evaluator = evaluator;
+        self
+    }
+
+    /// Positions in the database are scored exactly instead of searched.
+    pub fn with_endgame(mut self, endgame: Arc<EndgameDatabase>) -> AlphaBeta {
+        self.endgame = Some(endgame);
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> AlphaBeta {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Setting the returned flag from another thread stops the running search,
+    /// which then returns the best move found so far.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop_flag)
+    }
+
+    /// Stops with another engine running this search as part of its own.
+    pub fn with_stop_flag(mut self, stop_flag: Arc<AtomicBool>) -> AlphaBeta {
+        self.stop_flag = stop_flag;
+        self.owns_stop_flag = false;
+        self
+    }
+
+    /// True when the last search ended early on a limit or the stop flag.
+    pub fn is_aborted(&self) -> bool {
+        self.is_aborted
+    }
+
+    /// Clears a stop request, e.g. one that arrived after the last search finished.
+    pub fn reset_stop(&self) {
+        self.stop_flag.store(false, Ordering::Relaxed);
+    }
+
+    /// Fixed depth search.
+    pub fn search(&mut self, board: &CompactBoard, depth: u8) -> SearchResult {
+        self.deadline = None;
+        self.node_limit = None;
+        self.start_stats();
+
+        let root_moves = board.legal_moves().collect::<Vec<_>>();
+        let (value, best_move, _) = self.search_root(board, depth.max(1), &root_moves);
+        if self.owns_stop_flag {
+            self.reset_stop();
+        }
+        self.finish(best_move, value, depth.max(1))
+    }
+
+    /// Searches depth 1, 2, ... until a limit is hit or the stop flag is set, ordering root moves
+    /// by the previous iteration. `on_iteration` receives every completed iteration of the main
+    /// thread. The node budget only counts the main thread.
+    pub fn iterative_deepening(
+        &mut self,
+        board: &CompactBoard,
+        limits: &SearchLimits,
+        on_iteration: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        if self.threads == 1 {
+            let result = self.run_iterative_deepening(board, limits, 0, on_iteration);
+            if self.owns_stop_flag {
+                self.reset_stop();
+            }
+            return result;
+        }
+
+        let helpers_stop_flag = Arc::new(AtomicBool::new(false));
+
+        let result = thread::scope(|scope| {
+            let helpers = (1..self.threads)
+                .map(|helper_id| {
+                    let mut helper = AlphaBeta::with_table(Arc::clone(&self.table));
+                    helper.evaluator = Arc::clone(&self.evaluator);
+                    helper.endgame = self.endgame.clone();
+                    helper.stop_flag = Arc::clone(&helpers_stop_flag);
+                    let helper_limits = SearchLimits::depth(limits.depth);
+
+                    scope.spawn(move || helper.run_iterative_deepening(board, &helper_limits, helper_id, |_| {}).stats)
+                })
+                .collect::<Vec<_>>();
+
+            let mut result = self.run_iterative_deepening(board, limits, 0, on_iteration);
+            helpers_stop_flag.store(true, Ordering::Relaxed);
+
+            for helper in helpers {
+                result.stats.add(&helper.join().expect("helper search thread panicked"));
+            }
+            result
+        });
+
+        if self.owns_stop_flag {
+            self.reset_stop();
+        }
+        result
+    }
+
+    /// Expected line of play after a search: `best_move`, then the table's best moves for as
+    /// long as they are stored and legal, at most `max_len` moves.
+    pub fn principal_variation(&self, board: &CompactBoard, best_move: usize, max_len: usize) -> Vec<usize> {
+        let mut line = Vec::new();
+        let mut board = *board;
+        let mut next_move = Some(best_move);
+
+        while let Some(hole_num) = next_move.filter(|&hole_num| line.len() < max_len && board.is_legal_move(hole_num)) {
+            board.apply_move(hole_num).expect("legal moves apply");
+            line.push(hole_num);
+
+            next_move = if board.is_game_over() {
+                None
+            } else {
+                self.table.probe(board.zobrist_hash()).and_then(|entry| entry.best_move).map(|hole_num| hole_num as usize)
+            };
+        }
+
+        line
+    }
+
+    /// Helpers (`helper_id` > 0) start at a rotated root order and odd helpers skip depth 1,
+    /// so threads spread over different parts of the tree.
+    fn run_iterative_deepening(
+        &mut self,
+        board: &CompactBoard,
+        limits: &SearchLimits,
+        helper_id: usize,
+        mut on_iteration: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        self.deadline = limits.move_time.map(|move_time| Instant::now() + move_time);
+        self.node_limit = limits.nodes;
+        self.start_stats();
+
+        let mut root_moves = board.legal_moves().collect::<Vec<_>>();
+        if !root_moves.is_empty() {
+            let rotation = helper_id % root_moves.len();
+            root_moves.rotate_left(rotation);
+        }
+        let mut best = SearchResult {
+            best_move: root_moves.first().copied(),
+            value: Self::evaluate(board),
+            depth: 0,
+            is_exact: board.is_game_over(),
+            is_book_move: false,
+            stats: self.stats,
+        };
+
+        let first_depth = (1 + helper_id % 2).min(limits.depth.max(1) as usize) as u8;
+        for depth in first_depth..=limits.depth.max(1) {
+            let (value, best_move, move_values) = self.search_root(board, depth, &root_moves);
+
+            if self.is_aborted {
+                // The previous best is searched first, so any move finished in this
+                // iteration is at least as good as it at the deeper depth
+                if let Some(best_move) = best_move {
+                    best.best_move = Some(best_move);
+                    best.value = value;
+                }
+                break;
+            }
+
+            best = self.finish(best_move, value, depth);
+            on_iteration(&best);
+
+            if best.is_exact {
+                break;
+            }
+
+            // Previous best first, then by value
+            root_moves.sort_by_key(|hole_num| {
+                let value = move_values.iter().find(|(move_num, _)| move_num == hole_num).map(|&(_, value)| value);
+                (Some(*hole_num) != best_move, -value.unwrap_or(-i32::MAX))
+            });
+        }
+
+        best.stats = self.stats;
+        best
+    }
+
+    fn start_stats(&mut self) {
+        self.stats = SearchStats::default();
+        self.is_aborted = false;
+    }
+
+    fn finish(&self, best_move: Option<usize>, value: i32, depth: u8) -> SearchResult {
+        SearchResult {
+            best_move,
+            value,
+            depth,
+            is_exact: !self.is_depth_limited,
+            is_book_move: false,
+            stats: self.stats,
+        }
+    }
+
+    /// Returns the best value and move plus the value of every fully searched root move.
+    fn search_root(&mut self, board: &CompactBoard, depth: u8, root_moves: &[usize]) -> (i32, Option<usize>, Vec<(usize, i32)>) {
+        self.is_depth_limited = false;
+
+        if root_moves.is_empty() || board.is_game_over() {
+            return (Self::evaluate(board), None, Vec::new());
+        }
+
+        let mut alpha = -i32::MAX;
+        let mut best_move = None;
+        let mut move_values = Vec::new();
+
+        for &hole_num in root_moves {
+            let mut child = *board;
+            if child.apply_move(hole_num).is_err() {
+                continue;
+            }
+
+            let value = self.child_value(board, &child, depth, alpha, i32::MAX);
+            if self.is_aborted {
+                break;
+            }
+
+            move_values.push((hole_num, value));
+            if value > alpha || best_move.is_none() {
+                alpha = alpha.max(value);
+                best_move = Some(hole_num);
+            }
+        }
+
+        (alpha, best_move, move_values)
+    }
+
+    fn child_value(&mut self, board: &CompactBoard, child: &CompactBoard, depth: u8, alpha: i32, beta: i32) -> i32 {
+        if child.is_player_one_turn == board.is_player_one_turn {
+            self.negamax(child, depth - 1, alpha, beta)
+        } else {
+            -self.negamax(child, depth - 1, -beta, -alpha)
+        }
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stats.nodes.is_multiple_of(STOP_CHECK_INTERVAL) {
+            let is_timeout = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            let is_node_limit = self.node_limit.is_some_and(|node_limit| self.stats.nodes >= node_limit);
+
+            if is_timeout || is_node_limit || self.stop_flag.load(Ordering::Relaxed) {
+                self.is_aborted = true;
+            }
+        }
+        self.is_aborted
+    }
+
+    fn negamax(&mut self, board: &CompactBoard, depth: u8, mut alpha: i32, beta: i32) -> i32 {
+        self.stats.nodes += 1;
+
+        if self.should_stop() {
+            return 0;
+        }
+
+        if board.is_game_over() {
+            return Self::evaluate(board);
+        }
+
+        if let Some(value) = self.endgame.as_ref().and_then(|endgame| endgame.probe(board)) {
+            self.stats.endgame_hits += 1;
+            return value;
+        }
+
+        if depth == 0 {
+            self.is_depth_limited = true;
+            return self.evaluator.evaluate(board);
+        }
+
+        let key = board.zobrist_hash();
+        let alpha_orig = alpha;
+        let mut table_move = None;
+
+        self.stats.table.probes += 1;
+        if let Some(entry) = self.table.probe(key) {
+            self.stats.table.hits += 1;
+            table_move = entry.best_move.map(|hole_num| hole_num as usize);
+
+            if entry.depth >= depth {
+                let is_cutoff = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => entry.value >= beta,
+                    Bound::Upper => entry.value <= alpha,
+                };
+
+                if is_cutoff {
+                    self.stats.table.cutoffs += 1;
+                    if entry.depth != UNLIMITED_DEPTH {
+                        self.is_depth_limited = true;
+                    }
+                    return entry.value;
+                }
+            }
+        }
+
+        let mut best_value = -i32::MAX;
+        let mut best_move = None;
+
+        // Track the depth limit for this subtree only, so unlimited values can be stored as such
+        let is_outer_depth_limited = std::mem::replace(&mut self.is_depth_limited, false);
+
+        // Best move from the table is tried first
+        let moves = table_move
+            .into_iter()
+            .chain(board.legal_moves().filter(|&hole_num| Some(hole_num) != table_move))
+            .collect::<Vec<_>>();
+
+        for hole_num in moves {
+            let mut child = *board;
+            if child.apply_move(hole_num).is_err() {
+                continue;
+            }
+
+            let value = self.child_value(board, &child, depth, alpha, beta);
+            if self.is_aborted {
+                return 0;
+            }
+
+            if value > best_value {
+                best_value = value;
+                best_move = Some(hole_num);
+            }
+
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                self.stats.beta_cutoffs += 1;
+                break;
+            }
+        }
+
+        let bound = if best_value <= alpha_orig {
+            Bound::Upper
+        } else if best_value >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+
+        let is_subtree_depth_limited = self.is_depth_limited;
+        self.is_depth_limited = is_outer_depth_limited || is_subtree_depth_limited;
+
+        let is_stored = self.table.store(TableEntry {
+            key,
+            depth: if is_subtree_depth_limited { depth } else { UNLIMITED_DEPTH },
+            value: best_value,
+            bound,
+            best_move: best_move.map(|hole_num| hole_num as u8),
+        });
+        if is_stored {
+            self.stats.table.stores += 1;
+        } else {
+            self.stats.table.rejected_stores += 1;
+        }
+
+        best_value
+    }
+
+    /// Store difference from the point of view of the player to move, exact once the game is over.
+    pub fn evaluate(board: &CompactBoard) -> i32 {
+        if board.is_player_one_turn { board.score_diff() } else { -board.score_diff() }
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+
+use crate::game::game_config::GameConfig;
+use crate::game::game_record::GameRecord;
+
+use super::compact_board::CompactBoard;
+
+/// How much a move gave away compared to the best one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MoveClass {
+    Best,
+    /// Lost at most 1 stone.
+    Good,
+    /// Lost at most 3 stones.
+    Inaccuracy,
+    /// Lost at most 6 stones.
+    Mistake,
+    Blunder,
+}
+
+impl MoveClass {
+
+    pub const ALL: [MoveClass; 5] = [MoveClass::Best, MoveClass::Good, MoveClass::Inaccuracy, MoveClass::Mistake, MoveClass::Blunder];
+
+    pub fn from_loss(loss: i32) -> MoveClass {
+        match loss {
+            ..=0 => MoveClass::Best,
+            1 => MoveClass::Good,
+            2..=3 => MoveClass::Inaccuracy,
+            4..=6 => MoveClass::Mistake,
+            _ => MoveClass::Blunder,
+        }
+    }
+}
+
+impl fmt::Display for MoveClass {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MoveClass::Best => "best",
+            MoveClass::Good => "good",
+            MoveClass::Inaccuracy => "inaccuracy",
+            MoveClass::Mistake => "mistake",
+            MoveClass::Blunder => "blunder",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveAnalysis {
+    pub hole_num: usize,
+    pub is_player_one_move: bool,
+    /// Value of the played move for the player who made it.
+    pub value: i32,
+    pub best_move: usize,
+    pub best_value: i32,
+    pub class: MoveClass,
+}
+
+impl MoveAnalysis {
+
+    pub fn loss(&self) -> i32 {
+        self.best_value - self.value
+    }
+
+    /// Value after the move from player one's point of view, for evaluation graphs.
+    pub fn player_one_value(&self) -> i32 {
+        if self.is_player_one_move { self.value } else { -self.value }
+    }
+
+    /// Record comment such as `mistake -4, best 2 +1`.
+    pub fn comment(&self) -> String {
+        match self.class {
+            MoveClass::Best => format!("best {:+}", self.value),
+            _ => format!("{} {:+}, best {} {:+}", self.class, self.value, self.best_move, self.best_value),
+        }
+    }
+}
+
+/// Replays the record and classifies every move by its loss against the best move, taking
+/// move values from `analyze` (e.g. `search_move_values`). `on_move` receives the number of
+/// moves analysed so far and the total.
+pub fn analyze_game(
+    record: &GameRecord,
+    mut analyze: impl FnMut(&CompactBoard) -> Vec<(usize, i32)>,
+    mut on_move: impl FnMut(usize, usize),
+) -> Result<Vec<MoveAnalysis>, String> {
+    let mut board = start_board(record)?;
+    let mut analyses = Vec::new();
+
+    for (index, &hole_num) in record.moves.iter().enumerate() {
+        let move_values = analyze(&board);
+        let &(best_move, best_value) = move_values
+            .iter()
+            .max_by_key(|&&(hole_num, value)| (value, std::cmp::Reverse(hole_num)))
+            .ok_or_else(|| format!("Move {} ({}) is played after the game is over", index + 1, hole_num))?;
+        let &(_, value) = move_values
+            .iter()
+            .find(|&&(move_num, _)| move_num == hole_num)
+            .ok_or_else(|| format!("Move {} ({}) is illegal", index + 1, hole_num))?;
+
+        analyses.push(MoveAnalysis {
+            hole_num,
+            is_player_one_move: board.is_player_one_turn,
+            value,
+            best_move,
+            best_value,
+            class: MoveClass::from_loss(best_value - value),
+        });
+
+        board.apply_move(hole_num)?;
+        on_move(index + 1, record.moves.len());
+    }
+
+    Ok(analyses)
+}
+
+/// Copy of the record with a comment after every analysed move, replacing its comments.
+pub fn annotate(record: &GameRecord, analyses: &[MoveAnalysis]) -> GameRecord {
+    let mut annotated = record.clone();
+    annotated.comments = analyses.iter().enumerate().map(|(index, analysis)| (index + 1, analysis.comment())).collect();
+    annotated
+}
+
+/// Boards before the first move and after every move of the record.
+pub fn record_boards(record: &GameRecord) -> Result<Vec<CompactBoard>, String> {
+    let mut board = start_board(record)?;
+    let mut boards = vec![board];
+
+    for (index, &hole_num) in record.moves.iter().enumerate() {
+        board.apply_move(hole_num).map_err(|err| format!("Move {} ({}) is illegal: {}", index + 1, hole_num, err))?;
+        boards.push(board);
+    }
+
+    Ok(boards)
+}
+
+fn start_board(record: &GameRecord) -> Result<CompactBoard, String> {
+    match &record.start_position {
+        Some(position) => CompactBoard::from_position(position, record.is_check_pipe_rule),
+        None => Ok(CompactBoard::build(&GameConfig::build(record.stone_nums_in_hole, record.hole_nums, record.is_check_pipe_rule)?)),
+    }
+}
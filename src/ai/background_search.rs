@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc::{ self, Receiver };
+use std::thread::{ self, JoinHandle };
+use std::time::{ Duration, Instant };
+
+use super::alpha_beta::{ AlphaBeta, SearchLimits, SearchResult };
+use super::compact_board::CompactBoard;
+use super::engine::Engine;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchEvent {
+    /// A completed iterative deepening iteration.
+    Iteration(SearchResult),
+    /// The search is over, `best_move` should be played.
+    Finished(SearchResult),
+}
+
+/// Runs `Engine::think` on its own thread so a UI can keep drawing
+/// and stop the search early ("move now").
+pub struct BackgroundSearch<E: Engine = AlphaBeta> {
+    stop_flag: Arc<AtomicBool>,
+    receiver: Receiver<SearchEvent>,
+    handle: JoinHandle<E>,
+    started: Instant,
+}
+
+impl<E: Engine> BackgroundSearch<E> {
+
+    pub fn start(mut engine: E, board: CompactBoard, limits: SearchLimits) -> BackgroundSearch<E> {
+        engine.reset_stop();
+        let stop_flag = engine.stop_handle();
+        let (sender, receiver) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            let result = engine.think(&board, &limits, &mut |iteration| {
+                _ = sender.send(SearchEvent::Iteration(*iteration));
+            });
+            _ = sender.send(SearchEvent::Finished(result));
+            engine
+        });
+
+        BackgroundSearch { stop_flag, receiver, handle, started: Instant::now() }
+    }
+
+    /// Asks the search to return the best move found so far.
+    pub fn stop(&self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn try_next(&self) -> Option<SearchEvent> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Blocks until the search sends its next event.
+    pub fn next(&self) -> Option<SearchEvent> {
+        self.receiver.recv().ok()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Waits for the thread and hands back the engine so its table can be reused.
+    pub fn join(self) -> E {
+        self.handle.join().expect("search thread panicked")
+    }
+}
@@ -0,0 +1,179 @@
+use crate::game::game_config::GameConfig;
+use crate::game::game_process::GameProcess;
+use crate::game::game_status::GameStatus;
+use crate::game::position::Position;
+
+pub const MAX_HOLES: usize = 9;
+
+/// Count-only copy of a `GameProcess` position for search. Follows the same rules,
+/// scores count stones (classic scoring) and never allocates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompactBoard {
+    pub holes: [[u8; MAX_HOLES]; 2],
+    pub stores: [u8; 2],
+    pub hole_nums: u8,
+    pub is_player_one_turn: bool,
+    pub is_check_pipe_rule: bool,
+    pub total_turns: u32,
+}
+
+impl CompactBoard {
+
+    pub fn build(game_config: &GameConfig) -> CompactBoard {
+        let mut holes = [[0; MAX_HOLES]; 2];
+        for side in holes.iter_mut() {
+            side[..game_config.hole_nums].fill(game_config.stone_nums_in_hole as u8);
+        }
+
+        CompactBoard {
+            holes,
+            stores: [0, 0],
+            hole_nums: game_config.hole_nums as u8,
+            is_player_one_turn: true,
+            is_check_pipe_rule: game_config.is_check_pipe_rule,
+            total_turns: 0,
+        }
+    }
+
+    pub fn from_process(game_process: &GameProcess) -> CompactBoard {
+        Self::from_position(&Position::from_process(game_process), game_process.game_config.is_check_pipe_rule)
+            .expect("game process must fit a compact board")
+    }
+
+    pub fn from_position(position: &Position, is_check_pipe_rule: bool) -> Result<CompactBoard, String> {
+        let hole_nums = position.side_one.len();
+        if hole_nums > MAX_HOLES {
+            return Err(format!("Compact board supports at most {} holes (got {})", MAX_HOLES, hole_nums));
+        }
+
+        let total_stones = position.total_stones().unwrap_or(usize::MAX);
+        if total_stones > u8::MAX as usize {
+            return Err(format!("Compact board supports at most {} stones (got {})", u8::MAX, total_stones));
+        }
+
+        let mut holes = [[0; MAX_HOLES]; 2];
+        for (side, counts) in holes.iter_mut().zip([&position.side_one, &position.side_two]) {
+            for (hole, &count) in side.iter_mut().zip(counts.iter()) {
+                *hole = count as u8;
+            }
+        }
+
+        Ok(CompactBoard {
+            holes,
+            stores: [position.side_one_store as u8, position.side_two_store as u8],
+            hole_nums: hole_nums as u8,
+            is_player_one_turn: position.is_player_one_turn,
+            is_check_pipe_rule,
+            // Only the first turns matter for the pie rule, longer counts stop at the maximum
+            total_turns: u32::try_from(position.total_turns).unwrap_or(u32::MAX),
+        })
+    }
+
+    pub fn to_position(&self) -> Position {
+        let hole_nums = self.hole_nums as usize;
+        let counts = |side: &[u8; MAX_HOLES]| side[..hole_nums].iter().map(|&count| count as usize).collect();
+
+        Position {
+            side_one: counts(&self.holes[0]),
+            side_one_store: self.stores[0] as usize,
+            side_two: counts(&self.holes[1]),
+            side_two_store: self.stores[1] as usize,
+            is_player_one_turn: self.is_player_one_turn,
+            total_turns: self.total_turns as usize,
+        }
+    }
+
+    pub fn to_process(&self, player_one_name: String, player_two_name: String, game_config: GameConfig) -> Result<GameProcess, String> {
+        GameProcess::from_position(&self.to_position(), player_one_name, player_two_name, game_config)
+    }
+
+    /// Index of the side to move in `holes` and `stores`.
+    pub fn mover(&self) -> usize {
+        if self.is_player_one_turn { 0 } else { 1 }
+    }
+
+    pub fn is_legal_move(&self, hole_num: usize) -> bool {
+        hole_num >= 1 && hole_num <= self.hole_nums as usize && self.holes[self.mover()][hole_num - 1] > 0
+    }
+
+    /// Hole numbers (1-based) the side to move can play, in order.
+    pub fn legal_moves(&self) -> impl Iterator<Item = usize> + '_ {
+        (1..=self.hole_nums as usize).filter(|&hole_num| self.is_legal_move(hole_num))
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        let hole_nums = self.hole_nums as usize;
+        self.holes.iter().any(|side| side[..hole_nums].iter().all(|&count| count == 0))
+    }
+
+    /// Store difference from the point of view of player one.
+    pub fn score_diff(&self) -> i32 {
+        self.stores[0] as i32 - self.stores[1] as i32
+    }
+
+    /// Same rules and errors as `GameProcess::move_stones_from_hole`.
+    pub fn apply_move(&mut self, hole_num: usize) -> Result<GameStatus, String> {
+        let hole_nums = self.hole_nums as usize;
+
+        if hole_num == 0 || hole_num > hole_nums {
+            return Err(format!(
+                "hole_num must be in range 1..={} (got {})",
+                hole_nums, hole_num
+            ));
+        }
+
+        let mover = self.mover();
+        let opponent = 1 - mover;
+
+        let mut stones = std::mem::take(&mut self.holes[mover][hole_num - 1]);
+        if stones == 0 {
+            return Err("Selected hole is empty".into());
+        }
+
+        // Own holes are positions 0..hole_nums, own store is hole_nums, opponent holes follow
+        let cycle = 2 * hole_nums + 1;
+        let mut position = hole_num - 1;
+        while stones > 0 {
+            position += 1;
+            if position == cycle {
+                position = 0;
+            }
+            stones -= 1;
+
+            if position < hole_nums {
+                self.holes[mover][position] += 1;
+            } else if position == hole_nums {
+                self.stores[mover] += 1;
+            } else {
+                self.holes[opponent][position - hole_nums - 1] += 1;
+            }
+        }
+
+        if position < hole_nums && self.holes[mover][position] == 1 {
+            let opposite = hole_nums - position - 1;
+            self.stores[mover] += std::mem::take(&mut self.holes[opponent][opposite]);
+        }
+
+        self.total_turns = self.total_turns.saturating_add(1);
+
+        if self.is_game_over() {
+            for side in 0..2 {
+                self.stores[side] += self.holes[side][..hole_nums].iter().sum::<u8>();
+                self.holes[side] = [0; MAX_HOLES];
+            }
+            return Ok(GameStatus::Finished);
+        }
+
+        let is_extra_turn = position == hole_nums;
+        if !is_extra_turn {
+            self.is_player_one_turn = !self.is_player_one_turn;
+        }
+
+        if self.is_check_pipe_rule && is_extra_turn && self.total_turns == 1 && self.is_player_one_turn {
+            self.holes.swap(0, 1);
+            self.stores.swap(0, 1);
+        }
+
+        Ok(GameStatus::Run)
+    }
+}
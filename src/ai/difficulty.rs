@@ -0,0 +1,138 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+use rand::{ Rng, SeedableRng };
+use rand::rngs::StdRng;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use super::alpha_beta::{ AlphaBeta, SearchLimits, SearchResult, SearchStats };
+use super::compact_board::CompactBoard;
+use super::engine::Engine;
+use super::opening_book::search_move_values;
+
+/// Strength of the computer opponent, weakest first.
+#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Novice,
+    Casual,
+    Intermediate,
+    Strong,
+    /// Full-strength search within the move time.
+    Master,
+    /// The perfect-play solver.
+    Perfect,
+}
+
+impl Level {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Novice => "novice",
+            Level::Casual => "casual",
+            Level::Intermediate => "intermediate",
+            Level::Strong => "strong",
+            Level::Master => "master",
+            Level::Perfect => "perfect",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Level::Novice => "Looks two plies ahead and often misses captures",
+            Level::Casual => "Looks four plies ahead and sometimes misses captures",
+            Level::Intermediate => "Looks six plies ahead with the odd slip",
+            Level::Strong => "Looks ten plies ahead and rarely slips",
+            Level::Master => "Full-strength search within the move time",
+            Level::Perfect => "Perfect play on small boards, a timed search on larger ones",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Level, String> {
+        Level::iter().find(|level| level.name() == name).ok_or_else(|| format!(
+            "Unknown level '{}', expected one of {}",
+            name, Level::iter().map(|level| level.name()).collect::<Vec<_>>().join(", ")
+        ))
+    }
+
+    /// Handicaps of the weaker levels, None for full strength.
+    pub fn settings(&self) -> Option<LevelSettings> {
+        match self {
+            Level::Novice => Some(LevelSettings { depth: 2, noise: 3, blunder_chance: 0.3, blunder_depth: 1 }),
+            Level::Casual => Some(LevelSettings { depth: 4, noise: 2, blunder_chance: 0.2, blunder_depth: 1 }),
+            Level::Intermediate => Some(LevelSettings { depth: 6, noise: 1, blunder_chance: 0.1, blunder_depth: 2 }),
+            Level::Strong => Some(LevelSettings { depth: 10, noise: 0, blunder_chance: 0.03, blunder_depth: 3 }),
+            Level::Master | Level::Perfect => None,
+        }
+    }
+}
+
+/// How a handicapped engine picks moves: every move is valued by a `depth` search, except
+/// that with `blunder_chance` the whole turn is judged by a `blunder_depth` search instead.
+/// A blunder depth of 1 sees what a move does but none of the opponent's captures in reply,
+/// so mistakes are greedy moves a person would make rather than random ones.
+/// Up to `noise` stones are then added to or taken from each value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelSettings {
+    pub depth: u8,
+    pub noise: i32,
+    pub blunder_chance: f64,
+    pub blunder_depth: u8,
+}
+
+/// Plays with the handicaps of `LevelSettings`. Searches are shallow, so time and node
+/// limits are ignored.
+pub struct LevelEngine {
+    alpha_beta: AlphaBeta,
+    settings: LevelSettings,
+    rng: StdRng,
+}
+
+impl LevelEngine {
+
+    pub fn build(alpha_beta: AlphaBeta, settings: LevelSettings) -> LevelEngine {
+        LevelEngine { alpha_beta, settings, rng: StdRng::from_os_rng() }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> LevelEngine {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn settings(&self) -> &LevelSettings {
+        &self.settings
+    }
+}
+
+impl Engine for LevelEngine {
+
+    fn think(&mut self, board: &CompactBoard, _limits: &SearchLimits, on_iteration: &mut dyn FnMut(&SearchResult)) -> SearchResult {
+        let is_blunder = self.rng.random_bool(self.settings.blunder_chance);
+        let depth = if is_blunder { self.settings.blunder_depth } else { self.settings.depth };
+
+        let noise = self.settings.noise;
+        let best = search_move_values(&mut self.alpha_beta, board, depth)
+            .into_iter()
+            .map(|(hole_num, value)| (hole_num, value, value + self.rng.random_range(-noise..=noise)))
+            .max_by_key(|&(_, _, noisy_value)| noisy_value);
+
+        let result = SearchResult {
+            best_move: best.map(|(hole_num, _, _)| hole_num),
+            value: best.map_or_else(|| AlphaBeta::evaluate(board), |(_, value, _)| value),
+            depth,
+            is_exact: false,
+            is_book_move: false,
+            stats: SearchStats::default(),
+        };
+        on_iteration(&result);
+        result
+    }
+
+    fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.alpha_beta.stop_handle()
+    }
+
+    fn reset_stop(&self) {
+        self.alpha_beta.reset_stop()
+    }
+}
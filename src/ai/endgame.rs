@@ -0,0 +1,322 @@
+use std::fs::File;
+use std::io::{ self, BufReader, BufWriter, Read, Write };
+use std::path::Path;
+
+use super::compact_board::{ CompactBoard, MAX_HOLES };
+
+const MAGIC: &[u8; 8] = b"KALAHEGT";
+const VERSION: u8 = 1;
+
+/// Values are stored as `i8`, so no class may hold more seeds than that.
+pub const MAX_ENDGAME_SEEDS: usize = i8::MAX as usize;
+
+const UNSOLVED: i8 = i8::MIN;
+
+/// Exact outcome of every distribution of up to `max_seeds` seeds in the holes of a board
+/// with `hole_nums` holes per side, built by retrograde analysis.
+///
+/// Positions are stored from the mover's side: one class per seed count, each indexed by the
+/// rank of the hole counts (mover's holes first) among all distributions of that many seeds.
+/// A value is the store difference the mover still gains with perfect play; stores already
+/// filled do not change it. The pie rule is ignored, so positions before the first move are
+/// never probed.
+pub struct EndgameDatabase {
+    hole_nums: usize,
+    max_seeds: usize,
+    classes: Vec<Vec<i8>>,
+    /// `binomials[n][r]` is n choose r.
+    binomials: Vec<Vec<u64>>,
+}
+
+impl EndgameDatabase {
+
+    /// Solves every class from 0 seeds up to `max_seeds`, calling `on_class` after each one
+    /// with its seed count and number of positions.
+    pub fn generate(hole_nums: usize, max_seeds: usize, mut on_class: impl FnMut(usize, usize)) -> Result<EndgameDatabase, String> {
+        let mut database = Self::empty(hole_nums, max_seeds)?;
+
+        for seeds in 0..=max_seeds {
+            let class = database.solve_class(seeds)?;
+            on_class(seeds, class.len());
+            database.classes.push(class);
+        }
+
+        Ok(database)
+    }
+
+    fn empty(hole_nums: usize, max_seeds: usize) -> Result<EndgameDatabase, String> {
+        if hole_nums == 0 || hole_nums > MAX_HOLES {
+            return Err(format!("Endgame hole count must be in range 1..={} (got {})", MAX_HOLES, hole_nums));
+        }
+        if max_seeds > MAX_ENDGAME_SEEDS {
+            return Err(format!("Endgame seed count must be at most {} (got {})", MAX_ENDGAME_SEEDS, max_seeds));
+        }
+
+        let size = max_seeds + 2 * hole_nums;
+        let mut binomials = vec![vec![0u64; size + 1]; size + 1];
+        for n in 0..=size {
+            binomials[n][0] = 1;
+            for r in 1..=n {
+                binomials[n][r] = binomials[n - 1][r - 1].saturating_add(binomials[n - 1][r]);
+            }
+        }
+
+        Ok(EndgameDatabase { hole_nums, max_seeds, classes: Vec::new(), binomials })
+    }
+
+    pub fn hole_nums(&self) -> usize {
+        self.hole_nums
+    }
+
+    pub fn max_seeds(&self) -> usize {
+        self.max_seeds
+    }
+
+    /// Number of positions over all classes.
+    pub fn len(&self) -> usize {
+        self.classes.iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Final store difference with perfect play from the mover's point of view, or None
+    /// when the position is outside the database.
+    pub fn probe(&self, board: &CompactBoard) -> Option<i32> {
+        if board.hole_nums as usize != self.hole_nums || (board.is_check_pipe_rule && board.total_turns == 0) {
+            return None;
+        }
+
+        let mover = board.mover();
+        let holes = [&board.holes[mover][..self.hole_nums], &board.holes[1 - mover][..self.hole_nums]];
+        let seeds = holes.iter().flat_map(|side| side.iter()).map(|&count| count as usize).sum::<usize>();
+        if seeds > self.max_seeds {
+            return None;
+        }
+
+        let rank = self.rank(holes.iter().flat_map(|side| side.iter().copied()), seeds);
+        let store_diff = board.stores[mover] as i32 - board.stores[1 - mover] as i32;
+        Some(store_diff + self.classes[seeds][rank] as i32)
+    }
+
+    fn positions(&self, seeds: usize) -> u64 {
+        self.distributions(seeds, 2 * self.hole_nums)
+    }
+
+    /// Ways to put `seeds` seeds into `slots` holes.
+    fn distributions(&self, seeds: usize, slots: usize) -> u64 {
+        if slots == 0 {
+            return if seeds == 0 { 1 } else { 0 };
+        }
+        self.binomials[seeds + slots - 1][slots - 1]
+    }
+
+    /// Lexicographic rank of the hole counts among all distributions of `seeds` seeds.
+    fn rank(&self, counts: impl Iterator<Item = u8>, seeds: usize) -> usize {
+        let slots = 2 * self.hole_nums;
+        let mut remaining = seeds;
+        let mut rank = 0;
+
+        for (index, count) in counts.take(slots - 1).enumerate() {
+            for smaller in 0..count as usize {
+                rank += self.distributions(remaining - smaller, slots - index - 1);
+            }
+            remaining -= count as usize;
+        }
+
+        rank as usize
+    }
+
+    fn unrank(&self, mut rank: u64, seeds: usize) -> [[u8; MAX_HOLES]; 2] {
+        let slots = 2 * self.hole_nums;
+        let mut holes = [[0; MAX_HOLES]; 2];
+        let mut remaining = seeds;
+
+        for index in 0..slots {
+            let mut count = 0;
+            if index + 1 == slots {
+                count = remaining;
+            } else {
+                loop {
+                    let below = self.distributions(remaining - count, slots - index - 1);
+                    if rank < below {
+                        break;
+                    }
+                    rank -= below;
+                    count += 1;
+                }
+            }
+
+            holes[index / self.hole_nums][index % self.hole_nums] = count as u8;
+            remaining -= count;
+        }
+
+        holes
+    }
+
+    /// Seeds only move towards their own side's store until they leave the board, so a move
+    /// that keeps every seed on the board raises this sum. Solving a class from the highest
+    /// sum down means every child is already known.
+    fn progress(&self, holes: &[[u8; MAX_HOLES]; 2]) -> usize {
+        holes
+            .iter()
+            .flat_map(|side| side[..self.hole_nums].iter().enumerate())
+            .map(|(index, &count)| index * count as usize)
+            .sum()
+    }
+
+    fn solve_class(&self, seeds: usize) -> Result<Vec<i8>, String> {
+        let positions = self.positions(seeds);
+        if positions > u32::MAX as u64 {
+            return Err(format!("{} seeds on {} holes give too many positions ({})", seeds, self.hole_nums, positions));
+        }
+
+        let mut by_progress = vec![Vec::new(); (self.hole_nums - 1) * seeds + 1];
+        for rank in 0..positions {
+            by_progress[self.progress(&self.unrank(rank, seeds))].push(rank as u32);
+        }
+
+        let mut class = vec![UNSOLVED; positions as usize];
+        for &rank in by_progress.iter().rev().flatten() {
+            let holes = self.unrank(rank as u64, seeds);
+            class[rank as usize] = self.solve_position(holes, seeds, &class);
+        }
+
+        Ok(class)
+    }
+
+    /// Best gain for the mover (side 0) over all moves, children looked up in solved classes.
+    fn solve_position(&self, holes: [[u8; MAX_HOLES]; 2], seeds: usize, class: &[i8]) -> i8 {
+        let board = CompactBoard {
+            holes,
+            stores: [0, 0],
+            hole_nums: self.hole_nums as u8,
+            is_player_one_turn: true,
+            is_check_pipe_rule: false,
+            total_turns: 1,
+        };
+
+        if board.is_game_over() {
+            let side_seeds = |side: usize| holes[side].iter().map(|&count| count as i32).sum::<i32>();
+            return (side_seeds(0) - side_seeds(1)) as i8;
+        }
+
+        board
+            .legal_moves()
+            .map(|hole_num| {
+                let mut child = board;
+                child.apply_move(hole_num).expect("legal moves apply");
+
+                let gain = child.stores[0] as i32 - child.stores[1] as i32;
+                if child.is_game_over() {
+                    return gain;
+                }
+
+                let child_mover = child.mover();
+                let child_holes = [&child.holes[child_mover][..self.hole_nums], &child.holes[1 - child_mover][..self.hole_nums]];
+                let child_seeds = seeds - child.stores.iter().map(|&count| count as usize).sum::<usize>();
+                let rank = self.rank(child_holes.iter().flat_map(|side| side.iter().copied()), child_seeds);
+
+                let value = if child_seeds == seeds { class[rank] } else { self.classes[child_seeds][rank] };
+                debug_assert_ne!(value, UNSOLVED, "child solved before its parent");
+
+                if child.is_player_one_turn { gain + value as i32 } else { gain - value as i32 }
+            })
+            .max()
+            .expect("a position that is not over has a legal move") as i8
+    }
+
+    /// Header (magic, version, hole count, seed count) with its CRC-32, then one section per
+    /// class: position count, CRC-32 of the values and the values, one byte each.
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let header = self.header();
+        writer.write_all(&header)?;
+        writer.write_all(&crc32fast::hash(&header).to_le_bytes())?;
+
+        for class in &self.classes {
+            let bytes = class.iter().map(|&value| value as u8).collect::<Vec<_>>();
+            writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+            writer.write_all(&crc32fast::hash(&bytes).to_le_bytes())?;
+            writer.write_all(&bytes)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> Result<EndgameDatabase, String> {
+        let mut header = [0; 12];
+        read_exact(reader, &mut header)?;
+        if &header[..8] != MAGIC {
+            return Err("Not an endgame database".into());
+        }
+        if read_u32(reader)? != crc32fast::hash(&header) {
+            return Err("Endgame database header checksum mismatch".into());
+        }
+        if header[8] != VERSION {
+            return Err(format!("Unsupported endgame database version {}", header[8]));
+        }
+
+        let mut database = Self::empty(header[9] as usize, header[10] as usize)?;
+
+        for seeds in 0..=database.max_seeds {
+            let len = read_u64(reader)?;
+            if len != database.positions(seeds) {
+                return Err(format!("Endgame class {} has {} positions, expected {}", seeds, len, database.positions(seeds)));
+            }
+            let checksum = read_u32(reader)?;
+
+            let mut bytes = vec![0; len as usize];
+            read_exact(reader, &mut bytes)?;
+            if crc32fast::hash(&bytes) != checksum {
+                return Err(format!("Endgame class {} checksum mismatch", seeds));
+            }
+
+            database.classes.push(bytes.into_iter().map(|byte| byte as i8).collect());
+        }
+
+        Ok(database)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|err| format!("Cannot create '{}': {}", path.display(), err))?;
+        let mut writer = BufWriter::new(file);
+
+        self.write_to(&mut writer)
+            .and_then(|_| writer.flush())
+            .map_err(|err| format!("Cannot write '{}': {}", path.display(), err))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<EndgameDatabase, String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|err| format!("Cannot open '{}': {}", path.display(), err))?;
+
+        Self::read_from(&mut BufReader::new(file)).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    fn header(&self) -> [u8; 12] {
+        let mut header = [0; 12];
+        header[..8].copy_from_slice(MAGIC);
+        header[8] = VERSION;
+        header[9] = self.hole_nums as u8;
+        header[10] = self.max_seeds as u8;
+        header
+    }
+}
+
+fn read_exact(reader: &mut impl Read, buffer: &mut [u8]) -> Result<(), String> {
+    reader.read_exact(buffer).map_err(|err| format!("Truncated endgame database: {}", err))
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, String> {
+    let mut bytes = [0; 4];
+    read_exact(reader, &mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, String> {
+    let mut bytes = [0; 8];
+    read_exact(reader, &mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
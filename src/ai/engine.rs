@@ -0,0 +1,46 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+use super::alpha_beta::{ AlphaBeta, SearchLimits, SearchResult };
+use super::compact_board::CompactBoard;
+
+/// A searcher that picks moves for a computer player, e.g. on a `BackgroundSearch` thread.
+pub trait Engine: Send + 'static {
+
+    fn think(&mut self, board: &CompactBoard, limits: &SearchLimits, on_iteration: &mut dyn FnMut(&SearchResult)) -> SearchResult;
+
+    /// Setting the returned flag from another thread makes `think` return early.
+    fn stop_handle(&self) -> Arc<AtomicBool>;
+
+    fn reset_stop(&self);
+}
+
+impl Engine for AlphaBeta {
+
+    fn think(&mut self, board: &CompactBoard, limits: &SearchLimits, on_iteration: &mut dyn FnMut(&SearchResult)) -> SearchResult {
+        self.iterative_deepening(board, limits, on_iteration)
+    }
+
+    fn stop_handle(&self) -> Arc<AtomicBool> {
+        AlphaBeta::stop_handle(self)
+    }
+
+    fn reset_stop(&self) {
+        AlphaBeta::reset_stop(self)
+    }
+}
+
+impl<E: Engine + ?Sized> Engine for Box<E> {
+
+    fn think(&mut self, board: &CompactBoard, limits: &SearchLimits, on_iteration: &mut dyn FnMut(&SearchResult)) -> SearchResult {
+        (**self).think(board, limits, on_iteration)
+    }
+
+    fn stop_handle(&self) -> Arc<AtomicBool> {
+        (**self).stop_handle()
+    }
+
+    fn reset_stop(&self) {
+        (**self).reset_stop()
+    }
+}
@@ -0,0 +1,148 @@
+use super::compact_board::CompactBoard;
+
+pub const FEATURE_COUNT: usize = 6;
+
+/// Names of the features in weight files, in `Features` order.
+pub const FEATURE_NAMES: [&str; FEATURE_COUNT] = ["store", "seeds", "mobility", "extra_turns", "captures", "hoard"];
+
+/// Feature values of a position, each the mover's count minus the opponent's:
+/// store, seeds left in own holes, non-empty holes, moves ending in the store,
+/// best capture available and seeds in the hole next to the store.
+pub type Features = [f64; FEATURE_COUNT];
+
+/// Heuristic score of positions where the search stops before the end of the game.
+/// Shared between search threads, so implementations must be thread safe.
+pub trait Evaluator: Send + Sync {
+
+    /// Value from the point of view of the player to move, in stones of store difference.
+    fn evaluate(&self, board: &CompactBoard) -> i32;
+}
+
+/// Store difference only, the value the game ends with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreDiff;
+
+impl Evaluator for StoreDiff {
+
+    fn evaluate(&self, board: &CompactBoard) -> i32 {
+        let mover = board.mover();
+        board.stores[mover] as i32 - board.stores[1 - mover] as i32
+    }
+}
+
+pub fn features(board: &CompactBoard) -> Features {
+    let mover = board.mover();
+    let [own, opponent] = [side_features(board, mover), side_features(board, 1 - mover)];
+
+    std::array::from_fn(|index| own[index] - opponent[index])
+}
+
+fn side_features(board: &CompactBoard, side: usize) -> Features {
+    let hole_nums = board.hole_nums as usize;
+    let holes = &board.holes[side][..hole_nums];
+    let opposite = &board.holes[1 - side][..hole_nums];
+
+    let mut features = [0.0; FEATURE_COUNT];
+    features[0] = board.stores[side] as f64;
+
+    let mut best_capture = 0;
+    for (index, &count) in holes.iter().enumerate().filter(|&(_, &count)| count > 0) {
+        let count = count as usize;
+        features[1] += count as f64;
+        features[2] += 1.0;
+
+        // Sowing goes round own holes, own store and the opponent's holes
+        if (index + count) % (2 * hole_nums + 1) == hole_nums {
+            features[3] += 1.0;
+        }
+
+        // Last seed in an empty own hole without a full lap captures the opposite hole
+        let last = index + count;
+        if last < hole_nums && holes[last] == 0 && opposite[hole_nums - last - 1] > 0 {
+            best_capture = best_capture.max(opposite[hole_nums - last - 1] as usize);
+        }
+    }
+
+    features[4] = best_capture as f64;
+    features[5] = holes[hole_nums - 1] as f64;
+    features
+}
+
+/// Weighted sum of `Features`, rounded. Weights load from a text file with one
+/// `<feature> <weight>` line per feature; missing features weigh 0 and lines starting
+/// with `#` are comments:
+///
+/// ```text
+/// store 1
+/// seeds 0.25
+/// captures 0.5
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearEvaluator {
+    pub weights: [f64; FEATURE_COUNT],
+}
+
+impl Default for LinearEvaluator {
+
+    /// Scores like `StoreDiff`.
+    fn default() -> LinearEvaluator {
+        let mut weights = [0.0; FEATURE_COUNT];
+        weights[0] = 1.0;
+        LinearEvaluator { weights }
+    }
+}
+
+impl Evaluator for LinearEvaluator {
+
+    fn evaluate(&self, board: &CompactBoard) -> i32 {
+        self.score(&features(board)).round() as i32
+    }
+}
+
+impl LinearEvaluator {
+
+    /// Unrounded value of the features.
+    pub fn score(&self, features: &Features) -> f64 {
+        self.weights.iter().zip(features).map(|(weight, feature)| weight * feature).sum()
+    }
+
+    pub fn parse(text: &str) -> Result<LinearEvaluator, String> {
+        let mut weights = [0.0; FEATURE_COUNT];
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (name, weight) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("Weight line must be '<feature> <weight>' (got '{}')", line))?;
+            let index = FEATURE_NAMES
+                .iter()
+                .position(|&feature| feature == name)
+                .ok_or_else(|| format!("Unknown feature '{}', expected one of {}", name, FEATURE_NAMES.join(", ")))?;
+
+            weights[index] = weight
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|weight| weight.is_finite())
+                .ok_or_else(|| format!("Invalid weight for {} '{}'", name, weight.trim()))?;
+        }
+
+        Ok(LinearEvaluator { weights })
+    }
+
+    pub fn to_text(&self) -> String {
+        FEATURE_NAMES
+            .iter()
+            .zip(&self.weights)
+            .map(|(name, weight)| format!("{} {}\n", name, weight))
+            .collect()
+    }
+
+    pub fn load(path: &str) -> Result<LinearEvaluator, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("Cannot read '{}': {}", path, err))?;
+        Self::parse(&text).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_text()).map_err(|err| format!("Cannot write '{}': {}", path, err))
+    }
+}
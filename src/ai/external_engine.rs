@@ -0,0 +1,185 @@
+use std::io::{ BufRead, BufReader, Write };
+use std::process::{ Child, ChildStdin, Command, Stdio };
+use std::sync::mpsc::{ self, Receiver, RecvTimeoutError };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use crate::game::game_config::GameConfig;
+
+use super::alpha_beta::SearchLimits;
+use super::compact_board::CompactBoard;
+use super::match_runner::PlayerController;
+use super::protocol::{ EngineCommand, EngineReply };
+
+/// Time an engine gets to answer on top of its move time.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a quitting engine gets before it is killed.
+const QUIT_TIME: Duration = Duration::from_millis(200);
+
+/// Engine in another process speaking the engine protocol on its stdin and stdout.
+/// Every reply is awaited with a timeout, so an engine that hangs, crashes or plays an
+/// illegal move forfeits its game instead of stalling the match.
+pub struct ExternalEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    limits: SearchLimits,
+    timeout: Duration,
+}
+
+impl ExternalEngine {
+
+    /// Starts `command_line` (the program and its arguments, separated by whitespace) and
+    /// waits for the handshake. The engine is named by its `id name` reply.
+    pub fn spawn(command_line: &str) -> Result<ExternalEngine, String> {
+        let mut parts = command_line.split_whitespace();
+        let program = parts.next().ok_or("Engine command is empty")?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("Cannot start engine '{}': {}", program, err))?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = ExternalEngine {
+            name: program.to_string(),
+            child,
+            stdin,
+            lines,
+            limits: SearchLimits::move_time(Duration::from_secs(1)),
+            timeout: DEFAULT_TIMEOUT,
+        };
+
+        engine.send(&EngineCommand::Kalah)?;
+        let deadline = Instant::now() + engine.timeout;
+        loop {
+            match engine.receive(deadline)? {
+                EngineReply::Id { name } => engine.name = name,
+                EngineReply::KalahOk => return Ok(engine),
+                _ => {}
+            }
+        }
+    }
+
+    /// Limits sent with every `go`, one second per move by default.
+    pub fn with_limits(mut self, limits: SearchLimits) -> ExternalEngine {
+        self.limits = limits;
+        self
+    }
+
+    /// Time to answer on top of the move time.
+    pub fn with_timeout(mut self, timeout: Duration) -> ExternalEngine {
+        self.timeout = timeout;
+        self
+    }
+
+    fn send(&mut self, command: &EngineCommand) -> Result<(), String> {
+        writeln!(self.stdin, "{}", command.to_line())
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| self.exit_reason())
+    }
+
+    /// Next reply before `deadline`. Lines that are not replies are skipped.
+    fn receive(&mut self, deadline: Instant) -> Result<EngineReply, String> {
+        loop {
+            let line = match self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => return Err(format!("{} timed out", self.name)),
+                Err(RecvTimeoutError::Disconnected) => return Err(self.exit_reason()),
+            };
+
+            match EngineReply::parse(&line) {
+                Ok(EngineReply::Error(message)) => return Err(format!("{} reported an error: {}", self.name, message)),
+                Ok(reply) => return Ok(reply),
+                Err(_) => continue,
+            }
+        }
+    }
+
+    /// Waits until the engine has handled everything sent so far, dropping replies that
+    /// are left over, e.g. from a search that timed out.
+    fn sync(&mut self) -> Result<(), String> {
+        self.send(&EngineCommand::IsReady)?;
+        let deadline = Instant::now() + self.timeout;
+        while self.receive(deadline)? != EngineReply::ReadyOk {}
+        Ok(())
+    }
+
+    fn exit_reason(&mut self) -> String {
+        // The reader thread sees the end of the output slightly before the process is reaped
+        let deadline = Instant::now() + QUIT_TIME;
+        while Instant::now() < deadline {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                return format!("{} exited ({})", self.name, status);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        format!("{} closed its output", self.name)
+    }
+}
+
+impl PlayerController for ExternalEngine {
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self, game_config: &GameConfig) -> Result<(), String> {
+        self.send(&EngineCommand::NewGame(game_config.clone()))?;
+        self.sync()
+    }
+
+    fn choose_move(&mut self, board: &CompactBoard) -> Result<usize, String> {
+        self.send(&EngineCommand::Position { position: Some(board.to_position()), moves: Vec::new() })?;
+        self.send(&EngineCommand::Go { limits: self.limits, is_infinite: false })?;
+
+        let deadline = Instant::now() + self.limits.move_time.unwrap_or_default() + self.timeout;
+        loop {
+            match self.receive(deadline) {
+                Ok(EngineReply::BestMove(Some(hole_num))) if board.is_legal_move(hole_num) => return Ok(hole_num),
+                Ok(EngineReply::BestMove(hole_num)) => {
+                    let hole_num = hole_num.map_or("none".to_string(), |hole_num| hole_num.to_string());
+                    return Err(format!("{} played illegal move {}", self.name, hole_num));
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    // Let a slow engine stop so its late move cannot answer the next search
+                    _ = self.send(&EngineCommand::Stop);
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+
+    fn drop(&mut self) {
+        _ = self.send(&EngineCommand::Quit);
+
+        let deadline = Instant::now() + QUIT_TIME;
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        _ = self.child.kill();
+        _ = self.child.wait();
+    }
+}
@@ -0,0 +1,189 @@
+use std::cmp::Ordering;
+
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::game::game_config::GameConfig;
+
+use super::alpha_beta::SearchLimits;
+use super::compact_board::CompactBoard;
+use super::engine::Engine;
+
+/// Picks the moves of one player in a headless match.
+pub trait PlayerController {
+
+    fn name(&self) -> &str;
+
+    /// Called before every game.
+    fn new_game(&mut self, game_config: &GameConfig) -> Result<(), String>;
+
+    /// Move for the player to move on `board`. An error forfeits the game.
+    fn choose_move(&mut self, board: &CompactBoard) -> Result<usize, String>;
+}
+
+/// One of our engines, searching every move within `limits`.
+pub struct EngineController<E: Engine> {
+    name: String,
+    engine: E,
+    limits: SearchLimits,
+}
+
+impl<E: Engine> EngineController<E> {
+
+    pub fn build(name: String, engine: E, limits: SearchLimits) -> EngineController<E> {
+        EngineController { name, engine, limits }
+    }
+
+    /// Limits for the following moves, such as a move time fitting a clock.
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+}
+
+impl<E: Engine> PlayerController for EngineController<E> {
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self, _game_config: &GameConfig) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn choose_move(&mut self, board: &CompactBoard) -> Result<usize, String> {
+        self.engine.think(board, &self.limits, &mut |_| {}).best_move.ok_or_else(|| format!("{} found no move", self.name))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameResult {
+    /// Index of the controller that played player one.
+    pub player_one: usize,
+    /// Opening and played moves.
+    pub moves: Vec<usize>,
+    pub stores: [u8; 2],
+    /// Player (0 for player one) who forfeited the game and why.
+    pub forfeit: Option<(usize, String)>,
+}
+
+impl GameResult {
+
+    /// Index of the controller that won, None for a draw.
+    pub fn winner(&self) -> Option<usize> {
+        let player = match &self.forfeit {
+            Some((player, _)) => Some(1 - player),
+            None => match self.stores[0].cmp(&self.stores[1]) {
+                Ordering::Greater => Some(0),
+                Ordering::Less => Some(1),
+                Ordering::Equal => None,
+            },
+        };
+        player.map(|player| player ^ self.player_one)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchScore {
+    /// Wins of each controller.
+    pub wins: [usize; 2],
+    pub draws: usize,
+}
+
+/// Plays games between two controllers without a UI. Games come in pairs from the same
+/// random opening of `random_plies` moves with the players swapped, so neither side profits
+/// from a lucky opening.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchRunner {
+    pub games: usize,
+    pub random_plies: usize,
+}
+
+impl MatchRunner {
+
+    /// `on_game` receives the number and result of every finished game.
+    pub fn run(
+        &self,
+        game_config: &GameConfig,
+        controllers: [&mut dyn PlayerController; 2],
+        rng: &mut impl Rng,
+        mut on_game: impl FnMut(usize, &GameResult),
+    ) -> MatchScore {
+        let [first, second] = controllers;
+        let mut score = MatchScore::default();
+        let mut opening = Vec::new();
+
+        for game in 0..self.games {
+            if game % 2 == 0 {
+                opening = random_opening(game_config, self.random_plies, rng);
+            }
+
+            let player_one = game % 2;
+            let players: [&mut dyn PlayerController; 2] = if player_one == 0 { [&mut *first, &mut *second] } else { [&mut *second, &mut *first] };
+            let result = play_game(game_config, &opening, players, player_one);
+
+            match result.winner() {
+                Some(winner) => score.wins[winner] += 1,
+                None => score.draws += 1,
+            }
+            on_game(game + 1, &result);
+        }
+
+        score
+    }
+}
+
+/// Plays one game from the moves of `opening`, `players` in player order. `player_one` is only
+/// copied to the result.
+pub fn play_game(game_config: &GameConfig, opening: &[usize], mut players: [&mut dyn PlayerController; 2], player_one: usize) -> GameResult {
+    let mut board = CompactBoard::build(game_config);
+    let mut result = GameResult { player_one, moves: Vec::new(), stores: [0, 0], forfeit: None };
+
+    for &hole_num in opening {
+        board.apply_move(hole_num).expect("opening moves are legal");
+        result.moves.push(hole_num);
+    }
+
+    for (player, controller) in players.iter_mut().enumerate() {
+        if let Err(err) = controller.new_game(game_config) {
+            result.forfeit = Some((player, err));
+            return result;
+        }
+    }
+
+    while !board.is_game_over() {
+        let player = board.mover();
+        let controller = &mut players[player];
+        let chosen = controller.choose_move(&board).and_then(|hole_num| {
+            board.apply_move(hole_num).map_err(|err| format!("{} played illegal move {}: {}", controller.name(), hole_num, err))?;
+            Ok(hole_num)
+        });
+
+        match chosen {
+            Ok(hole_num) => result.moves.push(hole_num),
+            Err(err) => {
+                result.forfeit = Some((player, err));
+                break;
+            }
+        }
+    }
+
+    result.stores = board.stores;
+    result
+}
+
+/// Random legal moves from the start position, fewer if the game ends.
+pub fn random_opening(game_config: &GameConfig, plies: usize, rng: &mut impl Rng) -> Vec<usize> {
+    let mut board = CompactBoard::build(game_config);
+    let mut moves = Vec::new();
+
+    for _ in 0..plies {
+        let Some(&hole_num) = board.legal_moves().collect::<Vec<_>>().choose(rng) else { break };
+        board.apply_move(hole_num).expect("legal moves apply");
+        moves.push(hole_num);
+        if board.is_game_over() {
+            break;
+        }
+    }
+
+    moves
+}
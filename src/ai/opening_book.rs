@@ -0,0 +1,368 @@
+use std::collections::{ HashMap, HashSet };
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+use rand::{ Rng, SeedableRng };
+use rand::rngs::StdRng;
+
+use crate::game::game_config::GameConfig;
+use crate::game::game_record::GameRecord;
+use crate::game::position::Position;
+
+use super::alpha_beta::{ AlphaBeta, SearchLimits, SearchResult, SearchStats };
+use super::compact_board::CompactBoard;
+use super::engine::Engine;
+use super::solver::Solver;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+    pub hole_num: usize,
+    pub weight: u32,
+}
+
+/// Weighted moves for positions of one configuration, in a text format:
+///
+/// ```text
+/// [Holes "6"]
+/// [Stones "4"]
+/// [PieRule "on"]
+///
+/// 4,4,4,4,4,4:0/4,4,4,4,4,4:0 1 0 3:12 1:4
+/// ```
+///
+/// Each line is a position in notation followed by `<hole>:<weight>` moves.
+/// Lines starting with `#` are comments.
+#[derive(Debug, Clone)]
+pub struct OpeningBook {
+    pub stone_nums_in_hole: usize,
+    pub hole_nums: usize,
+    pub is_check_pipe_rule: bool,
+    entries: Vec<(Position, Vec<BookMove>)>,
+    index: HashMap<u64, usize>,
+}
+
+impl OpeningBook {
+
+    pub fn build(game_config: &GameConfig) -> OpeningBook {
+        OpeningBook {
+            stone_nums_in_hole: game_config.stone_nums_in_hole,
+            hole_nums: game_config.hole_nums,
+            is_check_pipe_rule: game_config.is_check_pipe_rule,
+            entries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Book moves for the position, empty when it is not in the book or from another configuration.
+    pub fn moves(&self, board: &CompactBoard) -> &[BookMove] {
+        if !self.is_same_config(board) {
+            return &[];
+        }
+
+        self.index
+            .get(&board.zobrist_hash())
+            .map_or(&[], |&index| self.entries[index].1.as_slice())
+    }
+
+    /// Adds `weight` to the move, creating the position's entry if needed. Weights stop at
+    /// `u32::MAX`, which merged or edited book files may reach.
+    pub fn add_move(&mut self, board: &CompactBoard, hole_num: usize, weight: u32) -> Result<(), String> {
+        if !self.is_same_config(board) {
+            return Err("Position does not belong to the book's configuration".into());
+        }
+        if !board.is_legal_move(hole_num) {
+            return Err(format!("Book move {} is illegal in {}", hole_num, board.to_position().to_notation()));
+        }
+
+        let index = *self.index.entry(board.zobrist_hash()).or_insert_with(|| {
+            self.entries.push((board.to_position(), Vec::new()));
+            self.entries.len() - 1
+        });
+
+        let moves = &mut self.entries[index].1;
+        match moves.iter_mut().find(|book_move| book_move.hole_num == hole_num) {
+            Some(book_move) => book_move.weight = book_move.weight.saturating_add(weight),
+            None => moves.push(BookMove { hole_num, weight }),
+        }
+        moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.weight));
+
+        Ok(())
+    }
+
+    /// Picks a book move. `randomness` 0 always plays the heaviest move, 1 picks in proportion
+    /// to the weights and values in between favour heavy moves more.
+    pub fn choose(&self, board: &CompactBoard, randomness: f64, rng: &mut impl Rng) -> Option<usize> {
+        let moves = self.moves(board);
+        let heaviest = moves.first()?;
+
+        if randomness <= 0.0 {
+            return Some(heaviest.hole_num);
+        }
+
+        let shares = moves
+            .iter()
+            .map(|book_move| (book_move.weight as f64 / heaviest.weight.max(1) as f64).powf(1.0 / randomness))
+            .collect::<Vec<_>>();
+        let mut pick = rng.random::<f64>() * shares.iter().sum::<f64>();
+
+        for (book_move, share) in moves.iter().zip(&shares) {
+            if pick < *share {
+                return Some(book_move.hole_num);
+            }
+            pick -= share;
+        }
+        Some(heaviest.hole_num)
+    }
+
+    fn is_same_config(&self, board: &CompactBoard) -> bool {
+        let stones = board.stores.iter().chain(board.holes.iter().flatten()).map(|&count| count as usize).sum::<usize>();
+
+        board.hole_nums as usize == self.hole_nums
+            && board.is_check_pipe_rule == self.is_check_pipe_rule
+            && stones == 2 * self.hole_nums * self.stone_nums_in_hole
+    }
+
+    pub fn parse(text: &str) -> Result<OpeningBook, String> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .peekable();
+
+        let mut tags = Vec::new();
+        while let Some(line) = lines.next_if(|line| line.starts_with('[')) {
+            tags.push(GameRecord::parse_tag(line)?);
+        }
+
+        let tag = |name: &str| tags.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+        let number_tag = |name: &str| tag(name)
+            .ok_or_else(|| format!("Missing [{}] tag", name))?
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid number in [{}] tag", name));
+
+        let is_check_pipe_rule = match tag("PieRule") {
+            Some("on") => true,
+            Some("off") | None => false,
+            Some(value) => return Err(format!("PieRule must be 'on' or 'off' (got '{}')", value)),
+        };
+        let game_config = GameConfig::build(number_tag("Stones")?, number_tag("Holes")?, is_check_pipe_rule)?;
+        let mut book = OpeningBook::build(&game_config);
+
+        for line in lines {
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            if parts.len() < 4 {
+                return Err(format!("Book line '{}' needs a position and at least one move", line));
+            }
+
+            let position = Position::parse(&parts[..3].join(" "))?;
+            let board = CompactBoard::from_position(&position, is_check_pipe_rule)?;
+
+            for book_move in &parts[3..] {
+                let (hole_num, weight) = book_move
+                    .split_once(':')
+                    .ok_or_else(|| format!("Book move must be '<hole>:<weight>' (got '{}')", book_move))?;
+                let hole_num = hole_num.parse().map_err(|_| format!("Invalid book move '{}'", book_move))?;
+                let weight = weight.parse().map_err(|_| format!("Invalid weight in book move '{}'", book_move))?;
+                book.add_move(&board, hole_num, weight)?;
+            }
+        }
+
+        Ok(book)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "[Holes \"{}\"]\n[Stones \"{}\"]\n[PieRule \"{}\"]\n\n",
+            self.hole_nums, self.stone_nums_in_hole, if self.is_check_pipe_rule { "on" } else { "off" }
+        );
+
+        for (position, moves) in &self.entries {
+            let moves = moves.iter().map(|book_move| format!("{}:{}", book_move.hole_num, book_move.weight)).collect::<Vec<_>>();
+            text.push_str(&format!("{} {}\n", position.to_notation(), moves.join(" ")));
+        }
+
+        text
+    }
+
+    pub fn load(path: &str) -> Result<OpeningBook, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("Cannot read '{}': {}", path, err))?;
+        Self::parse(&text).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_text()).map_err(|err| format!("Cannot write '{}': {}", path, err))
+    }
+}
+
+/// Builds books from per-move values of an analysis (search or solver), for the first `plies`
+/// plies. Moves within `margin` of the best value are book moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookBuilder {
+    pub plies: usize,
+    pub margin: i32,
+}
+
+impl BookBuilder {
+
+    /// Adds every position reachable through book moves, weighting moves by how close
+    /// they are to the best one.
+    pub fn expand(
+        &self,
+        book: &mut OpeningBook,
+        root: &CompactBoard,
+        mut analyze: impl FnMut(&CompactBoard) -> Vec<(usize, i32)>,
+    ) -> Result<(), String> {
+        let mut frontier = vec![*root];
+        let mut seen = HashSet::from([root.zobrist_hash()]);
+
+        for _ in 0..self.plies {
+            let mut next = Vec::new();
+
+            for board in frontier {
+                for (hole_num, value, best) in self.candidates(&analyze(&board)) {
+                    book.add_move(&board, hole_num, (self.margin - (best - value) + 1) as u32)?;
+
+                    let mut child = board;
+                    child.apply_move(hole_num)?;
+                    if !child.is_game_over() && seen.insert(child.zobrist_hash()) {
+                        next.push(child);
+                    }
+                }
+            }
+
+            frontier = next;
+        }
+
+        Ok(())
+    }
+
+    /// Plays `games` games choosing at random between the candidate moves and counts how
+    /// often each move was played.
+    pub fn self_play(
+        &self,
+        book: &mut OpeningBook,
+        root: &CompactBoard,
+        games: usize,
+        rng: &mut impl Rng,
+        mut analyze: impl FnMut(&CompactBoard) -> Vec<(usize, i32)>,
+    ) -> Result<(), String> {
+        let mut analyses = HashMap::new();
+
+        for _ in 0..games {
+            let mut board = *root;
+
+            for _ in 0..self.plies {
+                if board.is_game_over() {
+                    break;
+                }
+
+                let move_values = analyses.entry(board.zobrist_hash()).or_insert_with(|| analyze(&board));
+                let candidates = self.candidates(move_values);
+                let (hole_num, _, _) = candidates[rng.random_range(0..candidates.len())];
+
+                book.add_move(&board, hole_num, 1)?;
+                board.apply_move(hole_num)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves within the margin as `(hole_num, value, best value)`.
+    fn candidates(&self, move_values: &[(usize, i32)]) -> Vec<(usize, i32, i32)> {
+        let best = move_values.iter().map(|&(_, value)| value).max().unwrap_or(0);
+
+        move_values
+            .iter()
+            .filter(|&&(_, value)| best - value <= self.margin)
+            .map(|&(hole_num, value)| (hole_num, value, best))
+            .collect()
+    }
+}
+
+/// Value of every legal move from the mover's point of view, each child searched to `depth - 1`.
+pub fn search_move_values(alpha_beta: &mut AlphaBeta, board: &CompactBoard, depth: u8) -> Vec<(usize, i32)> {
+    move_values(board, |child| alpha_beta.search(child, depth.saturating_sub(1).max(1)).value)
+}
+
+/// Exact value of every legal move from the mover's point of view.
+pub fn solver_move_values(solver: &mut Solver, board: &CompactBoard) -> Vec<(usize, i32)> {
+    move_values(board, |child| solver.solve(child).expect("nothing stops the solver").value)
+}
+
+fn move_values(board: &CompactBoard, mut child_value: impl FnMut(&CompactBoard) -> i32) -> Vec<(usize, i32)> {
+    board
+        .legal_moves()
+        .map(|hole_num| {
+            let mut child = *board;
+            child.apply_move(hole_num).expect("legal moves apply");
+
+            let value = if child.is_game_over() { AlphaBeta::evaluate(&child) } else { child_value(&child) };
+            (hole_num, if child.is_player_one_turn == board.is_player_one_turn { value } else { -value })
+        })
+        .collect()
+}
+
+/// Plays from the book while the position is in it, then hands over to `engine`.
+pub struct BookEngine<E: Engine> {
+    book: Arc<OpeningBook>,
+    engine: E,
+    randomness: f64,
+    rng: StdRng,
+}
+
+impl<E: Engine> BookEngine<E> {
+
+    pub fn build(book: Arc<OpeningBook>, engine: E) -> BookEngine<E> {
+        BookEngine { book, engine, randomness: 0.0, rng: StdRng::from_os_rng() }
+    }
+
+    /// See `OpeningBook::choose`.
+    pub fn with_randomness(mut self, randomness: f64) -> BookEngine<E> {
+        self.randomness = randomness;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> BookEngine<E> {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn engine(&self) -> &E {
+        &self.engine
+    }
+}
+
+impl<E: Engine> Engine for BookEngine<E> {
+
+    fn think(&mut self, board: &CompactBoard, limits: &SearchLimits, on_iteration: &mut dyn FnMut(&SearchResult)) -> SearchResult {
+        match self.book.choose(board, self.randomness, &mut self.rng) {
+            Some(hole_num) => SearchResult {
+                best_move: Some(hole_num),
+                value: AlphaBeta::evaluate(board),
+                depth: 0,
+                is_exact: false,
+                is_book_move: true,
+                stats: SearchStats::default(),
+            },
+            None => self.engine.think(board, limits, on_iteration),
+        }
+    }
+
+    fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.engine.stop_handle()
+    }
+
+    fn reset_stop(&self) {
+        self.engine.reset_stop()
+    }
+}
@@ -0,0 +1,474 @@
+use std::io::{ self, BufRead, Write };
+use std::sync::mpsc::{ self, RecvTimeoutError };
+use std::thread;
+use std::time::Duration;
+
+use crate::game::game_config::GameConfig;
+use crate::game::position::Position;
+
+use super::alpha_beta::{ SearchLimits, SearchResult, SearchStats, MAX_DEPTH };
+use super::background_search::{ BackgroundSearch, SearchEvent };
+use super::compact_board::CompactBoard;
+use super::engine::Engine;
+
+/// How often a running session forwards search progress while it waits for input.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Line a GUI sends to an engine:
+///
+/// ```text
+/// kalah                                   handshake, answered by `id name ...` and `kalahok`
+/// isready                                 answered by `readyok`
+/// newgame holes=6 stones=4 pie=on         board configuration of the next positions
+/// position startpos moves 3 1             start position, or a position in notation
+/// position 4,4,4:0/4,4,4:0 1 0 moves 2    followed by moves played from it
+/// go depth 10 movetime 1000 nodes 50000   search until a limit is hit, `go infinite` until `stop`
+/// stop                                    answer now with the best move so far
+/// quit
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineCommand {
+    Kalah,
+    IsReady,
+    NewGame(GameConfig),
+    /// None for the start position.
+    Position { position: Option<Position>, moves: Vec<usize> },
+    /// `is_infinite` holds back the `bestmove` until `stop`.
+    Go { limits: SearchLimits, is_infinite: bool },
+    Stop,
+    Quit,
+}
+
+/// Line an engine sends back:
+///
+/// ```text
+/// id name kalah 0.1.0
+/// kalahok
+/// readyok
+/// info depth 8 score 3 move 2 nodes 51234 time 120 exact book
+/// bestmove 2                              `bestmove none` without legal moves
+/// error <message>                         a command was not understood or not legal
+/// ```
+///
+/// Scores are store differences for the player to move, `time` is in milliseconds and the
+/// `exact` and `book` flags are left out when they do not apply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineReply {
+    Id { name: String },
+    KalahOk,
+    ReadyOk,
+    /// A completed iteration. Only the node count of the stats is sent.
+    Info { result: SearchResult, time: Duration },
+    BestMove(Option<usize>),
+    Error(String),
+}
+
+impl EngineCommand {
+
+    pub fn parse(line: &str) -> Result<EngineCommand, String> {
+        let mut tokens = line.split_whitespace();
+        let command = match tokens.next() {
+            Some("kalah") => EngineCommand::Kalah,
+            Some("isready") => EngineCommand::IsReady,
+            Some("newgame") => EngineCommand::NewGame(Self::parse_new_game(tokens.by_ref())?),
+            Some("position") => Self::parse_position(tokens.by_ref())?,
+            Some("go") => Self::parse_go(tokens.by_ref())?,
+            Some("stop") => EngineCommand::Stop,
+            Some("quit") => EngineCommand::Quit,
+            Some(command) => return Err(format!("Unknown command '{}'", command)),
+            None => return Err("Empty command".into()),
+        };
+
+        match tokens.next() {
+            Some(token) => Err(format!("Unexpected '{}' in '{}'", token, line.trim())),
+            None => Ok(command),
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        match self {
+            EngineCommand::Kalah => "kalah".to_string(),
+            EngineCommand::IsReady => "isready".to_string(),
+            EngineCommand::NewGame(game_config) => format!(
+                "newgame holes={} stones={} pie={}",
+                game_config.hole_nums, game_config.stone_nums_in_hole, if game_config.is_check_pipe_rule { "on" } else { "off" }
+            ),
+            EngineCommand::Position { position, moves } => {
+                let mut line = match position {
+                    Some(position) => format!("position {}", position.to_notation()),
+                    None => "position startpos".to_string(),
+                };
+                if !moves.is_empty() {
+                    line += " moves";
+                    for hole_num in moves {
+                        line += &format!(" {}", hole_num);
+                    }
+                }
+                line
+            }
+            EngineCommand::Go { limits, is_infinite } => {
+                let mut line = "go".to_string();
+                if limits.depth != MAX_DEPTH {
+                    line += &format!(" depth {}", limits.depth);
+                }
+                if let Some(move_time) = limits.move_time {
+                    line += &format!(" movetime {}", move_time.as_millis());
+                }
+                if let Some(nodes) = limits.nodes {
+                    line += &format!(" nodes {}", nodes);
+                }
+                if *is_infinite {
+                    line += " infinite";
+                }
+                line
+            }
+            EngineCommand::Stop => "stop".to_string(),
+            EngineCommand::Quit => "quit".to_string(),
+        }
+    }
+
+    fn parse_new_game<'a>(tokens: impl Iterator<Item = &'a str>) -> Result<GameConfig, String> {
+        let (mut hole_nums, mut stone_nums_in_hole, mut is_check_pipe_rule) = (None, None, None);
+
+        for token in tokens {
+            let (key, value) = token.split_once('=').ok_or_else(|| format!("Expected key=value in newgame (got '{}')", token))?;
+            let invalid = || format!("Invalid newgame {} '{}'", key, value);
+            match key {
+                "holes" => hole_nums = Some(value.parse::<usize>().map_err(|_| invalid())?),
+                "stones" => stone_nums_in_hole = Some(value.parse::<usize>().map_err(|_| invalid())?),
+                "pie" => is_check_pipe_rule = Some(match value {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(invalid()),
+                }),
+                _ => return Err(format!("Unknown newgame key '{}'", key)),
+            }
+        }
+
+        match (hole_nums, stone_nums_in_hole, is_check_pipe_rule) {
+            (Some(hole_nums), Some(stone_nums_in_hole), Some(is_check_pipe_rule)) => {
+                Ok(GameConfig::build(stone_nums_in_hole, hole_nums, is_check_pipe_rule)?)
+            }
+            _ => Err("newgame needs holes, stones and pie".into()),
+        }
+    }
+
+    fn parse_position<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<EngineCommand, String> {
+        let position = match tokens.next() {
+            Some("startpos") => None,
+            Some(board) => {
+                let rest = [tokens.next(), tokens.next()];
+                let [Some(to_move), Some(total_turns)] = rest else {
+                    return Err("position needs startpos or a position in notation".into());
+                };
+                Some(Position::parse(&format!("{} {} {}", board, to_move, total_turns))?)
+            }
+            None => return Err("position needs startpos or a position in notation".into()),
+        };
+
+        let moves = match tokens.next() {
+            Some("moves") => tokens
+                .map(|token| token.parse::<usize>().map_err(|_| format!("Invalid move '{}'", token)))
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(token) => return Err(format!("Expected moves after the position (got '{}')", token)),
+            None => Vec::new(),
+        };
+
+        Ok(EngineCommand::Position { position, moves })
+    }
+
+    fn parse_go<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<EngineCommand, String> {
+        let mut limits = SearchLimits::depth(MAX_DEPTH);
+        let mut is_infinite = false;
+
+        while let Some(token) = tokens.next() {
+            if token == "infinite" {
+                is_infinite = true;
+                continue;
+            }
+
+            let value = tokens.next().ok_or_else(|| format!("go {} needs a value", token))?;
+            let invalid = || format!("Invalid go {} '{}'", token, value);
+            match token {
+                "depth" => limits.depth = value.parse().ok().filter(|&depth| depth > 0 && depth <= MAX_DEPTH).ok_or_else(invalid)?,
+                "movetime" => limits.move_time = Some(Duration::from_millis(value.parse().map_err(|_| invalid())?)),
+                "nodes" => limits.nodes = Some(value.parse().map_err(|_| invalid())?),
+                _ => return Err(format!("Unknown go limit '{}'", token)),
+            }
+        }
+
+        Ok(EngineCommand::Go { limits, is_infinite })
+    }
+}
+
+impl EngineReply {
+
+    pub fn parse(line: &str) -> Result<EngineReply, String> {
+        let line = line.trim();
+        let (reply, rest) = line.split_once(' ').unwrap_or((line, ""));
+
+        match reply {
+            "id" => match rest.split_once(' ') {
+                Some(("name", name)) => Ok(EngineReply::Id { name: name.trim().to_string() }),
+                _ => Err(format!("Invalid id line '{}'", line)),
+            },
+            "kalahok" => Ok(EngineReply::KalahOk),
+            "readyok" => Ok(EngineReply::ReadyOk),
+            "info" => Self::parse_info(rest),
+            "bestmove" => match rest.trim() {
+                "none" => Ok(EngineReply::BestMove(None)),
+                hole_num => hole_num.parse().map(|hole_num| EngineReply::BestMove(Some(hole_num)))
+                    .map_err(|_| format!("Invalid bestmove '{}'", hole_num)),
+            },
+            "error" => Ok(EngineReply::Error(rest.trim().to_string())),
+            _ => Err(format!("Unknown reply '{}'", reply)),
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        match self {
+            EngineReply::Id { name } => format!("id name {}", name),
+            EngineReply::KalahOk => "kalahok".to_string(),
+            EngineReply::ReadyOk => "readyok".to_string(),
+            EngineReply::Info { result, time } => {
+                let mut line = format!("info depth {} score {}", result.depth, result.value);
+                if let Some(hole_num) = result.best_move {
+                    line += &format!(" move {}", hole_num);
+                }
+                line += &format!(" nodes {} time {}", result.stats.nodes, time.as_millis());
+                if result.is_exact {
+                    line += " exact";
+                }
+                if result.is_book_move {
+                    line += " book";
+                }
+                line
+            }
+            EngineReply::BestMove(Some(hole_num)) => format!("bestmove {}", hole_num),
+            EngineReply::BestMove(None) => "bestmove none".to_string(),
+            EngineReply::Error(message) => format!("error {}", message),
+        }
+    }
+
+    /// Unknown tokens are skipped, so other engines may send more than we read.
+    fn parse_info(rest: &str) -> Result<EngineReply, String> {
+        let mut result = SearchResult {
+            best_move: None,
+            value: 0,
+            depth: 0,
+            is_exact: false,
+            is_book_move: false,
+            stats: SearchStats::default(),
+        };
+        let mut time = Duration::ZERO;
+
+        let mut tokens = rest.split_whitespace();
+        while let Some(token) = tokens.next() {
+            let mut value = || tokens.next().ok_or_else(|| format!("info {} needs a value", token));
+            let invalid = |value: &str| format!("Invalid info {} '{}'", token, value);
+            match token {
+                "depth" => { let value = value()?; result.depth = value.parse().map_err(|_| invalid(value))? }
+                "score" => { let value = value()?; result.value = value.parse().map_err(|_| invalid(value))? }
+                "move" => { let value = value()?; result.best_move = Some(value.parse().map_err(|_| invalid(value))?) }
+                "nodes" => { let value = value()?; result.stats.nodes = value.parse().map_err(|_| invalid(value))? }
+                "time" => { let value = value()?; time = Duration::from_millis(value.parse().map_err(|_| invalid(value))?) }
+                "exact" => result.is_exact = true,
+                "book" => result.is_book_move = true,
+                _ => {}
+            }
+        }
+
+        Ok(EngineReply::Info { result, time })
+    }
+}
+
+/// Engine side of the protocol: keeps the game configuration and position sent by the GUI
+/// and runs `go` on a `BackgroundSearch`, so `stop` is heard while the engine thinks.
+pub struct EngineSession<E: Engine> {
+    name: String,
+    engine: Option<E>,
+    search: Option<BackgroundSearch<E>>,
+    /// The running `go infinite` answers only after `stop`.
+    is_infinite: bool,
+    /// Best move of an infinite search that ended before `stop`.
+    held_best_move: Option<Option<usize>>,
+    game_config: GameConfig,
+    board: CompactBoard,
+}
+
+impl<E: Engine> EngineSession<E> {
+
+    /// Session starting from the start position of `game_config` until a `newgame`.
+    pub fn build(name: String, engine: E, game_config: GameConfig) -> EngineSession<E> {
+        let board = CompactBoard::build(&game_config);
+        EngineSession { name, engine: Some(engine), search: None, is_infinite: false, held_best_move: None, game_config, board }
+    }
+
+    pub fn board(&self) -> &CompactBoard {
+        &self.board
+    }
+
+    /// True from `go` until its `bestmove`.
+    pub fn is_searching(&self) -> bool {
+        self.search.is_some() || self.held_best_move.is_some()
+    }
+
+    /// Handles one command line, pushing its replies. Returns false after `quit`.
+    pub fn handle_line(&mut self, line: &str, replies: &mut Vec<EngineReply>) -> bool {
+        if line.trim().is_empty() {
+            return true;
+        }
+
+        match EngineCommand::parse(line).and_then(|command| self.handle_command(command, replies)) {
+            Ok(is_running) => is_running,
+            Err(err) => {
+                replies.push(EngineReply::Error(err));
+                true
+            }
+        }
+    }
+
+    /// Forwards finished iterations of a running search and its `bestmove`.
+    pub fn poll(&mut self, replies: &mut Vec<EngineReply>) {
+        while let Some(event) = self.search.as_ref().and_then(|search| search.try_next()) {
+            self.forward(event, replies);
+        }
+    }
+
+    /// Blocks until a running search is over. An infinite search keeps its `bestmove` until `stop`.
+    pub fn wait(&mut self, replies: &mut Vec<EngineReply>) {
+        while let Some(event) = self.search.as_ref().and_then(|search| search.next()) {
+            self.forward(event, replies);
+        }
+    }
+
+    /// Speaks the protocol over `input` and `output` until `quit`. At the end of the input a
+    /// running search still finishes and sends its `bestmove`.
+    pub fn run(mut self, input: impl BufRead + Send + 'static, output: &mut impl Write) -> io::Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in input.lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut replies = Vec::new();
+        loop {
+            let is_running = match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => self.handle_line(&line?, &mut replies),
+                Err(RecvTimeoutError::Timeout) => true,
+                Err(RecvTimeoutError::Disconnected) => {
+                    // Nothing is left to stop an infinite search
+                    if self.is_infinite {
+                        self.stop(&mut replies);
+                    }
+                    self.wait(&mut replies);
+                    false
+                }
+            };
+            self.poll(&mut replies);
+
+            for reply in replies.drain(..) {
+                writeln!(output, "{}", reply.to_line())?;
+            }
+            output.flush()?;
+
+            if !is_running {
+                return Ok(());
+            }
+        }
+    }
+
+    fn handle_command(&mut self, command: EngineCommand, replies: &mut Vec<EngineReply>) -> Result<bool, String> {
+        let is_busy = || format!("Cannot handle '{}' while searching", command.to_line());
+
+        match &command {
+            EngineCommand::Kalah => {
+                replies.push(EngineReply::Id { name: self.name.clone() });
+                replies.push(EngineReply::KalahOk);
+            }
+            EngineCommand::IsReady => replies.push(EngineReply::ReadyOk),
+            EngineCommand::NewGame(game_config) => {
+                if self.is_searching() {
+                    return Err(is_busy());
+                }
+                self.game_config = game_config.clone();
+                self.board = CompactBoard::build(game_config);
+            }
+            EngineCommand::Position { position, moves } => {
+                if self.is_searching() {
+                    return Err(is_busy());
+                }
+                let mut board = match position {
+                    Some(position) => {
+                        // The book, endgame database and evaluation were set up for the board of newgame
+                        let hole_nums = self.game_config.hole_nums;
+                        let stones = position.total_stones().ok_or("Position has too many stones")?;
+                        let expected_stones = 2 * hole_nums * self.game_config.stone_nums_in_hole;
+                        if position.side_one.len() != hole_nums || stones != expected_stones {
+                            return Err(format!(
+                                "Position has {} holes and {} stones, the game has {} and {}",
+                                position.side_one.len(), stones, hole_nums, expected_stones
+                            ));
+                        }
+                        CompactBoard::from_position(position, self.game_config.is_check_pipe_rule)?
+                    }
+                    None => CompactBoard::build(&self.game_config),
+                };
+                for (index, &hole_num) in moves.iter().enumerate() {
+                    board.apply_move(hole_num).map_err(|err| format!("Move {} ({}) is illegal: {}", index + 1, hole_num, err))?;
+                }
+                self.board = board;
+            }
+            EngineCommand::Go { limits, is_infinite } => {
+                if self.is_searching() {
+                    return Err(is_busy());
+                }
+                let engine = self.engine.take().ok_or_else(is_busy)?;
+                self.is_infinite = *is_infinite;
+                self.search = Some(BackgroundSearch::start(engine, self.board, *limits));
+            }
+            EngineCommand::Stop => self.stop(replies),
+            EngineCommand::Quit => {
+                self.held_best_move = None;
+                if let Some(search) = self.search.take() {
+                    search.stop();
+                    self.engine = Some(search.join());
+                }
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Stops a running search, answering at once for an infinite one that is already over.
+    fn stop(&mut self, replies: &mut Vec<EngineReply>) {
+        self.is_infinite = false;
+        if let Some(search) = &self.search {
+            search.stop();
+        }
+        if let Some(best_move) = self.held_best_move.take() {
+            replies.push(EngineReply::BestMove(best_move));
+        }
+    }
+
+    fn forward(&mut self, event: SearchEvent, replies: &mut Vec<EngineReply>) {
+        let time = self.search.as_ref().map_or(Duration::ZERO, |search| search.elapsed());
+
+        match event {
+            SearchEvent::Iteration(result) => replies.push(EngineReply::Info { result, time }),
+            SearchEvent::Finished(result) => {
+                let search = self.search.take().expect("events come from a running search");
+                self.engine = Some(search.join());
+                if self.is_infinite {
+                    self.held_best_move = Some(result.best_move);
+                } else {
+                    replies.push(EngineReply::BestMove(result.best_move));
+                }
+            }
+        }
+    }
+}
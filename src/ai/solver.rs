@@ -0,0 +1,351 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::time::Instant;
+
+use super::alpha_beta::{ AlphaBeta, SearchLimits, SearchResult, SearchStats, UNLIMITED_DEPTH };
+use super::compact_board::CompactBoard;
+use super::endgame::EndgameDatabase;
+use super::engine::Engine;
+use super::transposition_table::{ Bound, ReplacementPolicy, TableEntry, TranspositionTable };
+
+pub const DEFAULT_SOLVER_TABLE_SIZE: usize = 1 << 24;
+
+/// Depth of the `AlphaBeta` search giving MTD(f) its first guess.
+const GUESS_DEPTH: u8 = 10;
+
+/// How often (in nodes) the stop flag is checked.
+const STOP_CHECK_INTERVAL: u64 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Solution {
+    pub best_move: Option<usize>,
+    /// Final store difference with perfect play, from the point of view of the player to move.
+    pub value: i32,
+    /// Zero-window searches MTD(f) needed to converge.
+    pub passes: u32,
+    pub stats: SearchStats,
+}
+
+impl Solution {
+
+    /// Final store difference with perfect play from player one's point of view.
+    pub fn player_one_value(&self, board: &CompactBoard) -> i32 {
+        if board.is_player_one_turn { self.value } else { -self.value }
+    }
+}
+
+/// Game-theoretic outcome from the first player's point of view, e.g. "First player wins by 10".
+pub struct Verdict(pub i32);
+
+impl fmt::Display for Verdict {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => write!(f, "Draw"),
+            value if value > 0 => write!(f, "First player wins by {}", value),
+            value => write!(f, "Second player wins by {}", -value),
+        }
+    }
+}
+
+/// Exact solver: MTD(f) over zero-window alpha-beta searches without a depth limit.
+/// Every table entry is exact for the rest of the game, so entries are stored at
+/// `UNLIMITED_DEPTH` and the table can be shared with `AlphaBeta`.
+pub struct Solver {
+    pub table: Arc<TranspositionTable>,
+    endgame: Option<Arc<EndgameDatabase>>,
+    stats: SearchStats,
+    stop_flag: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    is_aborted: bool,
+    /// Move of the last fail-high pass, at least as good as the value proven so far.
+    proven_move: Option<usize>,
+    /// Depth-limited search giving MTD(f) its first guess, played when there is no time to solve.
+    guess: Option<SearchResult>,
+    total_stones: i32,
+}
+
+impl Solver {
+
+    pub fn build(table_size: usize) -> Solver {
+        Self::with_table(Arc::new(TranspositionTable::build(table_size, ReplacementPolicy::AlwaysReplace)))
+    }
+
+    pub fn with_table(table: Arc<TranspositionTable>) -> Solver {
+        Solver {
+            table,
+            endgame: None,
+            stats: SearchStats::default(),
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            deadline: None,
+            node_limit: None,
+            is_aborted: false,
+            proven_move: None,
+            guess: None,
+            total_stones: 0,
+        }
+    }
+
+    /// Positions in the database are scored exactly instead of searched.
+    pub fn with_endgame(mut self, endgame: Arc<EndgameDatabase>) -> Solver {
+        self.endgame = Some(endgame);
+        self
+    }
+
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop_flag)
+    }
+
+    pub fn reset_stop(&self) {
+        self.stop_flag.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns None if the stop flag was set before the position was solved.
+    pub fn solve(&mut self, board: &CompactBoard) -> Option<Solution> {
+        self.stats = SearchStats::default();
+        self.is_aborted = false;
+        self.proven_move = None;
+        self.guess = None;
+
+        let solution = self.mtdf(board);
+        self.reset_stop();
+        solution
+    }
+
+    fn mtdf(&mut self, board: &CompactBoard) -> Option<Solution> {
+        self.total_stones = board.stores.iter().map(|&count| count as i32).sum::<i32>()
+            + board.holes.iter().flatten().map(|&count| count as i32).sum::<i32>();
+
+        let (mut lower, mut upper) = self.value_bounds(board);
+
+        // A shallow search over the same table gives the first guess and fills in move ordering
+        let mut alpha_beta = AlphaBeta::with_table(Arc::clone(&self.table)).with_stop_flag(Arc::clone(&self.stop_flag));
+        if let Some(endgame) = &self.endgame {
+            alpha_beta = alpha_beta.with_endgame(Arc::clone(endgame));
+        }
+        let guess_limits = SearchLimits {
+            depth: GUESS_DEPTH,
+            move_time: self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now())),
+            nodes: self.node_limit,
+        };
+        let estimate = alpha_beta.iterative_deepening(board, &guess_limits, |_| {});
+        self.guess = Some(estimate);
+        if alpha_beta.is_aborted() {
+            self.is_aborted = true;
+            return None;
+        }
+        let mut guess = estimate.value.clamp(lower, upper);
+        let mut passes = 0;
+
+        while lower < upper {
+            let beta = if guess == lower { guess + 1 } else { guess };
+            let (value, hole_num) = self.search(board, beta - 1, beta);
+            if self.is_aborted {
+                return None;
+            }
+            passes += 1;
+
+            guess = value;
+            if value < beta {
+                upper = value;
+            } else {
+                lower = value;
+                self.proven_move = hole_num;
+            }
+        }
+
+        // A position worth exactly its lower bound never fails high, and then every move is as good
+        Some(Solution {
+            best_move: self.proven_move.or_else(|| board.legal_moves().next()).filter(|_| !board.is_game_over()),
+            value: lower,
+            passes,
+            stats: self.stats,
+        })
+    }
+
+    /// Children are probed in the endgame database here, so the root always gets a best move.
+    fn child_value(&mut self, board: &CompactBoard, child: &CompactBoard, alpha: i32, beta: i32) -> i32 {
+        let is_same_mover = child.is_player_one_turn == board.is_player_one_turn;
+
+        if let Some(value) = self.endgame.as_ref().and_then(|endgame| endgame.probe(child)) {
+            self.stats.endgame_hits += 1;
+            return if is_same_mover { value } else { -value };
+        }
+
+        if is_same_mover {
+            self.search(child, alpha, beta).0
+        } else {
+            -self.search(child, -beta, -alpha).0
+        }
+    }
+
+    /// Fail-soft alpha-beta returning the value and the move that produced it.
+    fn search(&mut self, board: &CompactBoard, mut alpha: i32, beta: i32) -> (i32, Option<usize>) {
+        self.stats.nodes += 1;
+
+        if self.stats.nodes.is_multiple_of(STOP_CHECK_INTERVAL) {
+            let is_timeout = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            let is_node_limit = self.node_limit.is_some_and(|node_limit| self.stats.nodes >= node_limit);
+            if is_timeout || is_node_limit || self.stop_flag.load(Ordering::Relaxed) {
+                self.is_aborted = true;
+            }
+        }
+        if self.is_aborted {
+            return (0, None);
+        }
+
+        if board.is_game_over() {
+            return (AlphaBeta::evaluate(board), None);
+        }
+
+        let (lower, upper) = self.value_bounds(board);
+        if lower >= beta {
+            return (lower, None);
+        }
+        if upper <= alpha {
+            return (upper, None);
+        }
+
+        let key = board.zobrist_hash();
+        let alpha_orig = alpha;
+        let mut table_move = None;
+
+        self.stats.table.probes += 1;
+        if let Some(entry) = self.table.probe(key) {
+            self.stats.table.hits += 1;
+            table_move = entry.best_move;
+
+            // Entries of a depth-limited `AlphaBeta` search sharing the table only order moves
+            let is_cutoff = entry.depth == UNLIMITED_DEPTH && match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.value >= beta,
+                Bound::Upper => entry.value <= alpha,
+            };
+            if is_cutoff {
+                self.stats.table.cutoffs += 1;
+                return (entry.value, table_move.map(|hole_num| hole_num as usize));
+            }
+        }
+
+        let mut best_value = -i32::MAX;
+        let mut best_move = None;
+
+        for (hole_num, child) in Self::ordered_children(board, table_move) {
+            let value = self.child_value(board, &child, alpha, beta);
+            if self.is_aborted {
+                return (0, None);
+            }
+
+            if value > best_value {
+                best_value = value;
+                best_move = Some(hole_num);
+            }
+
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                self.stats.beta_cutoffs += 1;
+                break;
+            }
+        }
+
+        let bound = if best_value <= alpha_orig {
+            Bound::Upper
+        } else if best_value >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+
+        let is_stored = self.table.store(TableEntry {
+            key,
+            depth: UNLIMITED_DEPTH,
+            value: best_value,
+            bound,
+            best_move: best_move.map(|hole_num| hole_num as u8),
+        });
+        if is_stored {
+            self.stats.table.stores += 1;
+        } else {
+            self.stats.table.rejected_stores += 1;
+        }
+
+        (best_value, best_move)
+    }
+
+    /// Stones in a store stay there, so each player finishes with at least their store.
+    fn value_bounds(&self, board: &CompactBoard) -> (i32, i32) {
+        let own_store = board.stores[board.mover()] as i32;
+        let opponent_store = board.stores[1 - board.mover()] as i32;
+
+        (2 * own_store - self.total_stones, self.total_stones - 2 * opponent_store)
+    }
+
+    /// Table move first, then extra turns, then by stones won into the own store.
+    fn ordered_children(board: &CompactBoard, table_move: Option<u8>) -> Vec<(usize, CompactBoard)> {
+        let mover = board.mover();
+        let mut children = board
+            .legal_moves()
+            .map(|hole_num| {
+                let mut child = *board;
+                child.apply_move(hole_num).expect("legal moves apply");
+                (hole_num, child)
+            })
+            .collect::<Vec<_>>();
+
+        children.sort_by_key(|(hole_num, child)| (
+            Some(*hole_num as u8) != table_move,
+            child.is_player_one_turn != board.is_player_one_turn,
+            board.stores[mover] as i32 - child.stores[mover] as i32,
+        ));
+        children
+    }
+}
+
+/// Plays perfectly within the time and node limits. When they or the stop flag end the
+/// search early, the best move proven so far is played, or else the move of the guess search.
+impl Engine for Solver {
+
+    fn think(&mut self, board: &CompactBoard, limits: &SearchLimits, on_iteration: &mut dyn FnMut(&SearchResult)) -> SearchResult {
+        self.deadline = limits.move_time.map(|move_time| Instant::now() + move_time);
+        self.node_limit = limits.nodes;
+        let solution = self.solve(board);
+        self.deadline = None;
+        self.node_limit = None;
+
+        let result = match solution {
+            Some(solution) => SearchResult {
+                best_move: solution.best_move,
+                value: solution.value,
+                depth: UNLIMITED_DEPTH,
+                is_exact: true,
+                is_book_move: false,
+                stats: solution.stats,
+            },
+            None => SearchResult {
+                best_move: self.proven_move
+                    .or_else(|| self.guess.and_then(|guess| guess.best_move))
+                    .or_else(|| board.legal_moves().next()),
+                value: self.guess.map_or_else(|| AlphaBeta::evaluate(board), |guess| guess.value),
+                depth: self.guess.map_or(0, |guess| guess.depth),
+                is_exact: false,
+                is_book_move: false,
+                stats: self.stats,
+            },
+        };
+
+        if result.is_exact {
+            on_iteration(&result);
+        }
+        result
+    }
+
+    fn stop_handle(&self) -> Arc<AtomicBool> {
+        Solver::stop_handle(self)
+    }
+
+    fn reset_stop(&self) {
+        Solver::reset_stop(self)
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The value is at least `value` (search failed high).
+    Lower,
+    /// The value is at most `value` (search failed low).
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableEntry {
+    pub key: u64,
+    pub depth: u8,
+    pub value: i32,
+    pub bound: Bound,
+    pub best_move: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplacementPolicy {
+    /// The newest entry always wins the slot.
+    AlwaysReplace,
+    /// Keep the deeper entry unless the slot holds the same position.
+    DepthPreferred,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableStats {
+    pub probes: u64,
+    pub hits: u64,
+    pub cutoffs: u64,
+    pub stores: u64,
+    pub rejected_stores: u64,
+}
+
+impl TableStats {
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 { 0.0 } else { self.hits as f64 / self.probes as f64 }
+    }
+
+    pub fn add(&mut self, other: &TableStats) {
+        self.probes += other.probes;
+        self.hits += other.hits;
+        self.cutoffs += other.cutoffs;
+        self.stores += other.stores;
+        self.rejected_stores += other.rejected_stores;
+    }
+}
+
+impl fmt::Display for TableStats {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tt probes {} hits {} ({:.1}%) cutoffs {} stores {} rejected {}",
+            self.probes, self.hits, self.hit_rate() * 100.0, self.cutoffs, self.stores, self.rejected_stores
+        )
+    }
+}
+
+/// One slot stores `key ^ data` next to `data`, so a slot torn by two threads writing
+/// at once fails the key check instead of returning a mixed entry.
+struct Slot {
+    checked_key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Fixed-size table indexed by the low bits of the Zobrist hash. Lock-free, so one
+/// table can be shared between search threads.
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    replacement_policy: ReplacementPolicy,
+}
+
+impl TranspositionTable {
+
+    /// `size` is rounded down to a power of two (at least one entry).
+    pub fn build(size: usize, replacement_policy: ReplacementPolicy) -> TranspositionTable {
+        let size = if size <= 1 { 1 } else { 1 << (usize::BITS - 1 - size.leading_zeros()) };
+
+        TranspositionTable {
+            slots: (0..size).map(|_| Slot { checked_key: AtomicU64::new(0), data: AtomicU64::new(0) }).collect(),
+            replacement_policy,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TableEntry> {
+        let slot = &self.slots[self.index(key)];
+        let data = slot.data.load(Ordering::Relaxed);
+        let checked_key = slot.checked_key.load(Ordering::Relaxed);
+
+        (data != 0 && checked_key ^ data == key).then(|| Self::unpack(key, data))
+    }
+
+    /// Returns false when the replacement policy kept the current entry.
+    pub fn store(&self, entry: TableEntry) -> bool {
+        let slot = &self.slots[self.index(entry.key)];
+
+        if self.replacement_policy == ReplacementPolicy::DepthPreferred {
+            let data = slot.data.load(Ordering::Relaxed);
+            let current_key = slot.checked_key.load(Ordering::Relaxed) ^ data;
+
+            if data != 0 && current_key != entry.key && entry.depth < Self::unpack(current_key, data).depth {
+                return false;
+            }
+        }
+
+        let data = Self::pack(&entry);
+        slot.checked_key.store(entry.key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+        true
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.checked_key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        key as usize & (self.slots.len() - 1)
+    }
+
+    /// Bit 0 marks a used slot, then depth (8 bits), bound (2), best move + 1 (8) and value (32).
+    fn pack(entry: &TableEntry) -> u64 {
+        let bound = match entry.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        let best_move = entry.best_move.map_or(0, |hole_num| hole_num as u64 + 1);
+
+        1 | (entry.depth as u64) << 1 | bound << 9 | best_move << 11 | (entry.value as u32 as u64) << 32
+    }
+
+    fn unpack(key: u64, data: u64) -> TableEntry {
+        let bound = match (data >> 9) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        let best_move = ((data >> 11) & 0xff) as u8;
+
+        TableEntry {
+            key,
+            depth: (data >> 1) as u8,
+            value: (data >> 32) as u32 as i32,
+            bound,
+            best_move: best_move.checked_sub(1),
+        }
+    }
+}
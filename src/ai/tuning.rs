@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use crate::game::game_config::GameConfig;
+
+use super::alpha_beta::AlphaBeta;
+use super::compact_board::CompactBoard;
+use super::evaluation::{ features, Evaluator, Features, LinearEvaluator, FEATURE_COUNT };
+use super::transposition_table::ReplacementPolicy;
+
+const SELF_PLAY_TABLE_SIZE: usize = 1 << 18;
+
+/// Smallest step the tuner tries before it gives up.
+const MIN_STEP: f64 = 1.0 / 64.0;
+
+/// A position seen in self-play with the outcome for the player to move then:
+/// 1 for a win, 0.5 for a draw and 0 for a loss.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrainingPosition {
+    pub features: Features,
+    pub result: f64,
+}
+
+/// Plays `games` games from the start position, the first `random_plies` plies at random and
+/// the rest with a `depth` search using `evaluator`, and returns every position after the
+/// pie rule could swap sides.
+pub fn self_play_positions(
+    game_config: &GameConfig,
+    games: usize,
+    random_plies: usize,
+    depth: u8,
+    evaluator: Arc<dyn Evaluator>,
+    rng: &mut impl Rng,
+) -> Vec<TrainingPosition> {
+    let mut alpha_beta = AlphaBeta::build(SELF_PLAY_TABLE_SIZE, ReplacementPolicy::DepthPreferred).with_evaluator(evaluator);
+    let mut positions = Vec::new();
+
+    for _ in 0..games {
+        let mut board = CompactBoard::build(game_config);
+        let mut seen = Vec::new();
+
+        while !board.is_game_over() {
+            if !board.is_check_pipe_rule || board.total_turns > 0 {
+                seen.push((features(&board), board.mover()));
+            }
+
+            let hole_num = if (board.total_turns as usize) < random_plies {
+                *board.legal_moves().collect::<Vec<_>>().choose(rng).expect("a running game has a legal move")
+            } else {
+                alpha_beta.search(&board, depth).best_move.expect("a running game has a best move")
+            };
+            board.apply_move(hole_num).expect("chosen moves are legal");
+        }
+
+        positions.extend(seen.into_iter().map(|(features, mover)| {
+            let store_diff = board.stores[mover] as i32 - board.stores[1 - mover] as i32;
+            TrainingPosition { features, result: (store_diff.signum() as f64 + 1.0) / 2.0 }
+        }));
+    }
+
+    positions
+}
+
+/// Fits `LinearEvaluator` weights to self-play outcomes Texel-style: the evaluation mapped
+/// through a sigmoid predicts the result, and hill climbing lowers the mean squared error.
+/// The store weight stays fixed, it sets the scale the other weights are measured in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tuner {
+    /// Evaluation (in stones) at which the predicted result is about 0.73.
+    pub scale: f64,
+    /// First change tried for each weight, halved whenever a pass finds no improvement.
+    pub step: f64,
+    pub passes: usize,
+}
+
+impl Default for Tuner {
+
+    fn default() -> Tuner {
+        Tuner { scale: 4.0, step: 0.5, passes: 50 }
+    }
+}
+
+impl Tuner {
+
+    pub fn error(&self, evaluator: &LinearEvaluator, positions: &[TrainingPosition]) -> f64 {
+        let total = positions
+            .iter()
+            .map(|position| {
+                let predicted = 1.0 / (1.0 + (-evaluator.score(&position.features) / self.scale).exp());
+                (position.result - predicted).powi(2)
+            })
+            .sum::<f64>();
+
+        total / positions.len().max(1) as f64
+    }
+
+    /// Returns the weights with the lowest error found, calling `on_pass` with the pass number
+    /// and error after each pass.
+    pub fn tune(
+        &self,
+        mut evaluator: LinearEvaluator,
+        positions: &[TrainingPosition],
+        mut on_pass: impl FnMut(usize, f64),
+    ) -> LinearEvaluator {
+        let mut best_error = self.error(&evaluator, positions);
+        let mut step = self.step;
+
+        for pass in 1..=self.passes {
+            let mut is_improved = false;
+
+            for index in 1..FEATURE_COUNT {
+                for delta in [step, -step] {
+                    let mut candidate = evaluator;
+                    candidate.weights[index] += delta;
+
+                    let error = self.error(&candidate, positions);
+                    if error < best_error {
+                        best_error = error;
+                        evaluator = candidate;
+                        is_improved = true;
+                        break;
+                    }
+                }
+            }
+
+            on_pass(pass, best_error);
+
+            if !is_improved {
+                step /= 2.0;
+                if step < MIN_STEP {
+                    break;
+                }
+            }
+        }
+
+        evaluator
+    }
+}
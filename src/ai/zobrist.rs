@@ -0,0 +1,86 @@
+use std::sync::OnceLock;
+
+use super::compact_board::{ CompactBoard, MAX_HOLES };
+
+const COUNTS: usize = u8::MAX as usize + 1;
+
+/// Fixed seed so hashes are stable between runs and can be stored in files.
+const SEED: u64 = 0x4b61_6c61_685f_5a6f;
+
+pub struct ZobristKeys {
+    holes: [[[u64; COUNTS]; MAX_HOLES]; 2],
+    stores: [[u64; COUNTS]; 2],
+    player_two_to_move: u64,
+    pie_rule_pending: u64,
+}
+
+impl ZobristKeys {
+
+    fn build() -> ZobristKeys {
+        let mut state = SEED;
+        let mut next = || {
+            // splitmix64
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+
+        let mut keys = ZobristKeys {
+            holes: [[[0; COUNTS]; MAX_HOLES]; 2],
+            stores: [[0; COUNTS]; 2],
+            player_two_to_move: 0,
+            pie_rule_pending: 0,
+        };
+
+        for side in keys.holes.iter_mut() {
+            for hole in side.iter_mut() {
+                hole.iter_mut().for_each(|key| *key = next());
+            }
+        }
+        for store in keys.stores.iter_mut() {
+            store.iter_mut().for_each(|key| *key = next());
+        }
+        keys.player_two_to_move = next();
+        keys.pie_rule_pending = next();
+
+        keys
+    }
+
+    pub fn get() -> &'static ZobristKeys {
+        static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+        KEYS.get_or_init(ZobristKeys::build)
+    }
+
+    pub fn hash(&self, board: &CompactBoard) -> u64 {
+        let mut hash = 0;
+
+        for (side, side_keys) in board.holes.iter().zip(self.holes.iter()) {
+            for (&count, hole_keys) in side.iter().zip(side_keys.iter()) {
+                hash ^= hole_keys[count as usize];
+            }
+        }
+        for (&count, store_keys) in board.stores.iter().zip(self.stores.iter()) {
+            hash ^= store_keys[count as usize];
+        }
+
+        if !board.is_player_one_turn {
+            hash ^= self.player_two_to_move;
+        }
+
+        // The first move can still trigger the pie rule swap
+        if board.is_check_pipe_rule && board.total_turns == 0 {
+            hash ^= self.pie_rule_pending;
+        }
+
+        hash
+    }
+}
+
+impl CompactBoard {
+
+    pub fn zobrist_hash(&self) -> u64 {
+        ZobristKeys::get().hash(self)
+    }
+}
@@ -0,0 +1,96 @@
+pub mod analysis;
+pub mod engine;
+pub mod generate;
+pub mod net;
+pub mod play;
+
+use std::sync::Arc;
+
+use kalah::ai::alpha_beta::{ AlphaBeta, DEFAULT_TABLE_SIZE };
+use kalah::ai::difficulty::{ Level, LevelEngine };
+use kalah::ai::endgame::EndgameDatabase;
+use kalah::ai::engine::Engine;
+use kalah::ai::evaluation::{ Evaluator, LinearEvaluator, StoreDiff };
+use kalah::ai::opening_book::{ BookEngine, OpeningBook };
+use kalah::ai::solver::{ Solver, DEFAULT_SOLVER_TABLE_SIZE };
+use kalah::ai::transposition_table::ReplacementPolicy;
+use kalah::game::game_config::GameConfig;
+
+use crate::{ exit_with_error, Options };
+
+/// Evaluation weights from `--weights`, the store difference without.
+pub fn load_evaluator(options: &Options) -> Arc<dyn Evaluator> {
+    match &options.weights_path {
+        Some(path) => Arc::new(LinearEvaluator::load(path).unwrap_or_else(|err| exit_with_error(&err))),
+        None => Arc::new(StoreDiff),
+    }
+}
+
+/// The `--endgame` database, which must be for the holes of the game.
+pub fn load_endgame(options: &Options, game_config: &GameConfig) -> Option<Arc<EndgameDatabase>> {
+    options.endgame_path.as_ref().map(|path| {
+        let endgame = EndgameDatabase::load(path).unwrap_or_else(|err| exit_with_error(&err));
+        if endgame.hole_nums() != game_config.hole_nums {
+            exit_with_error(&format!("{} is for {} holes, the game has {}", path, endgame.hole_nums(), game_config.hole_nums));
+        }
+        Arc::new(endgame)
+    })
+}
+
+/// The `--book` opening book, which must be for the board of the game.
+pub fn load_book(options: &Options, game_config: &GameConfig) -> Option<Arc<OpeningBook>> {
+    options.book_path.as_ref().map(|path| {
+        let book = OpeningBook::load(path).unwrap_or_else(|err| exit_with_error(&err));
+        if book.hole_nums != game_config.hole_nums
+            || book.stone_nums_in_hole != game_config.stone_nums_in_hole
+            || book.is_check_pipe_rule != game_config.is_check_pipe_rule
+        {
+            exit_with_error(&format!("{} is for another board configuration", path));
+        }
+        Arc::new(book)
+    })
+}
+
+/// Engine playing at `level` with the search options from the command line, playing
+/// from `book` while it has moves.
+pub fn build_engine(
+    options: &Options,
+    level: Level,
+    endgame: Option<&Arc<EndgameDatabase>>,
+    evaluator: &Arc<dyn Evaluator>,
+    book: Option<&Arc<OpeningBook>>,
+) -> Box<dyn Engine> {
+    let engine: Box<dyn Engine> = if level == Level::Perfect {
+        let solver = Solver::build(DEFAULT_SOLVER_TABLE_SIZE);
+        Box::new(match endgame {
+            Some(endgame) => solver.with_endgame(Arc::clone(endgame)),
+            None => solver,
+        })
+    } else {
+        let alpha_beta = build_alpha_beta(options, endgame, evaluator);
+        match level.settings() {
+            Some(settings) => Box::new(LevelEngine::build(alpha_beta, settings)),
+            None => Box::new(alpha_beta),
+        }
+    };
+
+    match book {
+        Some(book) => Box::new(BookEngine::build(Arc::clone(book), engine).with_randomness(options.book_randomness)),
+        None => engine,
+    }
+}
+
+pub fn build_alpha_beta(options: &Options, endgame: Option<&Arc<EndgameDatabase>>, evaluator: &Arc<dyn Evaluator>) -> AlphaBeta {
+    let alpha_beta = AlphaBeta::build(DEFAULT_TABLE_SIZE, ReplacementPolicy::DepthPreferred)
+        .with_threads(options.threads)
+        .with_evaluator(Arc::clone(evaluator));
+
+    match endgame {
+        Some(endgame) => alpha_beta.with_endgame(Arc::clone(endgame)),
+        None => alpha_beta,
+    }
+}
+
+pub fn engine_name(level: Level) -> String {
+    format!("kalah {} ({})", env!("CARGO_PKG_VERSION"), level.name())
+}
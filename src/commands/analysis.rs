@@ -0,0 +1,54 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use kalah::ai::analysis::{ analyze_game, annotate };
+use kalah::ai::endgame::EndgameDatabase;
+use kalah::ai::evaluation::Evaluator;
+use kalah::ai::opening_book::search_move_values;
+use kalah::game::game_record::GameRecord;
+
+use crate::tui::review::{ class_summary, run_review };
+use crate::tui::{ end_terminal, start_terminal };
+use crate::{ exit_with_error, Options };
+use super::build_alpha_beta;
+
+pub const DEFAULT_ANALYSIS_DEPTH: u8 = 10;
+
+/// Analyzes the record given to `analyze`, writes the annotated record to `--output` (or stdout)
+/// and opens the review screen with `--review`. Progress and the summary go to stderr.
+pub fn analyze(options: &Options, endgame: Option<Arc<EndgameDatabase>>, evaluator: Arc<dyn Evaluator>) -> Result<(), Box<dyn Error>> {
+    let input_path = options.input_path.as_deref().expect("analyze has a record file");
+    let record = std::fs::read_to_string(input_path)
+        .map_err(|err| format!("Cannot read '{}': {}", input_path, err))
+        .and_then(|text| GameRecord::parse(&text).map_err(|err| format!("{}: {}", input_path, err)))
+        .unwrap_or_else(|err| exit_with_error(&err));
+
+    let mut alpha_beta = build_alpha_beta(options, endgame.as_ref(), &evaluator);
+    let depth = options.depth.unwrap_or(DEFAULT_ANALYSIS_DEPTH);
+    let analyses = analyze_game(
+        &record,
+        |board| search_move_values(&mut alpha_beta, board, depth),
+        |done, total| eprint!("\rAnalyzed {}/{} moves", done, total),
+    )
+    .unwrap_or_else(|err| exit_with_error(&err));
+    eprintln!();
+
+    for (is_player_one, name) in [(true, &record.player_one_name), (false, &record.player_two_name)] {
+        eprintln!("{}: {}", name, class_summary(&analyses, is_player_one));
+    }
+
+    let annotated = annotate(&record, &analyses);
+    match &options.output_path {
+        Some(path) => annotated.save(path).unwrap_or_else(|err| exit_with_error(&err)),
+        None => print!("{}", annotated.to_text()),
+    }
+
+    if options.is_review {
+        let mut terminal = start_terminal()?;
+        let res = run_review(&mut terminal, &annotated, &analyses);
+        end_terminal(&mut terminal)?;
+        res?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use std::error::Error;
+use std::io::{ self, BufReader };
+use std::sync::Arc;
+
+use kalah::ai::alpha_beta::SearchLimits;
+use kalah::ai::compact_board::CompactBoard;
+use kalah::ai::difficulty::Level;
+use kalah::ai::endgame::EndgameDatabase;
+use kalah::ai::engine::Engine;
+use kalah::ai::external_engine::ExternalEngine;
+use kalah::ai::match_runner::{ EngineController, MatchRunner, PlayerController };
+use kalah::ai::protocol::EngineSession;
+use kalah::ai::solver::{ Solver, Verdict, DEFAULT_SOLVER_TABLE_SIZE };
+use kalah::game::game_config::GameConfig;
+
+use crate::{ exit_with_error, Options };
+use super::engine_name;
+
+const DEFAULT_MATCH_PLIES: usize = 2;
+const DEFAULT_MATCH_GAMES: usize = 10;
+
+/// Speaks the engine protocol on stdin and stdout until `quit` or the end of the input.
+/// The command-line board configuration applies until the first `newgame`.
+pub fn run_engine(level: Level, engine: Box<dyn Engine>, game_config: GameConfig) -> Result<(), Box<dyn Error>> {
+    EngineSession::build(engine_name(level), engine, game_config).run(BufReader::new(io::stdin()), &mut io::stdout())?;
+    Ok(())
+}
+
+/// Plays `--games` games between our engine at `level` and the engine started by the match
+/// command, printing every result and the final score.
+pub fn play_match(options: &Options, game_config: &GameConfig, level: Level, engine: Box<dyn Engine>) -> Result<(), Box<dyn Error>> {
+    let engine_command = options.engine_command.as_deref().expect("match has an engine command");
+    let limits = SearchLimits::move_time(options.move_time);
+    let mut external = ExternalEngine::spawn(engine_command)
+        .unwrap_or_else(|err| exit_with_error(&err))
+        .with_limits(limits)
+        .with_timeout(options.timeout);
+    let mut internal = EngineController::build(engine_name(level), engine, limits);
+    let names = [internal.name().to_string(), external.name().to_string()];
+
+    let runner = MatchRunner {
+        games: options.games.unwrap_or(DEFAULT_MATCH_GAMES),
+        random_plies: options.plies.unwrap_or(DEFAULT_MATCH_PLIES),
+    };
+    let score = runner.run(game_config, [&mut internal, &mut external], &mut rand::rng(), |game, result| {
+        let player_one = &names[result.player_one];
+        let player_two = &names[1 - result.player_one];
+        print!("Game {}: {} {} - {} {}", game, player_one, result.stores[0], result.stores[1], player_two);
+        match &result.forfeit {
+            Some((_, reason)) => println!(", forfeit: {}", reason),
+            None => println!(),
+        }
+    });
+
+    println!("Score: {} {}, {} {}, draws {}", names[0], score.wins[0], names[1], score.wins[1], score.draws);
+    Ok(())
+}
+
+/// Prints the game-theoretic value of the start (or `--position`) position.
+pub fn solve(options: &Options, game_config: &GameConfig, endgame: Option<Arc<EndgameDatabase>>) -> Result<(), Box<dyn Error>> {
+    let board = match &options.position {
+        Some(position) => CompactBoard::from_position(position, game_config.is_check_pipe_rule)?,
+        None => CompactBoard::build(game_config),
+    };
+
+    let mut solver = Solver::build(DEFAULT_SOLVER_TABLE_SIZE);
+    if let Some(endgame) = endgame {
+        solver = solver.with_endgame(endgame);
+    }
+    let solution = solver.solve(&board).expect("nothing stops the solver");
+
+    println!("{}", Verdict(solution.player_one_value(&board)));
+    if let Some(hole_num) = solution.best_move {
+        println!("Best move: {}", hole_num);
+    }
+    println!("MTD(f) passes {} {}", solution.passes, solution.stats);
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use kalah::ai::alpha_beta::{ AlphaBeta, DEFAULT_TABLE_SIZE };
+use kalah::ai::compact_board::CompactBoard;
+use kalah::ai::endgame::EndgameDatabase;
+use kalah::ai::evaluation::{ Evaluator, LinearEvaluator };
+use kalah::ai::opening_book::{ search_move_values, solver_move_values, BookBuilder, OpeningBook };
+use kalah::ai::solver::{ Solver, DEFAULT_SOLVER_TABLE_SIZE };
+use kalah::ai::transposition_table::ReplacementPolicy;
+use kalah::ai::tuning::{ self_play_positions, Tuner };
+use kalah::game::game_config::GameConfig;
+
+use crate::{ exit_with_error, BookSource, Options };
+
+const DEFAULT_BOOK_PLIES: usize = 6;
+const DEFAULT_BOOK_DEPTH: u8 = 12;
+const DEFAULT_BOOK_GAMES: usize = 100;
+const DEFAULT_TUNE_PLIES: usize = 4;
+const DEFAULT_TUNE_DEPTH: u8 = 4;
+const DEFAULT_TUNE_GAMES: usize = 200;
+
+/// Builds the endgame database for `--holes` and `--seeds` and writes it to `--output`.
+pub fn generate_endgame(options: &Options) -> Result<(), Box<dyn Error>> {
+    let output_path = options.output_path.clone()
+        .unwrap_or_else(|| format!("kalah-{}-{}.egdb", options.hole_nums, options.endgame_seeds));
+
+    let endgame = EndgameDatabase::generate(options.hole_nums, options.endgame_seeds, |seeds, positions| {
+        println!("{} seeds: {} positions", seeds, positions);
+    })
+    .unwrap_or_else(|err| exit_with_error(&err));
+
+    endgame.save(&output_path).unwrap_or_else(|err| exit_with_error(&err));
+    println!("Wrote {} positions to {}", endgame.len(), output_path);
+
+    Ok(())
+}
+
+/// Builds an opening book for the configuration and writes it to `--output`.
+pub fn build_book(
+    options: &Options,
+    game_config: &GameConfig,
+    endgame: Option<Arc<EndgameDatabase>>,
+    evaluator: Arc<dyn Evaluator>,
+) -> Result<(), Box<dyn Error>> {
+    let output_path = options.output_path.clone().unwrap_or_else(|| format!(
+        "kalah-{}-{}-{}.book",
+        game_config.hole_nums, game_config.stone_nums_in_hole, if game_config.is_check_pipe_rule { "pie" } else { "nopie" }
+    ));
+
+    let root = CompactBoard::build(game_config);
+    let builder = BookBuilder { plies: options.plies.unwrap_or(DEFAULT_BOOK_PLIES), margin: options.book_margin };
+    let depth = options.depth.unwrap_or(DEFAULT_BOOK_DEPTH);
+    let mut book = OpeningBook::build(game_config);
+
+    let mut alpha_beta = AlphaBeta::build(DEFAULT_TABLE_SIZE, ReplacementPolicy::DepthPreferred)
+        .with_threads(options.threads)
+        .with_evaluator(evaluator);
+    let mut solver = Solver::build(DEFAULT_SOLVER_TABLE_SIZE);
+    if let Some(endgame) = endgame {
+        alpha_beta = alpha_beta.with_endgame(Arc::clone(&endgame));
+        solver = solver.with_endgame(endgame);
+    }
+
+    let result = match options.book_source {
+        BookSource::Search => builder.expand(&mut book, &root, |board| search_move_values(&mut alpha_beta, board, depth)),
+        BookSource::Solver => builder.expand(&mut book, &root, |board| solver_move_values(&mut solver, board)),
+        BookSource::SelfPlay => {
+            let games = options.games.unwrap_or(DEFAULT_BOOK_GAMES);
+            builder.self_play(&mut book, &root, games, &mut rand::rng(), |board| search_move_values(&mut alpha_beta, board, depth))
+        }
+    };
+    result.unwrap_or_else(|err| exit_with_error(&err));
+
+    book.save(&output_path).unwrap_or_else(|err| exit_with_error(&err));
+    println!("Wrote {} positions to {}", book.len(), output_path);
+
+    Ok(())
+}
+
+/// Fits evaluation weights, starting from `--weights`, to self-play games and writes them to `--output`.
+pub fn tune(options: &Options, game_config: &GameConfig) -> Result<(), Box<dyn Error>> {
+    let output_path = options.output_path.clone()
+        .unwrap_or_else(|| format!("kalah-{}-{}.weights", game_config.hole_nums, game_config.stone_nums_in_hole));
+    let start = match &options.weights_path {
+        Some(path) => LinearEvaluator::load(path).unwrap_or_else(|err| exit_with_error(&err)),
+        None => LinearEvaluator::default(),
+    };
+
+    let games = options.games.unwrap_or(DEFAULT_TUNE_GAMES);
+    let random_plies = options.plies.unwrap_or(DEFAULT_TUNE_PLIES);
+    let depth = options.depth.unwrap_or(DEFAULT_TUNE_DEPTH);
+    let positions = self_play_positions(game_config, games, random_plies, depth, Arc::new(start), &mut rand::rng());
+    println!("{} positions from {} games", positions.len(), games);
+
+    let tuner = Tuner::default();
+    println!("Start error {:.5}", tuner.error(&start, &positions));
+    let tuned = tuner.tune(start, &positions, |pass, error| println!("Pass {}: error {:.5}", pass, error));
+
+    tuned.save(&output_path).unwrap_or_else(|err| exit_with_error(&err));
+    print!("{}", tuned.to_text());
+    println!("Wrote weights to {}", output_path);
+
+    Ok(())
+}
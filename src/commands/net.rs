@@ -0,0 +1,68 @@
+use std::error::Error;
+use std::thread;
+
+use kalah::ai::difficulty::Level;
+use kalah::ai::engine::Engine;
+use kalah::game::game_config::GameConfig;
+use kalah::net::bot::{ Bot, BotEvent };
+use kalah::net::client::Client;
+use kalah::net::server::Server;
+use kalah::net::websocket::WebGateway;
+
+use crate::tui::remote::run_remote;
+use crate::tui::{ end_terminal, start_terminal };
+use crate::Options;
+
+/// Hosts games for players and spectators connecting over TCP until the process is stopped.
+pub fn serve(options: &Options, game_config: GameConfig) -> Result<(), Box<dyn Error>> {
+    let mut server = Server::bind(&options.listen_address, game_config)
+        .map_err(|err| format!("Cannot listen on {}: {}", options.listen_address, err))?;
+    if let Some(path) = &options.records_path {
+        std::fs::create_dir_all(path).map_err(|err| format!("Cannot create '{}': {}", path, err))?;
+        server = server.with_records(path, options.is_chat_recorded);
+    }
+    server = server.with_grace_period(options.grace_period);
+    if let Some(path) = &options.saved_games_path {
+        std::fs::create_dir_all(path).map_err(|err| format!("Cannot create '{}': {}", path, err))?;
+        server = server.with_saved_games(path)?;
+    }
+    if let Some(address) = &options.web_address {
+        let gateway = WebGateway::bind(address, &server).map_err(|err| format!("Cannot listen on {}: {}", address, err))?;
+        println!("Board page at http://{}/", gateway.local_addr()?);
+        thread::spawn(move || gateway.run());
+    }
+    println!("Listening on {}", server.local_addr()?);
+    server.run();
+    Ok(())
+}
+
+/// Plays and watches games hosted by `kalah serve` in the terminal.
+pub fn connect(options: &Options, game_config: &GameConfig) -> Result<(), Box<dyn Error>> {
+    let address = options.server_address.clone().unwrap_or_default();
+    let name = options.name.clone()
+        .or_else(|| std::env::var("USER").ok().filter(|user| !user.is_empty()))
+        .unwrap_or_else(|| "Player".to_string());
+    let client = Client::connect(address.clone(), name);
+
+    let mut terminal = start_terminal()?;
+    let res = run_remote(&mut terminal, &client, &address, game_config);
+    end_terminal(&mut terminal)?;
+    res?;
+    Ok(())
+}
+
+/// Creates games on the server and plays them with the engine at `level`, one after the
+/// other, until the process is stopped.
+pub fn run_bot(options: &Options, game_config: &GameConfig, level: Level, engine: Box<dyn Engine>) -> Result<(), Box<dyn Error>> {
+    let address = options.server_address.clone().unwrap_or_default();
+    let name = options.name.clone().unwrap_or_else(|| format!("Computer ({})", level.name()));
+    let mut bot = Bot::connect(address.clone(), name, engine, game_config.clone(), options.move_time);
+
+    bot.run(|event| match event {
+        BotEvent::Connected => println!("Connected to {}", address),
+        BotEvent::Disconnected(reason) | BotEvent::Rejected(reason) => eprintln!("{}", reason),
+        BotEvent::Playing { game, player } => println!("Playing game #{} as player {}", game, player),
+        BotEvent::Finished { game, result } => println!("Game #{} over, {}", game, result),
+    })?;
+    Ok(())
+}
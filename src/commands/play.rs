@@ -0,0 +1,98 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use kalah::ai::analysis::annotate;
+use kalah::ai::compact_board::CompactBoard;
+use kalah::ai::endgame::EndgameDatabase;
+use kalah::ai::evaluation::Evaluator;
+use kalah::ai::opening_book::OpeningBook;
+use kalah::game::game_config::GameConfig;
+use kalah::game::game_field::GameField;
+use kalah::game::game_process::GameProcess;
+use kalah::game::game_record::GameRecord;
+use kalah::game::time_control::GameClock;
+
+use crate::tui::computer::Computer;
+use crate::tui::hints::Hints;
+use crate::tui::local::run_app;
+use crate::tui::review::{ run_analysis, run_review };
+use crate::tui::setup::run_setup;
+use crate::tui::{ end_terminal, start_terminal };
+use crate::{ exit_with_error, Options };
+use super::analysis::DEFAULT_ANALYSIS_DEPTH;
+use super::{ build_alpha_beta, build_engine };
+
+/// Plays a game in the terminal against another player or the computer, then reviews it if
+/// asked and saves its record to `--record`.
+pub fn play(
+    options: &Options,
+    game_config: GameConfig,
+    endgame: Option<Arc<EndgameDatabase>>,
+    evaluator: Arc<dyn Evaluator>,
+    book: Option<Arc<OpeningBook>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut clock = game_config.time_control.map(GameClock::build);
+    let player_one_name = "Player1".to_string();
+    let player_two_name = if options.is_computer_opponent { "Computer" } else { "Player2" }.to_string();
+    let mut game_process = match &options.position {
+        Some(position) => GameProcess::from_position(position, player_one_name, player_two_name, game_config)
+            .unwrap_or_else(|err| exit_with_error(&err)),
+        None => GameProcess::build(GameField::build(&game_config), player_one_name, player_two_name, game_config),
+    };
+
+    let mut hints = Hints::build(build_alpha_beta(options, endgame.as_ref(), &evaluator), CompactBoard::from_process(&game_process));
+
+    let mut terminal = start_terminal()?;
+
+    let res = match options.level {
+        Some(level) => Ok(Some(level)),
+        None if options.is_computer_opponent => run_setup(&mut terminal),
+        None => Ok(None),
+    }
+    .and_then(|level| {
+        if options.is_computer_opponent && level.is_none() {
+            return Ok(None); // Quit on the setup screen
+        }
+
+        let mut computer = level.map(|level| {
+            let engine = build_engine(options, level, endgame.as_ref(), &evaluator, book.as_ref());
+            Computer::build(level, options.move_time, engine)
+        });
+
+        // Game loop
+        let is_review = run_app(&mut terminal, &mut game_process, computer.as_mut(), &mut hints, clock.as_mut())?;
+
+        let mut record = GameRecord::from_process(&game_process, CompactBoard::from_process(&game_process).is_game_over());
+        record.hints = hints.counts;
+        if let Some(player) = clock.as_ref().and_then(GameClock::out_of_time) {
+            let name = if player == 0 { &game_process.player_one.name } else { &game_process.player_two.name };
+            record.comments.push((record.moves.len(), format!("{} loses on time", name)));
+        }
+
+        if is_review {
+            let alpha_beta = build_alpha_beta(options, endgame.as_ref(), &evaluator);
+            let depth = options.depth.unwrap_or(DEFAULT_ANALYSIS_DEPTH);
+            if let Some(analyses) = run_analysis(&mut terminal, &record, alpha_beta, depth)? {
+                record = annotate(&record, &analyses);
+                run_review(&mut terminal, &record, &analyses)?;
+            }
+        }
+
+        Ok(Some(record))
+    });
+
+    end_terminal(&mut terminal)?;
+
+    let record = match res {
+        Ok(record) => record,
+        Err(err) => panic!("Error: {:?}", err),
+    };
+
+    if let Some(path) = &options.record_path
+        && let Some(record) = record
+    {
+        record.save(path).unwrap_or_else(|err| exit_with_error(&err));
+    }
+
+    Ok(())
+}
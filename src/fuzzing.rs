@@ -0,0 +1,62 @@
+use crate::game::game_config::GameConfig;
+use crate::game::game_field::GameField;
+use crate::game::game_process::GameProcess;
+use crate::game::game_record::GameRecord;
+use crate::game::position::Position;
+
+/// Parses position notation, checks it round trips and plays every hole from it.
+pub fn fuzz_position(data: &[u8]) {
+    let Ok(notation) = std::str::from_utf8(data) else { return };
+    let Ok(position) = Position::parse(notation) else { return };
+
+    let reparsed = Position::parse(&position.to_notation()).expect("position notation must round trip");
+    assert_eq!(reparsed, position);
+
+    let hole_nums = position.side_one.len();
+    let Some(total_stones) = position.total_stones() else { return };
+    if hole_nums == 0 || total_stones % (2 * hole_nums) != 0 {
+        return;
+    }
+
+    let Ok(game_config) = GameConfig::build(total_stones / (2 * hole_nums), hole_nums, false) else { return };
+
+    for hole_num in 0..=hole_nums + 1 {
+        let Ok(mut game_process) = GameProcess::from_position(&position, "P1".to_string(), "P2".to_string(), game_config.clone()) else { return };
+
+        _ = game_process.move_stones_from_hole(hole_num);
+        game_process.validate().expect("game must stay valid");
+    }
+}
+
+/// Parses a game record, checks it round trips and replays it.
+pub fn fuzz_game_record(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else { return };
+    let Ok(record) = GameRecord::parse(text) else { return };
+
+    let reparsed = GameRecord::parse(&record.to_text()).expect("game record must round trip");
+    assert_eq!(reparsed, record);
+
+    if let Ok(game_process) = record.replay() {
+        game_process.validate().expect("replayed game must stay valid");
+    }
+}
+
+/// Uses the first three bytes as config and every following byte as a hole number.
+pub fn fuzz_moves(data: &[u8]) {
+    let [stone_nums_in_hole, hole_nums, is_check_pipe_rule, moves @ ..] = data else { return };
+
+    let game_config = GameConfig::build(
+        *stone_nums_in_hole as usize % 10,
+        *hole_nums as usize % 10,
+        is_check_pipe_rule & 1 == 1,
+    )
+    .unwrap();
+    let game_field = GameField::build(&game_config);
+    let mut game_process = GameProcess::build(game_field, "P1".to_string(), "P2".to_string(), game_config);
+
+    for &hole_num in moves {
+        // Out of range hole numbers are kept to exercise the error path
+        _ = game_process.move_stones_from_hole(hole_num as usize % 11);
+        game_process.validate().expect("game must stay valid");
+    }
+}
@@ -1,5 +1,9 @@
 pub mod game_config;
 pub mod game_field;
 pub mod game_process;
+pub mod game_record;
 pub mod game_status;
 pub mod player;
+pub mod position;
+pub mod scoring_mode;
+pub mod time_control;
@@ -1,7 +1,14 @@
+use super::scoring_mode::ScoringMode;
+use super::time_control::TimeControl;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GameConfig {
     pub stone_nums_in_hole: usize,
     pub hole_nums: usize,
     pub is_check_pipe_rule: bool,
+    pub scoring_mode: ScoringMode,
+    /// None for games without clocks.
+    pub time_control: Option<TimeControl>,
 }
 
 
@@ -20,6 +27,18 @@ impl GameConfig {
             stone_nums_in_hole, 
             hole_nums,
             is_check_pipe_rule,
+            scoring_mode: ScoringMode::StoneCount,
+            time_control: None,
         })
     }
+
+    pub fn with_scoring_mode(mut self, scoring_mode: ScoringMode) -> GameConfig {
+        self.scoring_mode = scoring_mode;
+        self
+    }
+
+    pub fn with_time_control(mut self, time_control: Option<TimeControl>) -> GameConfig {
+        self.time_control = time_control;
+        self
+    }
 }
@@ -4,13 +4,14 @@ use rand::seq::{ IndexedRandom };
 use rand;
 
 use super::game_config::GameConfig;
+use super::scoring_mode::ScoringMode;
 
 pub struct GameField {
     pub side_one: Side,
     pub side_two: Side,
 }
 
-#[derive(Debug, EnumIter, Clone, Copy)]
+#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq)]
 pub enum Color {
     Blue,
     Red,
@@ -19,13 +20,14 @@ pub enum Color {
     Yellow,
 }
 
-#[derive(Debug, EnumIter, Clone, Copy)]
+#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq)]
 pub enum Size {
     Small,
     Medium,
     Large,
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct Stone {
     pub color: Color,
     pub size: Size,
@@ -37,33 +39,47 @@ pub struct Hole {
 
 pub struct Side {
     pub holes: Vec<Hole>,
+    pub store: Hole,
 }
 
 impl GameField {
 
     pub fn build(config: &GameConfig) -> GameField {
-        let mut side_one = Side { holes: Vec::new() };
-        let mut side_two = Side { holes: Vec::new() };
-
-        for side in [&mut side_one, &mut side_two] {
-            for _ in 0..config.hole_nums {
-                let mut hole = Hole { stones: Vec::new() };
-                for _ in 0..config.stone_nums_in_hole {
-                    let color: Color = random_enum();
-                    let size: Size = random_enum();
-                    let stone = Stone { color, size };
-                    hole.stones.push(stone);
-                }
-                side.holes.push(hole);
-            }
+        let hole_counts = vec![config.stone_nums_in_hole; config.hole_nums];
+
+        GameField {
+            side_one: Side::build(&hole_counts, 0),
+            side_two: Side::build(&hole_counts, 0),
+        }
+    }
+
+}
+
+impl Side {
+
+    pub fn build(hole_counts: &[usize], store_count: usize) -> Side {
+        Side {
+            holes: hole_counts.iter().map(|&count| Hole::build(count)).collect(),
+            store: Hole::build(store_count),
         }
+    }
 
-        return GameField {
-            side_one,
-            side_two,
-        };
+    pub fn score(&self, scoring_mode: &ScoringMode) -> usize {
+        scoring_mode.stones_points(&self.store.stones)
     }
+}
 
+impl Hole {
+
+    pub fn build(stone_nums: usize) -> Hole {
+        let mut hole = Hole { stones: Vec::new() };
+        for _ in 0..stone_nums {
+            let color: Color = random_enum();
+            let size: Size = random_enum();
+            hole.stones.push(Stone { color, size });
+        }
+        hole
+    }
 }
 
 pub fn random_enum<T>() -> T
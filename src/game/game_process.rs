@@ -1,9 +1,11 @@
-use std::usize;
-
 use super::game_config::GameConfig;
-use super::game_field::{ GameField, Side, Hole };
+use super::game_field::{ GameField, Side, Hole, Stone };
 use super::player::{ Player};
 use super::game_status::GameStatus;
+use super::position::Position;
+use super::time_control::GameClock;
+
+use std::time::Instant;
 
 pub struct GameProcess {
     pub player_one: Player,
@@ -12,11 +14,11 @@ pub struct GameProcess {
     pub game_field: GameField,
     pub is_player_one_turn: bool,
     pub total_turns: usize,
+    pub move_history: Vec<usize>,
+    pub start_position: Option<Position>,
     #[cfg(feature = "test_hooks")]
     pub swap_side_called: bool,
     #[cfg(feature = "test_hooks")]
-    pub swap_players_called: bool,
-    #[cfg(feature = "test_hooks")]
     pub game_finished: bool,
 }
 
@@ -25,36 +27,64 @@ pub struct LastTurnHole {
     pub side: u8,
     pub hole_index: usize,
     pub player_num: u8,
+    pub is_store: bool,
 }
 
 impl GameProcess {
 
     pub fn build(game_field: GameField, player_one_name: String, player_two_name: String, game_config: GameConfig) -> GameProcess {
-        
-        let game_process = GameProcess { 
-            player_one: Player {
-                    name: player_one_name, 
-                    score: 0 
-                },
-            player_two: Player { 
-                    name: player_two_name, 
-                    score: 0 
-                }, 
-            game_field: game_field, 
-            game_config: game_config,
+        GameProcess { 
+            player_one: Player::build(player_one_name),
+            player_two: Player::build(player_two_name), 
+            game_field, 
+            game_config,
             is_player_one_turn: true,
             total_turns: 0,
+            move_history: Vec::new(),
+            start_position: None,
             #[cfg(feature = "test_hooks")]
             swap_side_called: false,
             #[cfg(feature = "test_hooks")]
-            swap_players_called: false,
-            #[cfg(feature = "test_hooks")]
             game_finished: false,
+        }
+    }
+    
+    pub fn from_position(position: &Position, player_one_name: String, player_two_name: String, game_config: GameConfig) -> Result<GameProcess, String> {
+        if position.side_one.len() != game_config.hole_nums {
+            return Err(format!(
+                "Position has {} holes per side, config expects {}",
+                position.side_one.len(), game_config.hole_nums
+            ));
+        }
+
+        // Checked before building the field so huge counts are never allocated
+        let expected_stones = 2 * game_config.hole_nums * game_config.stone_nums_in_hole;
+        if position.total_stones() != Some(expected_stones) {
+            return Err(format!("Position must contain {} stones", expected_stones));
+        }
+
+        let game_field = GameField {
+            side_one: Side::build(&position.side_one, position.side_one_store),
+            side_two: Side::build(&position.side_two, position.side_two_store),
         };
 
-        game_process
+        let mut game_process = GameProcess::build(game_field, player_one_name, player_two_name, game_config);
+        game_process.is_player_one_turn = position.is_player_one_turn;
+        game_process.total_turns = position.total_turns;
+        game_process.start_position = Some(position.clone());
+
+        game_process.validate()?;
+        Ok(game_process)
     }
-    
+
+    pub fn player_one_score(&self) -> usize {
+        self.game_field.side_one.score(&self.game_config.scoring_mode)
+    }
+
+    pub fn player_two_score(&self) -> usize {
+        self.game_field.side_two.score(&self.game_config.scoring_mode)
+    }
+
     pub fn move_stones_from_hole(&mut self, hole_num: usize) -> Result<GameStatus, String> {
 
         if hole_num == 0 || hole_num > self.game_config.hole_nums {
@@ -86,20 +116,21 @@ impl GameProcess {
             2
         };
 
-        let mut last_turn_hole = LastTurnHole {side: 1, hole_index: 0, player_num: 1};
+        let mut last_turn_hole = LastTurnHole {side: 1, hole_index: 0, player_num: 1, is_store: false};
 
-        while !stones.is_empty() {
-            
-            let stone = stones.pop().unwrap();
+        while let Some(stone) = stones.pop() {
 
             if hole_index == self.game_config.hole_nums {
-                // Last hole, deposit into score
+                // Last hole, deposit into store
                 if self.is_player_one_turn && active_side == 1  {
-                    self.player_one.score += 1;
+                    self.game_field.side_one.store.stones.push(stone);
+                    last_turn_hole.is_store = true;
                 }
                 else if !self.is_player_one_turn && active_side == 2 {
-                    self.player_two.score += 1;
+                    self.game_field.side_two.store.stones.push(stone);
+                    last_turn_hole.is_store = true;
                 } else {
+                    // Opponent's store is skipped, the stone goes to the next hole
                     stones.push(stone);
                 }
 
@@ -118,21 +149,20 @@ impl GameProcess {
                 side.holes[hole_index].stones.push(stone);
                 last_turn_hole.hole_index = hole_index;
                 last_turn_hole.side = active_side;
+                last_turn_hole.is_store = false;
                 hole_index += 1;
             }
         }
 
-        let single_stone_score = self.last_stone_single_score(&last_turn_hole);
-        let player = match self.is_player_one_turn {
-            true => &mut self.player_one,
-            _ => &mut self.player_two,
-        };
-        player.score += single_stone_score;
+        let captured_stones = self.last_stone_capture(&last_turn_hole);
+        self.get_curren_side_mut().store.stones.extend(captured_stones);
 
         self.total_turns += 1;
+        self.move_history.push(hole_num);
 
         if self.is_game_finish() { 
             self.finalize_score();
+            self.debug_validate();
             return Ok(GameStatus::Finished);
         }
 
@@ -140,14 +170,100 @@ impl GameProcess {
 
         if self.game_config.is_check_pipe_rule && self.check_pie_rule(&last_turn_hole) {
             self.swap_side();
-            self.swap_players_score();
         };
 
+        self.debug_validate();
         Ok(GameStatus::Run)
     }
 
+    /// Makes a move at `now` and presses `clock` for the next player, stopping it at the end of
+    /// the game. A move after the time of the player to move ran out is too late: it is not made,
+    /// and the game is finished with that player out of time on the clock.
+    pub fn move_stones_on_clock(&mut self, hole_num: usize, clock: &mut GameClock, now: Instant) -> Result<GameStatus, String> {
+        if clock.flag(now).is_some() {
+            return Ok(GameStatus::Finished);
+        }
+
+        let status = self.move_stones_from_hole(hole_num)?;
+        match status {
+            GameStatus::Finished => clock.stop(now),
+            GameStatus::Run => clock.press(if self.is_player_one_turn { 0 } else { 1 }, now),
+        }
+        Ok(status)
+    }
+
+    /// Checks stone conservation and turn bookkeeping, describing the position on failure.
+    pub fn validate(&self) -> Result<(), String> {
+        let sides = [&self.game_field.side_one, &self.game_field.side_two];
+
+        for (side_num, side) in sides.iter().enumerate() {
+            if side.holes.len() != self.game_config.hole_nums {
+                return Err(format!(
+                    "side {} has {} holes, expected {}; {}",
+                    side_num + 1, side.holes.len(), self.game_config.hole_nums, self.describe_position()
+                ));
+            }
+        }
+
+        let expected_stones = 2 * self.game_config.hole_nums * self.game_config.stone_nums_in_hole;
+        let total_stones: usize = sides
+            .iter()
+            .map(|side| Self::count_holes_stones(&side.holes) + side.store.stones.len())
+            .sum();
+
+        if total_stones != expected_stones {
+            return Err(format!(
+                "stone conservation violated: expected {} stones, found {}; {}",
+                expected_stones, total_stones, self.describe_position()
+            ));
+        }
+
+        if self.total_turns == 0 {
+            if !self.is_player_one_turn {
+                return Err(format!("player two to move before any turn was made; {}", self.describe_position()));
+            }
+
+            if sides.iter().any(|side| !side.store.stones.is_empty()) {
+                return Err(format!("stores are not empty before any turn was made; {}", self.describe_position()));
+            }
+        }
+
+        let is_side_empty = sides.map(|side| Self::count_holes_stones(&side.holes) == 0);
+        if is_side_empty[0] != is_side_empty[1] {
+            return Err(format!(
+                "one side is empty but the remaining stones were not moved to the store; {}",
+                self.describe_position()
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn describe_position(&self) -> String {
+        let describe_side = |side: &Side| format!(
+            "holes {:?} store {}",
+            side.holes.iter().map(|hole| hole.stones.len()).collect::<Vec<_>>(),
+            side.store.stones.len()
+        );
+
+        format!(
+            "side one: {}, side two: {}, total turns {}, {} to move",
+            describe_side(&self.game_field.side_one),
+            describe_side(&self.game_field.side_two),
+            self.total_turns,
+            if self.is_player_one_turn { "player one" } else { "player two" }
+        )
+    }
+
+    fn debug_validate(&self) {
+        #[cfg(debug_assertions)]
+        if let Err(err) = self.validate() {
+            panic!("Invalid game state after move: {}", err);
+        }
+    }
+
     fn check_pie_rule(&self, last_turn_hole: &LastTurnHole) -> bool {
-        if last_turn_hole.hole_index == 0 && self.total_turns == 1 && self.is_player_one_turn {
+        if last_turn_hole.is_store && self.total_turns == 1 && self.is_player_one_turn {
             return true;
         }
         false
@@ -161,28 +277,19 @@ impl GameProcess {
         }
     }
 
-    fn swap_players_score(&mut self) {
-        std::mem::swap(&mut self.player_one.score, &mut self.player_two.score);
-        #[cfg(feature = "test_hooks")]
-        {
-            self.swap_players_called = true;
-        }
-    }
-
     fn is_game_finish(&mut self) -> bool {
         let is_empty_side = [&self.game_field.side_one.holes, &self.game_field.side_two.holes]
             .iter()
-            .map(|holes| {
-                holes.iter().filter(|hole| hole.stones.len() > 0).next().is_none()
-            })
-            .any(|is_empty| is_empty);
+            .any(|holes| {
+                !holes.iter().any(|hole| !hole.stones.is_empty())
+            });
         
         #[cfg(feature = "test_hooks")]
         {
             self.game_finished = true;
         }
 
-        return is_empty_side;
+        is_empty_side
     }
 
     fn get_curren_side_mut(&mut self) -> &mut Side {
@@ -200,36 +307,47 @@ impl GameProcess {
     }
 
     fn finalize_score(&mut self) {
-        self.player_one.score += Self::count_holes_stouns(&self.game_field.side_one.holes);
-        self.player_one.score += Self::count_holes_stouns(&self.game_field.side_two.holes);
+        // Remaining stones go to the store of the side they are on
+        for side in [&mut self.game_field.side_one, &mut self.game_field.side_two] {
+            let stones = Self::drain_holes_stones(&mut side.holes);
+            side.store.stones.extend(stones);
+        }
     }
 
-    fn count_holes_stouns(holes: &[Hole]) -> usize {
+    fn count_holes_stones(holes: &[Hole]) -> usize {
         holes.iter().map(|h| h.stones.len()).sum()
     }
 
+    fn drain_holes_stones(holes: &mut [Hole]) -> Vec<Stone> {
+        holes.iter_mut().flat_map(|h| h.stones.drain(..)).collect()
+    }
+
     fn change_side(&mut self, hole_index: usize, active_side: u8) {
         // println!("Is player one turn {}, Active side {}", self.is_player_one_turn, active_side);
 
-        if hole_index != 0 {
-            self.is_player_one_turn = !self.is_player_one_turn;
-        }
+        // Last stone in own store: sides were already switched after the store, so the player moves again
+        let is_extra_turn = hole_index == 0
+            && (self.is_player_one_turn && active_side == 2 || !self.is_player_one_turn && active_side == 1);
 
-        else if !(self.is_player_one_turn && active_side == 2) && !(!self.is_player_one_turn && active_side == 1) {
+        if !is_extra_turn {
             self.is_player_one_turn = !self.is_player_one_turn;
         }
     }
 
-    fn last_stone_single_score(&mut self, last_turn_hole: &LastTurnHole) -> usize {
+    fn last_stone_capture(&mut self, last_turn_hole: &LastTurnHole) -> Vec<Stone> {
+
+        if last_turn_hole.is_store {
+            return Vec::new();
+        }
 
         if self.is_player_one_turn && last_turn_hole.side == 1 && self.get_curren_side().holes[last_turn_hole.hole_index].stones.len() == 1 {
-            return self.game_field.side_two.holes[self.game_config.hole_nums - last_turn_hole.hole_index - 1].stones.drain(..).collect::<Vec<_>>().len();
+            return self.game_field.side_two.holes[self.game_config.hole_nums - last_turn_hole.hole_index - 1].stones.drain(..).collect();
         }
 
         else if !self.is_player_one_turn && last_turn_hole.side == 2 && self.get_curren_side().holes[last_turn_hole.hole_index].stones.len() == 1 {
-           return self.game_field.side_one.holes[self.game_config.hole_nums - last_turn_hole.hole_index - 1].stones.drain(..).collect::<Vec<_>>().len();
+           return self.game_field.side_one.holes[self.game_config.hole_nums - last_turn_hole.hole_index - 1].stones.drain(..).collect();
         }
 
-        0
+        Vec::new()
     }
 }
@@ -0,0 +1,248 @@
+use super::game_config::GameConfig;
+use super::game_field::GameField;
+use super::game_process::GameProcess;
+use super::position::Position;
+use super::time_control::TimeControl;
+
+const CHAT_PREFIX: &str = "chat: ";
+
+/// A played game in a PGN-like text format:
+///
+/// ```text
+/// [PlayerOne "P1"]
+/// [PlayerTwo "P2"]
+/// [Holes "6"]
+/// [Stones "4"]
+/// [PieRule "off"]
+/// [Result "26-22"]
+/// [Hints "2-0"]
+/// [TimeControl "300+5"]
+///
+/// 3 6 1 {comment} 4 {chat: P2: well played}
+/// ```
+///
+/// An optional `[Position "<notation>"]` tag gives a start position other than the initial one
+/// and `[Hints "<one>-<two>"]` counts the hints each player asked for, written only when one did.
+/// `[TimeControl "<control>"]` is written for games with clocks, see `TimeControl`.
+/// Comments starting with `chat: ` are chat lines of a network game.
+/// Stone attributes are not recorded, so only the classic stone count scoring replays exactly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    pub player_one_name: String,
+    pub player_two_name: String,
+    pub stone_nums_in_hole: usize,
+    pub hole_nums: usize,
+    pub is_check_pipe_rule: bool,
+    pub start_position: Option<Position>,
+    pub moves: Vec<usize>,
+    /// `{comment}`s, each with the number of moves played before it.
+    pub comments: Vec<(usize, String)>,
+    /// Chat lines as shown to the players, each with the number of moves played before it.
+    pub chat: Vec<(usize, String)>,
+    pub result: Option<(usize, usize)>,
+    /// Hints used by player one and player two.
+    pub hints: (usize, usize),
+    pub time_control: Option<TimeControl>,
+}
+
+impl GameRecord {
+
+    pub fn from_process(game_process: &GameProcess, is_finished: bool) -> GameRecord {
+        GameRecord {
+            player_one_name: game_process.player_one.name.clone(),
+            player_two_name: game_process.player_two.name.clone(),
+            stone_nums_in_hole: game_process.game_config.stone_nums_in_hole,
+            hole_nums: game_process.game_config.hole_nums,
+            is_check_pipe_rule: game_process.game_config.is_check_pipe_rule,
+            start_position: game_process.start_position.clone(),
+            moves: game_process.move_history.clone(),
+            comments: Vec::new(),
+            chat: Vec::new(),
+            result: if is_finished {
+                Some((game_process.player_one_score(), game_process.player_two_score()))
+            } else {
+                None
+            },
+            hints: (0, 0),
+            time_control: game_process.game_config.time_control,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<GameRecord, String> {
+        let mut tags: Vec<(String, String)> = Vec::new();
+        let mut moves = Vec::new();
+        let mut comments = Vec::new();
+        let mut chat = Vec::new();
+
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty()).peekable();
+        while let Some(line) = lines.next_if(|line| line.starts_with('[')) {
+            tags.push(Self::parse_tag(line)?);
+        }
+
+        let moves_text = lines.collect::<Vec<_>>().join(" ");
+        let mut rest = moves_text.as_str();
+        while let Some(start) = rest.find('{') {
+            moves.extend(Self::parse_moves(&rest[..start])?);
+            let end = rest[start..]
+                .find('}')
+                .ok_or("Unclosed '{' comment in moves")?;
+            let comment = &rest[start + 1..start + end];
+            match comment.strip_prefix(CHAT_PREFIX) {
+                Some(line) => chat.push((moves.len(), line.to_string())),
+                None => comments.push((moves.len(), comment.to_string())),
+            }
+            rest = &rest[start + end + 1..];
+        }
+        moves.extend(Self::parse_moves(rest)?);
+
+        let tag = |name: &str| tags.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+        let required_tag = |name: &str| tag(name).ok_or_else(|| format!("Missing [{}] tag", name));
+        let number_tag = |name: &str| required_tag(name)?
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid number in [{}] tag", name));
+
+        let is_check_pipe_rule = match tag("PieRule") {
+            Some("on") => true,
+            Some("off") | None => false,
+            Some(value) => return Err(format!("PieRule must be 'on' or 'off' (got '{}')", value)),
+        };
+
+        let start_position = tag("Position").map(Position::parse).transpose()?;
+
+        let pair = |name: &str, value: &str| -> Result<(usize, usize), String> {
+            let (one, two) = value
+                .split_once('-')
+                .ok_or_else(|| format!("{} must be '<one>-<two>' (got '{}')", name, value))?;
+            let number = |number: &str| number.parse::<usize>().map_err(|_| format!("Invalid number '{}' in {}", number, name));
+            Ok((number(one)?, number(two)?))
+        };
+
+        let result = match tag("Result") {
+            Some("*") | None => None,
+            Some(value) => Some(pair("Result", value)?),
+        };
+        let hints = tag("Hints").map(|value| pair("Hints", value)).transpose()?.unwrap_or((0, 0));
+        let time_control = tag("TimeControl").map(TimeControl::parse).transpose()?.flatten();
+
+        Ok(GameRecord {
+            player_one_name: required_tag("PlayerOne")?.to_string(),
+            player_two_name: required_tag("PlayerTwo")?.to_string(),
+            stone_nums_in_hole: number_tag("Stones")?,
+            hole_nums: number_tag("Holes")?,
+            is_check_pipe_rule,
+            start_position,
+            moves,
+            comments,
+            chat,
+            result,
+            hints,
+            time_control,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let mut push_tag = |name: &str, value: &str| text.push_str(&format!("[{} \"{}\"]\n", name, value));
+
+        push_tag("PlayerOne", &self.player_one_name);
+        push_tag("PlayerTwo", &self.player_two_name);
+        push_tag("Holes", &self.hole_nums.to_string());
+        push_tag("Stones", &self.stone_nums_in_hole.to_string());
+        push_tag("PieRule", if self.is_check_pipe_rule { "on" } else { "off" });
+        if let Some(position) = &self.start_position {
+            push_tag("Position", &position.to_notation());
+        }
+        match self.result {
+            Some((one, two)) => push_tag("Result", &format!("{}-{}", one, two)),
+            None => push_tag("Result", "*"),
+        }
+        if self.hints != (0, 0) {
+            push_tag("Hints", &format!("{}-{}", self.hints.0, self.hints.1));
+        }
+        if let Some(time_control) = &self.time_control {
+            push_tag("TimeControl", &time_control.to_notation());
+        }
+
+        text.push('\n');
+        let mut comments = self.comments.iter().peekable();
+        let mut chat = self.chat.iter().peekable();
+        let mut tokens = Vec::new();
+        for index in 0..=self.moves.len() {
+            while let Some((_, comment)) = comments.next_if(|(before, _)| *before <= index) {
+                tokens.push(format!("{{{}}}", comment));
+            }
+            while let Some((_, line)) = chat.next_if(|(before, _)| *before <= index) {
+                tokens.push(format!("{{{}{}}}", CHAT_PREFIX, line));
+            }
+            if let Some(hole_num) = self.moves.get(index) {
+                tokens.push(hole_num.to_string());
+            }
+        }
+        tokens.extend(comments.map(|(_, comment)| format!("{{{}}}", comment)));
+        tokens.extend(chat.map(|(_, line)| format!("{{{}{}}}", CHAT_PREFIX, line)));
+        text.push_str(&tokens.join(" "));
+        text.push('\n');
+        text
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_text()).map_err(|err| format!("Cannot write '{}': {}", path, err))
+    }
+
+    /// Replays the moves and checks them against the recorded result.
+    pub fn replay(&self) -> Result<GameProcess, String> {
+        let game_config = GameConfig::build(self.stone_nums_in_hole, self.hole_nums, self.is_check_pipe_rule)?
+            .with_time_control(self.time_control);
+        let player_one_name = self.player_one_name.clone();
+        let player_two_name = self.player_two_name.clone();
+
+        let mut game_process = match &self.start_position {
+            Some(position) => GameProcess::from_position(position, player_one_name, player_two_name, game_config)?,
+            None => {
+                let game_field = GameField::build(&game_config);
+                GameProcess::build(game_field, player_one_name, player_two_name, game_config)
+            }
+        };
+
+        for (index, &hole_num) in self.moves.iter().enumerate() {
+            game_process
+                .move_stones_from_hole(hole_num)
+                .map_err(|err| format!("Move {} ({}) is illegal: {}", index + 1, hole_num, err))?;
+        }
+
+        if let Some(result) = self.result {
+            let scores = (game_process.player_one_score(), game_process.player_two_score());
+            if scores != result {
+                return Err(format!(
+                    "Recorded result {}-{} does not match replayed score {}-{}",
+                    result.0, result.1, scores.0, scores.1
+                ));
+            }
+        }
+
+        Ok(game_process)
+    }
+
+    pub(crate) fn parse_tag(line: &str) -> Result<(String, String), String> {
+        let inner = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+            .ok_or_else(|| format!("Malformed tag line '{}'", line))?;
+        let (name, value) = inner
+            .split_once(' ')
+            .ok_or_else(|| format!("Tag '{}' has no value", line))?;
+        let value = value
+            .trim()
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .ok_or_else(|| format!("Tag value must be quoted in '{}'", line))?;
+
+        Ok((name.to_string(), value.to_string()))
+    }
+
+    fn parse_moves(text: &str) -> Result<Vec<usize>, String> {
+        text.split_whitespace()
+            .map(|hole_num| hole_num.parse::<usize>().map_err(|_| format!("Invalid move '{}'", hole_num)))
+            .collect()
+    }
+}
@@ -1,4 +1,10 @@
 pub struct Player {
     pub name: String,
-    pub score: usize,
+}
+
+impl Player {
+
+    pub fn build(name: String) -> Player {
+        Player { name }
+    }
 }
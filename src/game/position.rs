@@ -0,0 +1,120 @@
+use serde::{ Deserialize, Serialize };
+
+use super::game_field::Hole;
+use super::game_process::GameProcess;
+
+/// Stone counts of a position, written as `4,4,4,4,4,4:0/4,4,4,4,4,4:0 1 0`:
+/// side one holes and store, side two holes and store, player to move (1 or 2) and total turns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    pub side_one: Vec<usize>,
+    pub side_one_store: usize,
+    pub side_two: Vec<usize>,
+    pub side_two_store: usize,
+    pub is_player_one_turn: bool,
+    pub total_turns: usize,
+}
+
+impl Position {
+
+    pub fn from_process(game_process: &GameProcess) -> Position {
+        let counts = |holes: &[Hole]| holes.iter().map(|hole| hole.stones.len()).collect();
+
+        Position {
+            side_one: counts(&game_process.game_field.side_one.holes),
+            side_one_store: game_process.game_field.side_one.store.stones.len(),
+            side_two: counts(&game_process.game_field.side_two.holes),
+            side_two_store: game_process.game_field.side_two.store.stones.len(),
+            is_player_one_turn: game_process.is_player_one_turn,
+            total_turns: game_process.total_turns,
+        }
+    }
+
+    pub fn parse(notation: &str) -> Result<Position, String> {
+        let mut parts = notation.split_whitespace();
+
+        let board = parts.next().ok_or("Position notation is empty")?;
+        let to_move = parts.next().ok_or("Position notation is missing the player to move")?;
+        let total_turns = parts.next().ok_or("Position notation is missing the total turns")?;
+
+        if parts.next().is_some() {
+            return Err(format!("Unexpected trailing input in position notation '{}'", notation));
+        }
+
+        let (side_one, side_two) = board
+            .split_once('/')
+            .ok_or_else(|| format!("Board '{}' must contain two sides separated by '/'", board))?;
+        let (side_one, side_one_store) = Self::parse_side(side_one)?;
+        let (side_two, side_two_store) = Self::parse_side(side_two)?;
+
+        if side_one.len() != side_two.len() {
+            return Err(format!(
+                "Sides have different hole numbers ({} and {})",
+                side_one.len(), side_two.len()
+            ));
+        }
+
+        let is_player_one_turn = match to_move {
+            "1" => true,
+            "2" => false,
+            _ => return Err(format!("Player to move must be 1 or 2 (got '{}')", to_move)),
+        };
+
+        // Bounded by u32, the turn counter of a compact board, which stops at its maximum
+        let total_turns = total_turns
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid total turns '{}'", total_turns))? as usize;
+
+        Ok(Position {
+            side_one,
+            side_one_store,
+            side_two,
+            side_two_store,
+            is_player_one_turn,
+            total_turns,
+        })
+    }
+
+    pub fn to_notation(&self) -> String {
+        let side = |holes: &[usize], store: usize| format!(
+            "{}:{}",
+            holes.iter().map(|count| count.to_string()).collect::<Vec<_>>().join(","),
+            store
+        );
+
+        format!(
+            "{}/{} {} {}",
+            side(&self.side_one, self.side_one_store),
+            side(&self.side_two, self.side_two_store),
+            if self.is_player_one_turn { 1 } else { 2 },
+            self.total_turns
+        )
+    }
+
+    /// Total stones in holes and stores, `None` on overflow.
+    pub fn total_stones(&self) -> Option<usize> {
+        self.side_one
+            .iter()
+            .chain(self.side_two.iter())
+            .chain([&self.side_one_store, &self.side_two_store])
+            .try_fold(0usize, |total, &count| total.checked_add(count))
+    }
+
+    fn parse_side(side: &str) -> Result<(Vec<usize>, usize), String> {
+        let (holes, store) = side
+            .split_once(':')
+            .ok_or_else(|| format!("Side '{}' must end with ':<store>'", side))?;
+
+        let parse_count = |count: &str| count
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid stone count '{}'", count));
+
+        let holes = if holes.is_empty() {
+            Vec::new()
+        } else {
+            holes.split(',').map(parse_count).collect::<Result<Vec<_>, _>>()?
+        };
+
+        Ok((holes, parse_count(store)?))
+    }
+}
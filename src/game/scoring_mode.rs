@@ -0,0 +1,32 @@
+use super::game_field::{ Color, Size, Stone };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoringMode {
+    /// Every stone is worth one point (classic Kalah).
+    StoneCount,
+    /// Small = 1, medium = 2, large = 3.
+    BySize,
+    /// Every stone is worth one point, stones of the bonus colour are worth `bonus` extra.
+    BonusColor { color: Color, bonus: usize },
+}
+
+impl ScoringMode {
+
+    pub fn stone_points(&self, stone: &Stone) -> usize {
+        match self {
+            ScoringMode::StoneCount => 1,
+            ScoringMode::BySize => match stone.size {
+                Size::Small => 1,
+                Size::Medium => 2,
+                Size::Large => 3,
+            },
+            ScoringMode::BonusColor { color, bonus } => {
+                if stone.color == *color { 1 + bonus } else { 1 }
+            }
+        }
+    }
+
+    pub fn stones_points(&self, stones: &[Stone]) -> usize {
+        stones.iter().map(|stone| self.stone_points(stone)).sum()
+    }
+}
@@ -0,0 +1,162 @@
+use std::time::{ Duration, Instant };
+
+/// Longest base time, increment or delay, a day.
+pub const MAX_TIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Time each player has for the game, written in seconds as `300` (sudden death), `300+5`
+/// (Fischer increment) or `300d5` (Bronstein delay). `-` is no clock, as in game records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControl {
+    /// The whole game in `base`.
+    SuddenDeath { base: Duration },
+    /// `increment` is added after every move.
+    Fischer { base: Duration, increment: Duration },
+    /// Up to `delay` of every move is not taken from the clock.
+    Bronstein { base: Duration, delay: Duration },
+}
+
+/// The clocks of both players, of which only the one of the player to move runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameClock {
+    pub time_control: TimeControl,
+    /// Time left to player one and player two, before the move being made.
+    pub remaining: [Duration; 2],
+    /// Player whose clock runs, and since when.
+    pub running: Option<(usize, Instant)>,
+}
+
+impl TimeControl {
+
+    /// None for `-`.
+    pub fn parse(text: &str) -> Result<Option<TimeControl>, String> {
+        let text = text.trim();
+        if text == "-" {
+            return Ok(None);
+        }
+        let seconds = |value: &str| value
+            .parse::<u64>()
+            .map(Duration::from_secs)
+            .map_err(|_| format!("Time control must be <seconds>, <seconds>+<increment> or <seconds>d<delay> (got '{}')", text));
+
+        let time_control = if let Some((base, increment)) = text.split_once('+') {
+            TimeControl::Fischer { base: seconds(base)?, increment: seconds(increment)? }
+        } else if let Some((base, delay)) = text.split_once('d') {
+            TimeControl::Bronstein { base: seconds(base)?, delay: seconds(delay)? }
+        } else {
+            TimeControl::SuddenDeath { base: seconds(text)? }
+        };
+        if time_control.base().is_zero() {
+            return Err("A time control needs some time".into());
+        }
+        let (base, bonus) = match time_control {
+            TimeControl::SuddenDeath { base } => (base, Duration::ZERO),
+            TimeControl::Fischer { base, increment: bonus } | TimeControl::Bronstein { base, delay: bonus } => (base, bonus),
+        };
+        if base > MAX_TIME || bonus > MAX_TIME {
+            return Err(format!("Times of a time control must be at most {} seconds (got '{}')", MAX_TIME.as_secs(), text));
+        }
+        Ok(Some(time_control))
+    }
+
+    pub fn to_notation(&self) -> String {
+        match self {
+            TimeControl::SuddenDeath { base } => base.as_secs().to_string(),
+            TimeControl::Fischer { base, increment } => format!("{}+{}", base.as_secs(), increment.as_secs()),
+            TimeControl::Bronstein { base, delay } => format!("{}d{}", base.as_secs(), delay.as_secs()),
+        }
+    }
+
+    pub fn base(&self) -> Duration {
+        match *self {
+            TimeControl::SuddenDeath { base } | TimeControl::Fischer { base, .. } | TimeControl::Bronstein { base, .. } => base,
+        }
+    }
+
+    /// Clock time a move taking `elapsed` uses up.
+    fn charge(&self, elapsed: Duration) -> Duration {
+        match *self {
+            TimeControl::Bronstein { delay, .. } => elapsed.saturating_sub(delay),
+            _ => elapsed,
+        }
+    }
+}
+
+impl GameClock {
+
+    /// Both players with the base time and the clocks stopped.
+    pub fn build(time_control: TimeControl) -> GameClock {
+        GameClock { time_control, remaining: [time_control.base(); 2], running: None }
+    }
+
+    /// Time left to `player` at `now`.
+    pub fn remaining_at(&self, player: usize, now: Instant) -> Duration {
+        match self.running {
+            Some((running, since)) if running == player => {
+                self.remaining[player].saturating_sub(self.time_control.charge(now.saturating_duration_since(since)))
+            }
+            _ => self.remaining[player],
+        }
+    }
+
+    /// Starts the clock of `player`.
+    pub fn start(&mut self, player: usize, now: Instant) {
+        self.running = Some((player, now));
+    }
+
+    /// Ends the move being made at `now`, adding the increment unless the player ran out of time.
+    pub fn stop(&mut self, now: Instant) {
+        let Some((player, _)) = self.running else { return };
+        self.remaining[player] = self.remaining_at(player, now);
+        self.running = None;
+        if let TimeControl::Fischer { increment, .. } = self.time_control
+            && !self.remaining[player].is_zero()
+        {
+            self.remaining[player] = self.remaining[player].saturating_add(increment);
+        }
+    }
+
+    /// Ends the move being made and starts the clock of `next_player`, who may be the same
+    /// player again after an extra turn.
+    pub fn press(&mut self, next_player: usize, now: Instant) {
+        self.stop(now);
+        self.start(next_player, now);
+    }
+
+    /// Stops the clocks when the player to move has run out of time and returns that player.
+    pub fn flag(&mut self, now: Instant) -> Option<usize> {
+        let (player, _) = self.running?;
+        if !self.remaining_at(player, now).is_zero() {
+            return None;
+        }
+        self.remaining[player] = Duration::ZERO;
+        self.running = None;
+        Some(player)
+    }
+
+    /// Player who ran out of time.
+    pub fn out_of_time(&self) -> Option<usize> {
+        self.remaining.iter().position(Duration::is_zero)
+    }
+
+    /// Time an engine may spend on the next move of `player`: a share of what is left, plus
+    /// what the move gets back.
+    pub fn move_budget(&self, player: usize, now: Instant) -> Duration {
+        let bonus = match self.time_control {
+            TimeControl::SuddenDeath { .. } => Duration::ZERO,
+            TimeControl::Fischer { increment, .. } => increment,
+            TimeControl::Bronstein { delay, .. } => delay,
+        };
+        let remaining = self.remaining_at(player, now);
+        (remaining / 20).saturating_add(bonus).min(remaining / 2)
+    }
+}
+
+/// Clock time as shown to players, `4:05` or `0:09.3` in the last ten seconds.
+pub fn format_clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 10 {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
@@ -1 +1,5 @@
-pub mod game;
\ No newline at end of file
+pub mod ai;
+pub mod game;
+pub mod net;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
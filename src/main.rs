@@ -1,149 +1,318 @@
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
-    execute, terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+mod commands;
+mod tui;
 
-pub mod game;
+use kalah::{ ai, game, net };
 
+use ai::difficulty::{ Level };
+use ai::external_engine::{ DEFAULT_TIMEOUT };
+use commands::analysis::{ analyze };
+use commands::engine::{ play_match, run_engine, solve };
+use commands::generate::{ build_book, generate_endgame, tune };
+use commands::net::{ connect, run_bot, serve };
+use commands::play::{ play };
+use commands::{ build_engine, load_book, load_endgame, load_evaluator };
 use game::game_config::{ GameConfig };
-use game::game_field::{ GameField };
-use game::game_process::{ GameProcess };
-use game::game_status::{ GameStatus };
-use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    widgets::{Block, Borders, Paragraph},
-    Terminal,
-};
-use std::{char, error::Error, io};
+use game::position::{ Position };
+use game::time_control::{ TimeControl };
+use net::server::{ DEFAULT_GRACE_PERIOD, DEFAULT_PORT };
+use std::{error::Error, time::Duration};
 
+const DEFAULT_MOVE_TIME_MS: u64 = 2000;
+const DEFAULT_ENDGAME_SEEDS: usize = 12;
+const USAGE: &str = "Usage: kalah [solve | endgame | book | tune | engine | match <engine command> | serve | connect <address> | bot <address> | analyze <record>] [--review] [--timeout <ms>] [--listen <address>] [--web <address>] [--records <dir>] [--record-chat] [--grace <s>] [--saved-games <dir>] [--name <name>] [--holes <n>] [--stones <n>] [--no-pie] [--position <notation>] \
+[--time <s>|<s>+<inc>|<s>d<delay>] [--computer] [--level <name>] [--perfect] [--move-time <ms>] [--record <file>] [--threads <n>] [--endgame <file>] [--seeds <n>] [--output <file>] \
+[--book <file>] [--book-randomness <0..1>] [--weights <file>] [--source search|solver|self-play] [--plies <n>] [--depth <n>] [--margin <n>] [--games <n>]";
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    let game_config = GameConfig {stone_nums_in_hole: 6, hole_nums: 6, is_check_pipe_rule: true};
-    let game_field = GameField::build(&game_config);
-    let player_one_name = "Player1".to_string();
-    let player_two_name = "Player2".to_string();
-    let mut game_process = GameProcess::build(game_field, player_one_name, player_two_name, game_config);
-
-    // Game loop
-    let res = run_app(&mut terminal, &mut game_process);
-
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
-
-    if let Err(err) = res {
-        panic!("Error: {:?}", err);
-    }
+#[derive(PartialEq, Eq)]
+enum Command {
+    Play,
+    Solve,
+    GenerateEndgame,
+    BuildBook,
+    Tune,
+    Analyze,
+    /// Speaks the engine protocol on stdin and stdout.
+    Engine,
+    /// Plays an external engine.
+    Match,
+    /// Hosts network games.
+    Serve,
+    /// Plays network games.
+    Connect,
+    /// Plays network games with the engine.
+    Bot,
+}
 
-    Ok(())
+#[derive(PartialEq, Eq)]
+enum BookSource {
+    Search,
+    Solver,
+    SelfPlay,
 }
 
-fn run_app(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, game_process: &mut GameProcess) -> io::Result<()> {
-
-    loop {
-        terminal.draw(|f| {
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(2)
-                .constraints([Constraint::Length(2), Constraint::Length(1), Constraint::Min(0)].as_ref())
-                .split(f.area());
-
-            let mut side_strs: Vec<Vec<usize>> = [&game_process.game_field.side_one, &game_process.game_field.side_two].iter().map(|side|
-                side.holes.iter().map(|hole| hole.stones.len()).collect::<Vec<_>>()
-            ).collect();
-
-            side_strs[1] = side_strs[1].iter().copied().rev().collect();
-
-            let side_strs_str: Vec<String> = side_strs
-                .iter()
-                .map(|ss| 
-                    ss.iter()
-                        .map(|el| format!("[{}]", el))
-                        .collect::<Vec<String>>()
-                        .join(" ")
-                ).collect();
-
-            let top_row = Paragraph::new(format!("{}: {}", "P2".to_string(), side_strs_str[1]));
-            let bottom_row = Paragraph::new(format!("{}{}: {}", " ".repeat(4), side_strs_str[0], "P1".to_string()));
-            let midle_row = Paragraph::new(format!("{}{}{}", game_process.player_two.score, " ".repeat(side_strs_str[0].len() + 5), game_process.player_one.score));
-            
-            let player_turn_str = match game_process.is_player_one_turn {
-                true => format!("Is {} turn", game_process.player_one.name),
-                _ => format!("Is {} turn", game_process.player_two.name),
-            };
-            let block = Block::default()
-                .title(format!("Kalah - Press 1–{} to move, q to quit. {}", game_process.game_config.hole_nums, player_turn_str))
-                .borders(Borders::ALL);
-
-            f.render_widget(block, f.area());
-            f.render_widget(top_row, chunks[0]);
-            f.render_widget(midle_row, chunks[1]);
-            f.render_widget(bottom_row, chunks[2]);
-        })?;
-
-        if event::poll(std::time::Duration::from_millis(200))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-
-                    KeyCode::Char(c) if c >= '1'
-                        && c <= char::from_digit(game_process.game_config.hole_nums as u32, 10).unwrap() =>
-                    {
-                        let hole_num = c.to_digit(10).unwrap() as usize;
-
-                        match game_process.move_stones_from_hole(hole_num) {
-                            Ok(GameStatus::Finished) => {
-                                // Clear screen and draw final score
-                                terminal.draw(|f| {
-                                    let score_text = format!(
-                                        "🏁 Game Over!\n\nPlayer 1 Score: {}\nPlayer 2 Score: {}\n\nPress any key to exit.",
-                                        game_process.player_one.score,
-                                        game_process.player_two.score
-                                    );
-
-                                    let block = Block::default().title("Kalah").borders(Borders::ALL);
-                                    let paragraph = Paragraph::new(score_text)
-                                        .block(block)
-                                        .alignment(ratatui::layout::Alignment::Center);
-
-                                    f.render_widget(paragraph, f.area());
-                                })?;
-
-                                // Wait for user to press any key before quitting
-                                loop {
-                                    if let Event::Key(_) = event::read()? {
-                                        return Ok(()); // Exit the game
-                                    }
-                                }
-                            }
-
-                            Ok(_) => continue, // Game continues
-
-                            Err(e) => {
-                                // Optional: Show error message if needed
-                                // eprintln!("Move error: {}", e);
-                                continue;
-                            }
-                        }
-                    }
-
-                    KeyCode::Char('q') => return Ok(()),
-
-                    _ => {}
+struct Options {
+    command: Command,
+    hole_nums: usize,
+    stone_nums_in_hole: usize,
+    is_check_pipe_rule: bool,
+    position: Option<Position>,
+    /// Clocks of local games, and of the games created on a server.
+    time_control: Option<TimeControl>,
+    is_computer_opponent: bool,
+    /// Chosen on the setup screen when the computer plays and no level is given.
+    level: Option<Level>,
+    move_time: Duration,
+    record_path: Option<String>,
+    /// Game record to analyze.
+    input_path: Option<String>,
+    /// External engine program and arguments to play a match against.
+    engine_command: Option<String>,
+    timeout: Duration,
+    /// Address the server listens on.
+    listen_address: String,
+    /// Address the server serves the board page and WebSocket endpoint on.
+    web_address: Option<String>,
+    /// Directory the server writes records of finished games to.
+    records_path: Option<String>,
+    is_chat_recorded: bool,
+    /// Time a server player may be away from a game in progress before forfeiting it.
+    grace_period: Duration,
+    /// Directory the server saves games in progress to, and resumes them from.
+    saved_games_path: Option<String>,
+    /// Server to connect to.
+    server_address: Option<String>,
+    /// Player name sent to the server.
+    name: Option<String>,
+    is_review: bool,
+    threads: usize,
+    endgame_path: Option<String>,
+    endgame_seeds: usize,
+    output_path: Option<String>,
+    book_path: Option<String>,
+    book_randomness: f64,
+    book_source: BookSource,
+    book_margin: i32,
+    weights_path: Option<String>,
+    /// Book plies, or random opening plies when tuning.
+    plies: Option<usize>,
+    depth: Option<u8>,
+    games: Option<usize>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        command: Command::Play,
+        hole_nums: 6,
+        stone_nums_in_hole: 6,
+        is_check_pipe_rule: true,
+        position: None,
+        time_control: None,
+        is_computer_opponent: false,
+        level: None,
+        move_time: Duration::from_millis(DEFAULT_MOVE_TIME_MS),
+        record_path: None,
+        input_path: None,
+        engine_command: None,
+        timeout: DEFAULT_TIMEOUT,
+        listen_address: format!("0.0.0.0:{}", DEFAULT_PORT),
+        web_address: None,
+        records_path: None,
+        is_chat_recorded: false,
+        grace_period: DEFAULT_GRACE_PERIOD,
+        saved_games_path: None,
+        server_address: None,
+        name: None,
+        is_review: false,
+        threads: 1,
+        endgame_path: None,
+        endgame_seeds: DEFAULT_ENDGAME_SEEDS,
+        output_path: None,
+        book_path: None,
+        book_randomness: 0.0,
+        book_source: BookSource::Search,
+        book_margin: 1,
+        weights_path: None,
+        plies: None,
+        depth: None,
+        games: None,
+    };
+
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if(|arg| arg == "solve").is_some() {
+        options.command = Command::Solve;
+    } else if args.next_if(|arg| arg == "endgame").is_some() {
+        options.command = Command::GenerateEndgame;
+    } else if args.next_if(|arg| arg == "book").is_some() {
+        options.command = Command::BuildBook;
+    } else if args.next_if(|arg| arg == "tune").is_some() {
+        options.command = Command::Tune;
+    } else if args.next_if(|arg| arg == "engine").is_some() {
+        options.command = Command::Engine;
+    } else if args.next_if(|arg| arg == "match").is_some() {
+        options.command = Command::Match;
+        options.engine_command = Some(args.next_if(|arg| !arg.starts_with("--")).ok_or("match needs an engine command")?);
+    } else if args.next_if(|arg| arg == "serve").is_some() {
+        options.command = Command::Serve;
+    } else if args.next_if(|arg| arg == "connect").is_some() {
+        options.command = Command::Connect;
+        options.server_address = Some(args.next_if(|arg| !arg.starts_with("--")).ok_or("connect needs a server address such as localhost:7878")?);
+    } else if args.next_if(|arg| arg == "bot").is_some() {
+        options.command = Command::Bot;
+        options.server_address = Some(args.next_if(|arg| !arg.starts_with("--")).ok_or("bot needs a server address such as localhost:7878")?);
+    } else if args.next_if(|arg| arg == "analyze").is_some() {
+        options.command = Command::Analyze;
+        options.input_path = Some(args.next_if(|arg| !arg.starts_with("--")).ok_or("analyze needs a game record file")?);
+    }
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--holes" | "--stones" => {
+                let value = args.next().ok_or(format!("{} needs a value", arg))?;
+                let count = value.parse().map_err(|_| format!("Invalid {} '{}'", arg, value))?;
+                if arg == "--holes" { options.hole_nums = count } else { options.stone_nums_in_hole = count }
+            }
+            "--no-pie" => options.is_check_pipe_rule = false,
+            "--position" => {
+                let value = args.next().ok_or("--position needs a position in notation")?;
+                options.position = Some(Position::parse(&value)?);
+            }
+            "--time" => options.time_control = TimeControl::parse(&args.next().ok_or("--time needs a time control such as 300+5")?)?,
+            "--computer" => options.is_computer_opponent = true,
+            "--level" => {
+                options.is_computer_opponent = true;
+                options.level = Some(Level::parse(&args.next().ok_or("--level needs a level name")?)?);
+            }
+            "--perfect" => {
+                options.is_computer_opponent = true;
+                options.level = Some(Level::Perfect);
+            }
+            "--move-time" => {
+                let value = args.next().ok_or("--move-time needs a value in milliseconds")?;
+                let millis = value.parse().map_err(|_| format!("Invalid --move-time '{}'", value))?;
+                options.move_time = Duration::from_millis(millis);
+            }
+            "--timeout" => {
+                let value = args.next().ok_or("--timeout needs a value in milliseconds")?;
+                let millis = value.parse().map_err(|_| format!("Invalid --timeout '{}'", value))?;
+                options.timeout = Duration::from_millis(millis);
+            }
+            "--review" => options.is_review = true,
+            "--listen" => options.listen_address = args.next().ok_or("--listen needs an address such as 0.0.0.0:7878")?,
+            "--web" => options.web_address = Some(args.next().ok_or("--web needs an address such as 0.0.0.0:8080")?),
+            "--records" => options.records_path = Some(args.next().ok_or("--records needs a directory")?),
+            "--record-chat" => options.is_chat_recorded = true,
+            "--grace" => {
+                let value = args.next().ok_or("--grace needs a value in seconds")?;
+                let seconds = value.parse().map_err(|_| format!("Invalid --grace '{}'", value))?;
+                options.grace_period = Duration::from_secs(seconds);
+            }
+            "--saved-games" => options.saved_games_path = Some(args.next().ok_or("--saved-games needs a directory")?),
+            "--name" => options.name = Some(args.next().ok_or("--name needs a player name")?),
+            "--record" => options.record_path = Some(args.next().ok_or("--record needs a file")?),
+            "--threads" => {
+                let value = args.next().ok_or("--threads needs a value")?;
+                options.threads = value.parse().ok().filter(|&threads| threads > 0).ok_or(format!("Invalid --threads '{}'", value))?;
+            }
+            "--endgame" => options.endgame_path = Some(args.next().ok_or("--endgame needs a database file")?),
+            "--seeds" => {
+                let value = args.next().ok_or("--seeds needs a value")?;
+                options.endgame_seeds = value.parse().map_err(|_| format!("Invalid --seeds '{}'", value))?;
+            }
+            "--output" => options.output_path = Some(args.next().ok_or("--output needs a file")?),
+            "--book" => options.book_path = Some(args.next().ok_or("--book needs a book file")?),
+            "--book-randomness" => {
+                let value = args.next().ok_or("--book-randomness needs a value")?;
+                options.book_randomness = value.parse().ok().filter(|randomness| (0.0..=1.0).contains(randomness))
+                    .ok_or(format!("Invalid --book-randomness '{}', expected 0 to 1", value))?;
+            }
+            "--weights" => options.weights_path = Some(args.next().ok_or("--weights needs a weights file")?),
+            "--source" => {
+                options.book_source = match args.next().as_deref() {
+                    Some("search") => BookSource::Search,
+                    Some("solver") => BookSource::Solver,
+                    Some("self-play") => BookSource::SelfPlay,
+                    _ => return Err("--source needs search, solver or self-play".into()),
+                };
+            }
+            "--plies" | "--depth" | "--margin" | "--games" => {
+                let value = args.next().ok_or(format!("{} needs a value", arg))?;
+                let invalid = || format!("Invalid {} '{}'", arg, value);
+                match arg.as_str() {
+                    "--plies" => options.plies = Some(value.parse().map_err(|_| invalid())?),
+                    "--depth" => options.depth = Some(value.parse().ok().filter(|&depth| depth > 0).ok_or_else(invalid)?),
+                    "--margin" => options.book_margin = value.parse().ok().filter(|&margin| margin >= 0).ok_or_else(invalid)?,
+                    _ => options.games = Some(value.parse().map_err(|_| invalid())?),
                 }
             }
+            _ => return Err(format!("Unknown argument '{}'\n{}", arg, USAGE)),
         }
     }
+
+    Ok(options)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = parse_options().unwrap_or_else(|err| exit_with_error(&err));
+    let game_config = GameConfig::build(options.stone_nums_in_hole, options.hole_nums, options.is_check_pipe_rule)
+        .unwrap_or_else(|err| exit_with_error(err))
+        .with_time_control(options.time_control);
+
+    if options.command == Command::GenerateEndgame {
+        return generate_endgame(&options);
+    }
+
+    if options.command == Command::Serve {
+        return serve(&options, game_config);
+    }
+
+    if options.command == Command::Connect {
+        return connect(&options, &game_config);
+    }
+
+    let evaluator = load_evaluator(&options);
+
+    if options.command == Command::Tune {
+        return tune(&options, &game_config);
+    }
+
+    let endgame = load_endgame(&options, &game_config);
+
+    if options.command == Command::Solve {
+        return solve(&options, &game_config, endgame);
+    }
+
+    if options.command == Command::BuildBook {
+        return build_book(&options, &game_config, endgame, evaluator);
+    }
+
+    if options.command == Command::Analyze {
+        return analyze(&options, endgame, evaluator);
+    }
+
+    let book = load_book(&options, &game_config);
+
+    if options.command == Command::Engine {
+        let level = options.level.unwrap_or(Level::Master);
+        let engine = build_engine(&options, level, endgame.as_ref(), &evaluator, book.as_ref());
+        return run_engine(level, engine, game_config);
+    }
+
+    if options.command == Command::Match {
+        let level = options.level.unwrap_or(Level::Master);
+        let engine = build_engine(&options, level, endgame.as_ref(), &evaluator, book.as_ref());
+        return play_match(&options, &game_config, level, engine);
+    }
+
+    if options.command == Command::Bot {
+        let level = options.level.unwrap_or(Level::Master);
+        let engine = build_engine(&options, level, endgame.as_ref(), &evaluator, book.as_ref());
+        return run_bot(&options, &game_config, level, engine);
+    }
+
+    play(&options, game_config, endgame, evaluator, book)
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
 }
@@ -0,0 +1,5 @@
+pub mod protocol;
+pub mod server;
+pub mod client;
+pub mod websocket;
+pub mod bot;
@@ -0,0 +1,100 @@
+use std::time::{ Duration, Instant };
+
+use crate::ai::alpha_beta::SearchLimits;
+use crate::ai::compact_board::CompactBoard;
+use crate::ai::engine::Engine;
+use crate::ai::match_runner::{ EngineController, PlayerController };
+use crate::game::game_config::GameConfig;
+
+use super::client::{ Client, ClientEvent };
+use super::protocol::{ ClientMessage, Phase, ServerMessage };
+
+/// What a bot reports while it plays.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BotEvent {
+    Connected,
+    Disconnected(String),
+    /// Seated in a game, 1 for player one.
+    Playing { game: u64, player: u8 },
+    Finished { game: u64, result: String },
+    /// A message the server did not accept.
+    Rejected(String),
+}
+
+/// Plays games hosted by `kalah serve` with an engine: creates a game of `game_config`, plays it
+/// once somebody joins and creates the next one when it is over.
+pub struct Bot<E: Engine> {
+    client: Client,
+    controller: EngineController<E>,
+    game_config: GameConfig,
+    /// Longest search of a move, shortened to fit the clock.
+    move_time: Duration,
+}
+
+impl<E: Engine> Bot<E> {
+
+    /// Connects in the background, reconnecting whenever the connection is lost.
+    pub fn connect(address: String, name: String, engine: E, game_config: GameConfig, move_time: Duration) -> Bot<E> {
+        let controller = EngineController::build(name.clone(), engine, SearchLimits::move_time(move_time));
+        Bot { client: Client::connect(address, name), controller, game_config, move_time }
+    }
+
+    /// Plays until the engine fails, which is the only way it returns.
+    pub fn run(&mut self, mut on_event: impl FnMut(BotEvent)) -> Result<(), String> {
+        let mut player = None;
+        let mut is_creating = false;
+        // Turn we moved on, so a repeated state does not make us move twice
+        let mut moved_on = None;
+
+        loop {
+            let Some(event) = self.client.next_timeout(Duration::from_secs(1)) else { continue };
+            match event {
+                ClientEvent::Connected => on_event(BotEvent::Connected),
+                ClientEvent::Disconnected(reason) => {
+                    on_event(BotEvent::Disconnected(reason));
+                    is_creating = false;
+                }
+                ClientEvent::Message(ServerMessage::Games { .. }) if !is_creating => {
+                    self.client.send(ClientMessage::CreateGame {
+                        holes: Some(self.game_config.hole_nums),
+                        stones: Some(self.game_config.stone_nums_in_hole),
+                        pie: Some(self.game_config.is_check_pipe_rule),
+                        time_control: self.game_config.time_control.map(|time_control| time_control.to_notation()),
+                    });
+                    is_creating = true;
+                }
+                ClientEvent::Message(ServerMessage::Welcome { game, player: seat, .. }) => {
+                    on_event(BotEvent::Playing { game, player: seat });
+                    player = Some(seat as usize - 1);
+                    is_creating = false;
+                    moved_on = None;
+                    self.controller.new_game(&self.game_config)?;
+                }
+                ClientEvent::Message(ServerMessage::State(state)) => {
+                    let Some(player) = player else { continue };
+                    match state.phase {
+                        Phase::Finished => {
+                            on_event(BotEvent::Finished { game: state.game, result: state.result_str() });
+                            self.client.send(ClientMessage::LeaveGame);
+                        }
+                        Phase::Playing if state.position.is_player_one_turn == (player == 0) && moved_on != Some(state.position.total_turns) => {
+                            let board = CompactBoard::from_position(&state.position, self.game_config.is_check_pipe_rule)?;
+                            // Keep to a share of the clock
+                            let move_time = state.clock_at(Instant::now())
+                                .map_or(self.move_time, |clock| self.move_time.min(clock.move_budget(player, Instant::now())));
+                            self.controller.set_limits(SearchLimits::move_time(move_time));
+                            self.client.send(ClientMessage::Move { hole: self.controller.choose_move(&board)? });
+                            moved_on = Some(state.position.total_turns);
+                        }
+                        _ => {}
+                    }
+                }
+                ClientEvent::Message(ServerMessage::Error { message }) => {
+                    on_event(BotEvent::Rejected(message));
+                    is_creating = false;
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::io::{ BufRead, BufReader, Write };
+use std::net::{ Shutdown, TcpStream };
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc::{ self, Receiver, RecvTimeoutError, Sender };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use super::protocol::{ ClientMessage, ServerMessage };
+
+/// Time between pings while connected.
+pub const PING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Time between attempts to reach the server after the connection is lost.
+pub const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the network thread checks for messages to send and a closed connection.
+const TICK: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientEvent {
+    /// Connected, or connected again, and said hello.
+    Connected,
+    /// The connection failed or was lost. The client keeps trying to reconnect.
+    Disconnected(String),
+    Message(ServerMessage),
+    /// A pong came back after `round_trip`.
+    Latency { round_trip: Duration, latencies: [Option<u64>; 2] },
+}
+
+/// Pings of one connection, shared by its reader and writer threads.
+#[derive(Default)]
+struct Pings {
+    /// Ping id and send time of the ping waiting for its pong.
+    pending: Option<(u64, Instant)>,
+    /// Round trip of the last answered ping, reported to the server with the next one.
+    last_round_trip: Option<Duration>,
+}
+
+/// Connection to a game server run on a background thread. After the connection drops the
+/// client reconnects and resumes with the token of its seat, so the server gives the seat back.
+pub struct Client {
+    commands: Sender<ClientMessage>,
+    events: Receiver<ClientEvent>,
+    is_running: Arc<AtomicBool>,
+}
+
+impl Client {
+
+    pub fn connect(address: String, name: String) -> Client {
+        let (commands, command_receiver) = mpsc::channel();
+        let (event_sender, events) = mpsc::channel();
+        let is_running = Arc::new(AtomicBool::new(true));
+
+        let thread_is_running = Arc::clone(&is_running);
+        thread::spawn(move || run_connection(&address, &name, command_receiver, event_sender, thread_is_running));
+
+        Client { commands, events, is_running }
+    }
+
+    /// Sends `message` once connected. Messages given while disconnected are dropped.
+    pub fn send(&self, message: ClientMessage) {
+        _ = self.commands.send(message);
+    }
+
+    pub fn try_next(&self) -> Option<ClientEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Waits up to `timeout` for the next event.
+    pub fn next_timeout(&self, timeout: Duration) -> Option<ClientEvent> {
+        self.events.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for Client {
+
+    fn drop(&mut self) {
+        self.is_running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Connects, serves the connection until it drops and starts over, until the client is dropped.
+fn run_connection(
+    address: &str,
+    name: &str,
+    commands: Receiver<ClientMessage>,
+    events: Sender<ClientEvent>,
+    is_running: Arc<AtomicBool>,
+) {
+    let mut last_error = None;
+    // Token of the seat taken last, until the client is back in the lobby
+    let token = Arc::new(Mutex::new(None::<String>));
+
+    while is_running.load(Ordering::Relaxed) {
+        let reason = match TcpStream::connect(address) {
+            Ok(stream) => {
+                last_error = None;
+                serve(stream, name, &token, &commands, &events, &is_running)
+            }
+            Err(err) => format!("Cannot connect to {}: {}", address, err),
+        };
+
+        // Repeated failures while the server is away are reported once
+        if last_error.as_ref() != Some(&reason) {
+            _ = events.send(ClientEvent::Disconnected(reason.clone()));
+            last_error = Some(reason);
+        }
+
+        let retry_at = Instant::now() + RECONNECT_INTERVAL;
+        while is_running.load(Ordering::Relaxed) && Instant::now() < retry_at {
+            thread::sleep(TICK);
+        }
+    }
+}
+
+/// Runs one connection and returns why it ended.
+fn serve(
+    stream: TcpStream,
+    name: &str,
+    token: &Arc<Mutex<Option<String>>>,
+    commands: &Receiver<ClientMessage>,
+    events: &Sender<ClientEvent>,
+    is_running: &AtomicBool,
+) -> String {
+    _ = stream.set_nodelay(true);
+    let Ok(reader_stream) = stream.try_clone() else { return "Cannot read from the server".to_string() };
+    let mut writer = stream;
+
+    let pings = Arc::new(Mutex::new(Pings::default()));
+    let (closed_sender, closed) = mpsc::channel::<()>();
+
+    let reader_events = events.clone();
+    let reader_pings = Arc::clone(&pings);
+    let reader_token = Arc::clone(token);
+    thread::spawn(move || {
+        for line in BufReader::new(reader_stream).lines() {
+            let Ok(line) = line else { break };
+            let event = match ServerMessage::parse(&line) {
+                Ok(ServerMessage::Pong { id, latencies }) => {
+                    let mut pings = reader_pings.lock().unwrap();
+                    match pings.pending {
+                        Some((ping_id, sent)) if ping_id == id => {
+                            let round_trip = sent.elapsed();
+                            pings.pending = None;
+                            pings.last_round_trip = Some(round_trip);
+                            ClientEvent::Latency { round_trip, latencies }
+                        }
+                        _ => continue,
+                    }
+                }
+                Ok(message) => {
+                    match &message {
+                        ServerMessage::Welcome { token, .. } => *reader_token.lock().unwrap() = Some(token.clone()),
+                        ServerMessage::Games { .. } => *reader_token.lock().unwrap() = None,
+                        _ => {}
+                    }
+                    ClientEvent::Message(message)
+                }
+                Err(_) => continue,
+            };
+            if reader_events.send(event).is_err() {
+                break;
+            }
+        }
+        drop(closed_sender);
+    });
+
+    // Moves typed while disconnected would be stale
+    while commands.try_recv().is_ok() {}
+
+    let mut send = |message: &ClientMessage| writeln!(writer, "{}", message.to_line()).and_then(|_| writer.flush());
+    let hello = match token.lock().unwrap().clone() {
+        Some(token) => ClientMessage::Resume { name: name.to_string(), token },
+        None => ClientMessage::Hello { name: name.to_string() },
+    };
+    if send(&hello).is_err() {
+        return "Lost the connection to the server".to_string();
+    }
+    _ = events.send(ClientEvent::Connected);
+
+    let mut next_ping_id = 0;
+    let mut next_ping = Instant::now();
+
+    let reason = loop {
+        if !is_running.load(Ordering::Relaxed) {
+            break "Closed".to_string();
+        }
+        if closed.try_recv() == Err(mpsc::TryRecvError::Disconnected) {
+            break "Lost the connection to the server".to_string();
+        }
+
+        if Instant::now() >= next_ping {
+            let mut pings = pings.lock().unwrap();
+            // A ping still unanswered took at least this long
+            if let Some((_, sent)) = pings.pending {
+                pings.last_round_trip = Some(sent.elapsed());
+            }
+            next_ping_id += 1;
+            pings.pending = Some((next_ping_id, Instant::now()));
+            let latency_ms = pings.last_round_trip.map(|round_trip| round_trip.as_millis() as u64);
+            drop(pings);
+
+            if send(&ClientMessage::Ping { id: next_ping_id, latency_ms }).is_err() {
+                break "Lost the connection to the server".to_string();
+            }
+            next_ping = Instant::now() + PING_INTERVAL;
+        }
+
+        match commands.recv_timeout(TICK) {
+            Ok(message) => {
+                if send(&message).is_err() {
+                    break "Lost the connection to the server".to_string();
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break "Closed".to_string(),
+        }
+    };
+
+    _ = writer.shutdown(Shutdown::Both);
+    reason
+}
@@ -0,0 +1,281 @@
+use std::time::{ Duration, Instant };
+
+use serde::{ Deserialize, Serialize };
+
+use crate::game::position::Position;
+use crate::game::time_control::{ GameClock, TimeControl };
+
+/// Longest player name the server accepts.
+pub const MAX_NAME_LEN: usize = 24;
+
+/// Longest chat message the server accepts.
+pub const MAX_CHAT_LEN: usize = 200;
+
+/// Longest message line the server reads, in bytes. A client sending a longer one is disconnected.
+pub const MAX_LINE_LEN: usize = 4096;
+
+/// Message from a client to the server, one JSON object per line:
+///
+/// ```text
+/// {"type":"hello","name":"Alice"}                   enter the lobby
+/// {"type":"resume","name":"Alice","token":"5f0c..."}  say hello and take the seat of the token again
+/// {"type":"list_games"}                             answered by the games
+/// {"type":"create_game","holes":6,"stones":4,"time_control":"300+5"}  open a game and take its first seat
+/// {"type":"join_game","game":3}                     take a free seat of a game
+/// {"type":"watch_game","game":3}                    follow a game as a spectator
+/// {"type":"leave_game"}                             go back to the lobby
+/// {"type":"move","hole":3}                          sow from a hole (1-based) of the own side
+/// {"type":"chat","text":"good luck"}                say something in the lobby or the game
+/// {"type":"emote","emote":"good_game"}              a canned chat line, see `Emote`
+/// {"type":"ping","id":7,"latency_ms":23}            answered by a pong, with the last measured round trip
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Hello { name: String },
+    /// A hello from a player coming back, with the token of the seat. Without a game in
+    /// progress for the token it is a plain hello.
+    Resume { name: String, token: String },
+    ListGames,
+    /// Settings left out are the server's.
+    CreateGame {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        holes: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stones: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pie: Option<bool>,
+        /// In `TimeControl` notation, `-` for no clock.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time_control: Option<String>,
+    },
+    JoinGame { game: u64 },
+    WatchGame { game: u64 },
+    LeaveGame,
+    Move { hole: usize },
+    Chat { text: String },
+    Emote { emote: Emote },
+    Ping { id: u64, latency_ms: Option<u64> },
+}
+
+/// Message from the server to a client, one JSON object per line:
+///
+/// ```text
+/// {"type":"games","games":[...]}                 the lobby, again after every change while in it
+/// {"type":"welcome","game":3,"player":1,"token":"5f0c..."}  seat taken, player 1 or 2, and its token
+/// {"type":"watching","game":3}                   following a game
+/// {"type":"state","game":3,"phase":"playing",...}  the game after every change, see `GameState`
+/// {"type":"chat","game":3,"from":"Bob","text":"good luck","is_emote":false}  a chat line, game null in the lobby
+/// {"type":"pong","id":7,"latencies":[23,null]}   answer to a ping with the players' round trips
+/// {"type":"error","message":"Not your turn"}     the last message was rejected
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Games { games: Vec<GameSummary> },
+    /// `token` takes the seat back after the connection is lost, see `ClientMessage::Resume`.
+    Welcome { game: u64, player: u8, token: String },
+    Watching { game: u64 },
+    State(GameState),
+    Chat(ChatLine),
+    /// Latencies are the round trips last reported by player one and player two, in milliseconds.
+    Pong { id: u64, latencies: [Option<u64>; 2] },
+    Error { message: String },
+}
+
+/// Canned chat lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Emote {
+    Wave,
+    GoodLuck,
+    GoodGame,
+    WellPlayed,
+    Thinking,
+    Oops,
+}
+
+/// A chat message or emote in the lobby or a game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatLine {
+    /// None in the lobby.
+    pub game: Option<u64>,
+    pub from: String,
+    pub text: String,
+    /// Whether `text` is an emote to show as "* Alice waves".
+    pub is_emote: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// A seat is still free.
+    Waiting,
+    Playing,
+    Finished,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeatInfo {
+    pub name: String,
+    pub is_connected: bool,
+}
+
+/// A game as listed in the lobby.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSummary {
+    pub id: u64,
+    pub holes: usize,
+    pub stones: usize,
+    pub pie: bool,
+    pub phase: Phase,
+    pub players: [Option<SeatInfo>; 2],
+    pub spectators: usize,
+    /// In `TimeControl` notation, None without clocks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_control: Option<String>,
+}
+
+/// Everything a client needs to draw the game, sent to players and spectators alike:
+///
+/// ```text
+/// {"type":"state","game":3,"phase":"playing",
+///  "players":[{"name":"Alice","is_connected":true},{"name":"Bob","is_connected":true}],
+///  "position":{"side_one":[0,5,5,5,5,4],"side_one_store":1,"side_two":[4,4,4,4,4,4],"side_two_store":0,
+///              "is_player_one_turn":false,"total_turns":1},
+///  "scores":[1,0],"last_move":1,"forfeit_in_ms":[null,null]}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameState {
+    pub game: u64,
+    pub phase: Phase,
+    /// Player one and player two, None while the seat is free.
+    pub players: [Option<SeatInfo>; 2],
+    pub position: Position,
+    pub scores: [usize; 2],
+    pub last_move: Option<usize>,
+    /// Time left to a player away from the game in progress before forfeiting it.
+    #[serde(default)]
+    pub forfeit_in_ms: [Option<u64>; 2],
+    /// Player 1 or 2 who lost the game by staying away too long.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forfeit: Option<u8>,
+    /// None for games without clocks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<Box<ClockState>>,
+    /// Player 1 or 2 who lost the game on time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_of_time: Option<u8>,
+}
+
+/// The clocks when a state is sent:
+///
+/// ```text
+/// "clock":{"time_control":"300+5","remaining_ms":[281500,300000],"elapsed_ms":1200}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockState {
+    pub time_control: String,
+    /// Time left to player one and player two, before the move being made.
+    pub remaining_ms: [u64; 2],
+    /// Time the player to move has spent on the move, None while the clocks are stopped.
+    pub elapsed_ms: Option<u64>,
+}
+
+impl ClientMessage {
+
+    pub fn parse(line: &str) -> Result<ClientMessage, String> {
+        serde_json::from_str(line).map_err(|err| format!("Invalid message: {}", err))
+    }
+
+    pub fn to_line(&self) -> String {
+        serde_json::to_string(self).expect("messages serialize")
+    }
+}
+
+impl Emote {
+
+    /// Emote for a chat command such as `/gg`.
+    pub fn parse(command: &str) -> Option<Emote> {
+        match command {
+            "wave" | "hi" => Some(Emote::Wave),
+            "gl" => Some(Emote::GoodLuck),
+            "gg" => Some(Emote::GoodGame),
+            "wp" => Some(Emote::WellPlayed),
+            "think" | "hmm" => Some(Emote::Thinking),
+            "oops" => Some(Emote::Oops),
+            _ => None,
+        }
+    }
+
+    /// What the player does, after their name.
+    pub fn text(self) -> &'static str {
+        match self {
+            Emote::Wave => "waves",
+            Emote::GoodLuck => "wishes good luck",
+            Emote::GoodGame => "says good game",
+            Emote::WellPlayed => "says well played",
+            Emote::Thinking => "is thinking",
+            Emote::Oops => "says oops",
+        }
+    }
+}
+
+impl ChatLine {
+
+    /// The line as shown to players: `Alice: hi` or `* Alice waves`.
+    pub fn display(&self) -> String {
+        if self.is_emote {
+            format!("* {} {}", self.from, self.text)
+        } else {
+            format!("{}: {}", self.from, self.text)
+        }
+    }
+}
+
+impl ClockState {
+
+    pub fn from_clock(clock: &GameClock, now: Instant) -> ClockState {
+        ClockState {
+            time_control: clock.time_control.to_notation(),
+            remaining_ms: clock.remaining.map(|remaining| remaining.as_millis() as u64),
+            elapsed_ms: clock.running.map(|(_, since)| now.saturating_duration_since(since).as_millis() as u64),
+        }
+    }
+}
+
+impl GameState {
+
+    /// The clocks as they run on the server, for a state received at `received`.
+    pub fn clock_at(&self, received: Instant) -> Option<GameClock> {
+        let clock = self.clock.as_ref()?;
+        let time_control = TimeControl::parse(&clock.time_control).ok()??;
+        let player = if self.position.is_player_one_turn { 0 } else { 1 };
+        let running = clock.elapsed_ms.map(|elapsed_ms| {
+            let elapsed = Duration::from_millis(elapsed_ms);
+            (player, received.checked_sub(elapsed).unwrap_or(received))
+        });
+        Some(GameClock { time_control, remaining: clock.remaining_ms.map(Duration::from_millis), running })
+    }
+
+    /// How a finished game ended.
+    pub fn result_str(&self) -> String {
+        let name = |player: u8| self.players[player as usize - 1].as_ref().map_or("?", |seat| seat.name.as_str());
+        match (self.out_of_time, self.forfeit) {
+            (Some(player), _) => format!("{} lost on time", name(player)),
+            (_, Some(player)) => format!("{} forfeited", name(player)),
+            _ => format!("{} to {}", self.scores[0], self.scores[1]),
+        }
+    }
+}
+
+impl ServerMessage {
+
+    pub fn parse(line: &str) -> Result<ServerMessage, String> {
+        serde_json::from_str(line).map_err(|err| format!("Invalid message: {}", err))
+    }
+
+    pub fn to_line(&self) -> String {
+        serde_json::to_string(self).expect("messages serialize")
+    }
+}
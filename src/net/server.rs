@@ -0,0 +1,916 @@
+use std::collections::{ BTreeMap, HashMap, VecDeque };
+use std::fs;
+use std::io::{ self, BufRead, BufReader, Read, Write };
+use std::net::{ SocketAddr, TcpListener, TcpStream, ToSocketAddrs };
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex, MutexGuard, PoisonError };
+use std::sync::mpsc::{ self, Sender };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use serde::{ Deserialize, Serialize };
+
+use crate::game::game_config::GameConfig;
+use crate::game::game_field::GameField;
+use crate::game::game_process::GameProcess;
+use crate::game::game_record::GameRecord;
+use crate::game::game_status::GameStatus;
+use crate::game::position::Position;
+use crate::game::time_control::{ GameClock, TimeControl };
+
+use super::protocol::{ ChatLine, ClientMessage, ClockState, GameState, GameSummary, Phase, SeatInfo, ServerMessage, MAX_CHAT_LEN, MAX_LINE_LEN, MAX_NAME_LEN };
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// Chat lines a client may send at once before the rate limit applies.
+pub const CHAT_BURST: u32 = 5;
+
+/// Time it takes to earn one more chat line, up to `CHAT_BURST`.
+pub const CHAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Chat lines sent to a client entering the lobby or a game.
+pub const CHAT_HISTORY: usize = 50;
+
+/// Time a player may stay away from a game in progress before forfeiting it.
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+/// How often the server looks for players whose grace period or time is over.
+const FORFEIT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Hosts any number of games for remote players and spectators. Clients say hello in the lobby,
+/// then create, join or watch games. The server owns the only `GameProcess` of every game:
+/// players send moves, the server checks them with `move_stones_from_hole` and sends the new
+/// state to everyone at the table. Every connection is served on its own thread.
+pub struct Server {
+    listener: TcpListener,
+    pub(super) lobby: Arc<Mutex<Lobby>>,
+}
+
+/// Where a connected client is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Location {
+    Lobby,
+    /// Seated in a game as player 0 or 1.
+    Playing(u64, usize),
+    Watching(u64),
+}
+
+struct Connection {
+    /// None until the client says hello.
+    name: Option<String>,
+    sender: Sender<ServerMessage>,
+    location: Location,
+    chat_limiter: RateLimiter,
+}
+
+/// Token bucket holding up to `CHAT_BURST` chat lines, refilled one every `CHAT_INTERVAL`.
+struct RateLimiter {
+    tokens: u32,
+    refilled: Instant,
+}
+
+/// A seated player. The seat is kept when the player leaves during the game, and the player
+/// takes it back by resuming with its token, or joining the game from the same connection.
+/// Nobody else may use the name while the seat is kept.
+struct Seat {
+    name: String,
+    token: String,
+    /// Id of the connection in the seat.
+    connection: Option<usize>,
+    latency_ms: Option<u64>,
+    /// When the player left the seat, starting the grace period.
+    away_since: Option<Instant>,
+    /// Moves played before the player left, the ones after are replayed on return.
+    moves_seen: usize,
+}
+
+struct ServerGame {
+    game_process: GameProcess,
+    seats: [Option<Seat>; 2],
+    spectators: Vec<usize>,
+    last_move: Option<usize>,
+    is_finished: bool,
+    /// Player 0 or 1 who lost by staying away longer than the grace period.
+    forfeit: Option<usize>,
+    /// Runs from the moment both seats are taken, None without a time control.
+    clock: Option<GameClock>,
+    /// The state after every move, for players coming back.
+    history: Vec<GameState>,
+    /// Chat lines, each with the number of moves played before it.
+    chat: Vec<(usize, ChatLine)>,
+}
+
+/// A game in progress as saved in `game-<id>.json`, to be resumed after a restart.
+#[derive(Serialize, Deserialize)]
+struct SavedGame {
+    id: u64,
+    holes: usize,
+    stones: usize,
+    pie: bool,
+    players: [SavedSeat; 2],
+    moves: Vec<usize>,
+    chat: Vec<(usize, ChatLine)>,
+    /// In `TimeControl` notation, with the time left to each player.
+    #[serde(default)]
+    clock: Option<(String, [u64; 2])>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedSeat {
+    name: String,
+    token: String,
+    moves_seen: usize,
+}
+
+/// Where the records of finished games are written.
+struct Records {
+    directory: PathBuf,
+    is_chat_recorded: bool,
+}
+
+/// Everything shared by the connection threads, of the TCP server and the WebSocket gateway.
+pub(super) struct Lobby {
+    /// Settings of created games that leave them out.
+    game_config: GameConfig,
+    next_connection_id: usize,
+    next_game_id: u64,
+    games: BTreeMap<u64, ServerGame>,
+    connections: HashMap<usize, Connection>,
+    /// Games as last sent to the clients in the lobby.
+    listed: Vec<GameSummary>,
+    /// The last `CHAT_HISTORY` lines of the lobby chat.
+    chat: VecDeque<ChatLine>,
+    records: Option<Records>,
+    grace_period: Duration,
+    /// Where games in progress are saved.
+    saved_games: Option<PathBuf>,
+}
+
+impl Server {
+
+    /// `game_config` is used for the settings a created game leaves out.
+    pub fn bind(address: impl ToSocketAddrs, game_config: GameConfig) -> io::Result<Server> {
+        let lobby = Lobby {
+            game_config,
+            next_connection_id: 0,
+            next_game_id: 1,
+            games: BTreeMap::new(),
+            connections: HashMap::new(),
+            listed: Vec::new(),
+            chat: VecDeque::new(),
+            records: None,
+            grace_period: DEFAULT_GRACE_PERIOD,
+            saved_games: None,
+        };
+        Ok(Server { listener: TcpListener::bind(address)?, lobby: Arc::new(Mutex::new(lobby)) })
+    }
+
+    /// Writes the record of every finished game to `game-<id>.txt` in `directory`, with the
+    /// game's chat when `is_chat_recorded`.
+    pub fn with_records(self, directory: impl Into<PathBuf>, is_chat_recorded: bool) -> Server {
+        lock(&self.lobby).records = Some(Records { directory: directory.into(), is_chat_recorded });
+        self
+    }
+
+    /// Gives players who leave a game in progress `grace_period` to come back before they forfeit.
+    pub fn with_grace_period(self, grace_period: Duration) -> Server {
+        lock(&self.lobby).grace_period = grace_period;
+        self
+    }
+
+    /// Saves every game in progress to `game-<id>.json` in `directory` after each change, and
+    /// resumes the games saved there. Their players get the grace period from now to come back.
+    pub fn with_saved_games(self, directory: impl Into<PathBuf>) -> Result<Server, String> {
+        let directory = directory.into();
+        let mut lobby = lock(&self.lobby);
+        lobby.load_games(&directory)?;
+        lobby.saved_games = Some(directory);
+        drop(lobby);
+        Ok(self)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections for as long as the process runs.
+    pub fn run(self) {
+        let lobby = Arc::clone(&self.lobby);
+        thread::spawn(move || loop {
+            thread::sleep(FORFEIT_CHECK_INTERVAL);
+            let mut lobby = lock(&lobby);
+            lobby.forfeit_absent(Instant::now());
+            lobby.flag_out_of_time(Instant::now());
+        });
+
+        for stream in self.listener.incoming() {
+            // A failed accept only affects that client
+            let Ok(stream) = stream else { continue };
+            let lobby = Arc::clone(&self.lobby);
+            thread::spawn(move || serve_connection(stream, lobby));
+        }
+    }
+}
+
+/// Locks the lobby even after a thread panicked while holding it, so one failed message does not
+/// take down the server for everybody. Games and connections are separate entries that only
+/// refer to each other by id, so a panic leaves at most the ones of that message half-updated,
+/// and messages tripping over them fail the same way while the other games go on.
+pub(super) fn lock(lobby: &Mutex<Lobby>) -> MutexGuard<'_, Lobby> {
+    lobby.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Reads messages until the client hangs up. Replies go through a channel to a writer
+/// thread, so a slow client never blocks the others while the lobby is locked.
+fn serve_connection(stream: TcpStream, lobby: Arc<Mutex<Lobby>>) {
+    // Messages are small and answer moves, so they are sent at once
+    _ = stream.set_nodelay(true);
+    let Ok(mut writer) = stream.try_clone() else { return };
+    let (sender, receiver) = mpsc::channel::<ServerMessage>();
+    thread::spawn(move || {
+        for message in receiver {
+            if writeln!(writer, "{}", message.to_line()).and_then(|_| writer.flush()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let id = lock(&lobby).connect(sender);
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        // Reads no more than a line may have, so an endless one is not buffered
+        match (&mut reader).take(MAX_LINE_LEN as u64 + 1).read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) if line.len() > MAX_LINE_LEN && !line.ends_with('\n') => break,
+            Ok(_) => lock(&lobby).handle_line(id, line.trim_end_matches(['\r', '\n'])),
+        }
+    }
+
+    lock(&lobby).disconnect(id);
+}
+
+impl Lobby {
+
+    /// Adds a client that gets its messages through `sender` and returns its id.
+    pub(super) fn connect(&mut self, sender: Sender<ServerMessage>) -> usize {
+        let id = self.next_connection_id;
+        self.next_connection_id += 1;
+        self.connections.insert(id, Connection { name: None, sender, location: Location::Lobby, chat_limiter: RateLimiter::build() });
+        id
+    }
+
+    /// Handles one message from the client, answering rejected ones with an error.
+    pub(super) fn handle_line(&mut self, id: usize, line: &str) {
+        // The connection may have been replaced by the player resuming from another one
+        if line.trim().is_empty() || !self.connections.contains_key(&id) {
+            return;
+        }
+        if let Err(message) = ClientMessage::parse(line).and_then(|message| self.handle(id, message)) {
+            self.send(id, ServerMessage::Error { message });
+        }
+        self.update_listing();
+    }
+
+    fn handle(&mut self, id: usize, message: ClientMessage) -> Result<(), String> {
+        match message {
+            ClientMessage::Hello { name } => return self.hello(id, name.trim(), None),
+            ClientMessage::Resume { name, token } => return self.hello(id, name.trim(), Some(&token)),
+            _ => {}
+        }
+        let location = self.connections[&id].location;
+        if self.connections[&id].name.is_none() {
+            return Err("Say hello first".into());
+        }
+
+        match message {
+            ClientMessage::Hello { .. } | ClientMessage::Resume { .. } => unreachable!("handled above"),
+            ClientMessage::ListGames => {
+                self.send(id, ServerMessage::Games { games: self.summaries() });
+                Ok(())
+            }
+            ClientMessage::CreateGame { holes, stones, pie, time_control } => {
+                self.expect_lobby(location)?;
+                let holes = holes.unwrap_or(self.game_config.hole_nums);
+                let stones = stones.unwrap_or(self.game_config.stone_nums_in_hole);
+                if holes == 0 || stones == 0 {
+                    return Err("A game needs at least one hole and one stone".into());
+                }
+                let time_control = match time_control {
+                    Some(time_control) => TimeControl::parse(&time_control)?,
+                    None => self.game_config.time_control,
+                };
+                let game_config = GameConfig::build(stones, holes, pie.unwrap_or(self.game_config.is_check_pipe_rule))?
+                    .with_scoring_mode(self.game_config.scoring_mode)
+                    .with_time_control(time_control);
+
+                let game_id = self.next_game_id;
+                self.next_game_id += 1;
+                let game_process = GameProcess::build(GameField::build(&game_config), String::new(), String::new(), game_config);
+                let game = ServerGame::build(game_process);
+                self.games.insert(game_id, game);
+                self.take_seat(id, game_id, 0);
+                Ok(())
+            }
+            ClientMessage::JoinGame { game: game_id } => {
+                self.expect_lobby(location)?;
+                let name = self.connections[&id].name.clone().unwrap_or_default();
+                let game = self.games.get(&game_id).ok_or(format!("There is no game {}", game_id))?;
+
+                // Nobody else may use the name of a kept seat, so a seat with the name is ours
+                let player = match game.seat_named(&name) {
+                    Some(player) => player,
+                    None => game.seats.iter().position(Option::is_none).ok_or("The game is full")?,
+                };
+                self.take_seat(id, game_id, player);
+                Ok(())
+            }
+            ClientMessage::WatchGame { game: game_id } => {
+                self.expect_lobby(location)?;
+                let game = self.games.get_mut(&game_id).ok_or(format!("There is no game {}", game_id))?;
+                game.spectators.push(id);
+                let state = game.state(game_id, self.grace_period);
+
+                self.connections.get_mut(&id).unwrap().location = Location::Watching(game_id);
+                self.send(id, ServerMessage::Watching { game: game_id });
+                self.send(id, ServerMessage::State(state));
+                self.send_game_chat(id, game_id);
+                Ok(())
+            }
+            ClientMessage::LeaveGame => {
+                if location == Location::Lobby {
+                    return Err("You are not in a game".into());
+                }
+                self.leave_game(id);
+                self.show_lobby(id);
+                Ok(())
+            }
+            ClientMessage::Move { hole } => {
+                let Location::Playing(game_id, player) = location else { return Err("Join a game first".into()) };
+                // A move after the flag fell is too late
+                self.flag_out_of_time(Instant::now());
+                let game = self.games.get_mut(&game_id).unwrap();
+                game.play(player, hole)?;
+                game.press_clock(Instant::now());
+                let state = game.state(game_id, self.grace_period);
+                game.history.push(state);
+                self.broadcast_state(game_id);
+                if self.games[&game_id].is_finished {
+                    self.save_record(game_id);
+                }
+                Ok(())
+            }
+            ClientMessage::Chat { text } => {
+                // Control characters could mess up the terminals of the others
+                let text = text.chars().filter(|c| !c.is_control()).collect::<String>();
+                let text = text.trim();
+                if text.is_empty() || text.chars().count() > MAX_CHAT_LEN {
+                    return Err(format!("Chat messages must have 1 to {} characters", MAX_CHAT_LEN));
+                }
+                self.chat(id, text.to_string(), false)
+            }
+            ClientMessage::Emote { emote } => self.chat(id, emote.text().to_string(), true),
+            ClientMessage::Ping { id: ping_id, latency_ms } => {
+                let latencies = match location {
+                    Location::Playing(game_id, player) => {
+                        let game = self.games.get_mut(&game_id).unwrap();
+                        if let Some(seat) = &mut game.seats[player] {
+                            seat.latency_ms = latency_ms;
+                        }
+                        game.latencies()
+                    }
+                    Location::Watching(game_id) => self.games[&game_id].latencies(),
+                    Location::Lobby => [None, None],
+                };
+                self.send(id, ServerMessage::Pong { id: ping_id, latencies });
+                Ok(())
+            }
+        }
+    }
+
+    /// Names the client. A player with the token of a seat in a game in progress gets the seat
+    /// again, everyone else gets the lobby.
+    fn hello(&mut self, id: usize, name: &str, token: Option<&str>) -> Result<(), String> {
+        if let Some(own_name) = &self.connections[&id].name {
+            return Err(format!("You are already known as '{}'", own_name));
+        }
+        if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+            return Err(format!("Name must have 1 to {} characters", MAX_NAME_LEN));
+        }
+        // Names are shown in the terminals of the others and written into the tags of records
+        if name.chars().any(|c| c.is_control() || c == '"') {
+            return Err("Names cannot have control characters or quotes".into());
+        }
+
+        let seat = token.and_then(|token| self.games.iter().filter(|(_, game)| !game.is_finished).find_map(|(&game_id, game)| {
+            let player = game.seats.iter().position(|seat| seat.as_ref().is_some_and(|seat| seat.token == token && seat.name == name))?;
+            Some((game_id, player))
+        }));
+        if let Some((game_id, player)) = seat {
+            let game = self.games.get_mut(&game_id).unwrap();
+            let seat = game.seats[player].as_mut().unwrap();
+            // The player is back before the server noticed the old connection was lost
+            if let Some(old_id) = seat.connection.take() {
+                seat.moves_seen = game.history.len();
+                self.connections.remove(&old_id);
+            }
+        }
+
+        let is_kept = self.games.values().any(|game| !game.is_finished && game.seat_named(name).is_some());
+        if self.connections.values().any(|connection| connection.name.as_deref() == Some(name)) || (seat.is_none() && is_kept) {
+            return Err(format!("The name '{}' is taken", name));
+        }
+        self.connections.get_mut(&id).unwrap().name = Some(name.to_string());
+
+        match seat {
+            Some((game_id, player)) => self.take_seat(id, game_id, player),
+            None => {
+                self.show_lobby(id);
+                for line in &self.chat {
+                    self.send(id, ServerMessage::Chat(line.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn expect_lobby(&self, location: Location) -> Result<(), String> {
+        match location {
+            Location::Lobby => Ok(()),
+            _ => Err("Leave the game first".into()),
+        }
+    }
+
+    /// Seats the client as `player`, in a free seat or the one kept under its name.
+    fn take_seat(&mut self, id: usize, game_id: u64, player: usize) {
+        let connection = self.connections.get_mut(&id).unwrap();
+        let name = connection.name.clone().unwrap_or_default();
+        connection.location = Location::Playing(game_id, player);
+
+        let game = self.games.get_mut(&game_id).unwrap();
+        let missed = match &mut game.seats[player] {
+            Some(seat) => {
+                seat.connection = Some(id);
+                seat.away_since = None;
+                game.history[seat.moves_seen..].to_vec()
+            }
+            None => {
+                let token = format!("{:032x}", rand::random::<u128>());
+                game.seats[player] = Some(Seat { name: name.clone(), token, connection: Some(id), latency_ms: None, away_since: None, moves_seen: 0 });
+                let game_player = if player == 0 { &mut game.game_process.player_one } else { &mut game.game_process.player_two };
+                game_player.name = name;
+                Vec::new()
+            }
+        };
+        let token = game.seats[player].as_ref().unwrap().token.clone();
+        // The opponent of a player back in a restored game is away from now on
+        let now = Instant::now();
+        if let Some(opponent) = &mut game.seats[1 - player]
+            && opponent.connection.is_none()
+            && opponent.away_since.is_none()
+        {
+            opponent.away_since = Some(now);
+        }
+        // The clock starts when the second player sits down, or the first one is back in a restored game
+        let is_playing = game.phase() == Phase::Playing;
+        let player_to_move = if game.game_process.is_player_one_turn { 0 } else { 1 };
+        if let Some(clock) = &mut game.clock
+            && is_playing
+            && clock.running.is_none()
+        {
+            clock.start(player_to_move, now);
+        }
+
+        self.send(id, ServerMessage::Welcome { game: game_id, player: player as u8 + 1, token });
+        for state in missed {
+            self.send(id, ServerMessage::State(state));
+        }
+        self.broadcast_state(game_id);
+        self.send_game_chat(id, game_id);
+    }
+
+    /// Sends a chat line to everyone in the lobby or the game of the client.
+    fn chat(&mut self, id: usize, text: String, is_emote: bool) -> Result<(), String> {
+        let connection = self.connections.get_mut(&id).unwrap();
+        if !connection.chat_limiter.try_take(Instant::now()) {
+            return Err("You are chatting too fast, wait a moment".into());
+        }
+        let from = connection.name.clone().unwrap_or_default();
+
+        match connection.location {
+            Location::Lobby => {
+                let line = ChatLine { game: None, from, text, is_emote };
+                if self.chat.len() == CHAT_HISTORY {
+                    self.chat.pop_front();
+                }
+                self.chat.push_back(line.clone());
+                let listeners = self.connections.values().filter(|connection| connection.name.is_some() && connection.location == Location::Lobby);
+                for connection in listeners {
+                    _ = connection.sender.send(ServerMessage::Chat(line.clone()));
+                }
+            }
+            Location::Playing(game_id, _) | Location::Watching(game_id) => {
+                let line = ChatLine { game: Some(game_id), from, text, is_emote };
+                let game = self.games.get_mut(&game_id).unwrap();
+                game.chat.push((game.game_process.move_history.len(), line.clone()));
+                self.save_game(game_id);
+                for id in self.games[&game_id].audience() {
+                    self.send(id, ServerMessage::Chat(line.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the last `CHAT_HISTORY` lines of the game's chat to a client joining it.
+    fn send_game_chat(&self, id: usize, game_id: u64) {
+        let chat = &self.games[&game_id].chat;
+        for (_, line) in &chat[chat.len().saturating_sub(CHAT_HISTORY)..] {
+            self.send(id, ServerMessage::Chat(line.clone()));
+        }
+    }
+
+    /// Writes the record of a finished game, when records are kept. A failed write only
+    /// loses the record, the game goes on.
+    fn save_record(&self, game_id: u64) {
+        let Some(records) = &self.records else { return };
+        let game = &self.games[&game_id];
+
+        // A game lost by forfeit or on time has no result on the board
+        let out_of_time = game.clock.as_ref().and_then(GameClock::out_of_time);
+        let mut record = GameRecord::from_process(&game.game_process, game.is_finished && game.forfeit.is_none() && out_of_time.is_none());
+        let name = |player: usize| &game.seats[player].as_ref().unwrap().name;
+        if let Some(player) = game.forfeit {
+            record.comments.push((record.moves.len(), format!("{} forfeits", name(player))));
+        }
+        if let Some(player) = out_of_time {
+            record.comments.push((record.moves.len(), format!("{} loses on time", name(player))));
+        }
+        if records.is_chat_recorded {
+            // Braces would end the comment the line is written in
+            record.chat = game.chat.iter()
+                .map(|(before, line)| (*before, line.display().replace('{', "(").replace('}', ")")))
+                .collect();
+        }
+        _ = record.save(&records.directory.join(format!("game-{}.txt", game_id)).to_string_lossy());
+    }
+
+    /// Saves a game in progress, or removes the saved game once it is over. A failed write
+    /// only loses the game on a restart.
+    fn save_game(&self, game_id: u64) {
+        let Some(directory) = &self.saved_games else { return };
+        let path = directory.join(format!("game-{}.json", game_id));
+        let game = &self.games[&game_id];
+        if game.phase() != Phase::Playing {
+            _ = fs::remove_file(path);
+            return;
+        }
+
+        let game_config = &game.game_process.game_config;
+        let players = [0, 1].map(|player| {
+            let seat = game.seats[player].as_ref().unwrap();
+            let moves_seen = if seat.connection.is_some() { game.history.len() } else { seat.moves_seen };
+            SavedSeat { name: seat.name.clone(), token: seat.token.clone(), moves_seen }
+        });
+        let saved = SavedGame {
+            id: game_id,
+            holes: game_config.hole_nums,
+            stones: game_config.stone_nums_in_hole,
+            pie: game_config.is_check_pipe_rule,
+            players,
+            moves: game.game_process.move_history.clone(),
+            chat: game.chat.clone(),
+            clock: game.clock.as_ref().map(|clock| {
+                let now = Instant::now();
+                let remaining = [0, 1].map(|player| clock.remaining_at(player, now).as_millis() as u64);
+                (clock.time_control.to_notation(), remaining)
+            }),
+        };
+        // Written aside first, so a crash never leaves half a game
+        let temporary = path.with_extension("json.tmp");
+        let json = serde_json::to_string(&saved).expect("saved games serialize");
+        if fs::write(&temporary, json).is_ok() {
+            _ = fs::rename(temporary, path);
+        }
+    }
+
+    /// Resumes the games saved in `directory` with all players away. Neither the grace period
+    /// nor the clock runs until one of the players comes back.
+    fn load_games(&mut self, directory: &Path) -> Result<(), String> {
+        let entries = fs::read_dir(directory).map_err(|err| format!("Cannot read '{}': {}", directory.display(), err))?;
+        for entry in entries {
+            let path = entry.map_err(|err| format!("Cannot read '{}': {}", directory.display(), err))?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let cannot_load = |err: String| format!("Cannot load '{}': {}", path.display(), err);
+            let text = fs::read_to_string(&path).map_err(|err| cannot_load(err.to_string()))?;
+            let saved: SavedGame = serde_json::from_str(&text).map_err(|err| cannot_load(err.to_string()))?;
+
+            let time_control = saved.clock.as_ref().map(|(time_control, _)| TimeControl::parse(time_control)).transpose().map_err(cannot_load)?.flatten();
+            let game_config = GameConfig::build(saved.stones, saved.holes, saved.pie)
+                .map_err(|err| cannot_load(err.to_string()))?
+                .with_scoring_mode(self.game_config.scoring_mode)
+                .with_time_control(time_control);
+            let [one, two] = saved.players.each_ref().map(|seat| seat.name.clone());
+            let mut game = ServerGame::build(GameProcess::build(GameField::build(&game_config), one, two, game_config));
+            game.seats = saved.players.map(|seat| Some(Seat {
+                name: seat.name,
+                token: seat.token,
+                connection: None,
+                latency_ms: None,
+                away_since: None,
+                moves_seen: seat.moves_seen.min(saved.moves.len()),
+            }));
+            for hole in saved.moves {
+                let player = if game.game_process.is_player_one_turn { 0 } else { 1 };
+                game.play(player, hole).map_err(cannot_load)?;
+                let state = game.state(saved.id, self.grace_period);
+                game.history.push(state);
+            }
+            game.chat = saved.chat;
+            // The clock goes on from where it was saved once a player is back
+            if let Some((clock, (_, remaining_ms))) = game.clock.as_mut().zip(saved.clock) {
+                clock.remaining = remaining_ms.map(Duration::from_millis);
+            }
+
+            self.next_game_id = self.next_game_id.max(saved.id + 1);
+            self.games.insert(saved.id, game);
+        }
+        Ok(())
+    }
+
+    /// Ends the games of players who stayed away longer than the grace period. When both
+    /// players are away, the one who left first forfeits.
+    pub(super) fn forfeit_absent(&mut self, now: Instant) {
+        let forfeits = self.games.iter()
+            .filter(|(_, game)| game.phase() == Phase::Playing)
+            .filter_map(|(&game_id, game)| {
+                let (player, away_since) = (0..2)
+                    .filter_map(|player| Some((player, game.seats[player].as_ref()?.away_since?)))
+                    .min_by_key(|&(_, away_since)| away_since)?;
+                (now.duration_since(away_since) >= self.grace_period).then_some((game_id, player))
+            })
+            .collect::<Vec<_>>();
+        if forfeits.is_empty() {
+            return;
+        }
+
+        for (game_id, player) in forfeits {
+            let game = self.games.get_mut(&game_id).unwrap();
+            game.forfeit = Some(player);
+            if let Some(clock) = &mut game.clock {
+                clock.stop(now);
+            }
+            self.end_game(game_id);
+        }
+        self.update_listing();
+    }
+
+    /// Ends the games whose player to move has run out of time.
+    pub(super) fn flag_out_of_time(&mut self, now: Instant) {
+        let flagged = self.games.iter_mut()
+            .filter(|(_, game)| !game.is_finished)
+            .filter_map(|(&game_id, game)| game.clock.as_mut()?.flag(now).map(|_| game_id))
+            .collect::<Vec<_>>();
+        if flagged.is_empty() {
+            return;
+        }
+
+        for game_id in flagged {
+            self.end_game(game_id);
+        }
+        self.update_listing();
+    }
+
+    /// Finishes a game before its end on the board.
+    fn end_game(&mut self, game_id: u64) {
+        self.games.get_mut(&game_id).unwrap().is_finished = true;
+        self.broadcast_state(game_id);
+        self.save_record(game_id);
+        self.close_if_abandoned(game_id);
+    }
+
+    /// Takes the client back to the lobby. A seat is freed while the game has not started,
+    /// otherwise it is kept for the player.
+    fn leave_game(&mut self, id: usize) {
+        let Some(connection) = self.connections.get_mut(&id) else { return };
+        let location = std::mem::replace(&mut connection.location, Location::Lobby);
+
+        let game_id = match location {
+            Location::Lobby => return,
+            Location::Playing(game_id, player) => {
+                let game = self.games.get_mut(&game_id).unwrap();
+                if game.phase() == Phase::Waiting {
+                    game.seats[player] = None;
+                } else if let Some(seat) = &mut game.seats[player] {
+                    seat.connection = None;
+                    seat.latency_ms = None;
+                    seat.away_since = Some(Instant::now());
+                    seat.moves_seen = game.history.len();
+                }
+                self.broadcast_state(game_id);
+                game_id
+            }
+            Location::Watching(game_id) => {
+                self.games.get_mut(&game_id).unwrap().spectators.retain(|&spectator| spectator != id);
+                game_id
+            }
+        };
+
+        self.close_if_abandoned(game_id);
+    }
+
+    /// Closes a game that is not in progress once nobody is left in it. Games in progress
+    /// are kept for their players to come back.
+    fn close_if_abandoned(&mut self, game_id: u64) {
+        let game = &self.games[&game_id];
+        if game.phase() != Phase::Playing && game.audience().is_empty() {
+            // Again, with what was said after the end
+            if game.is_finished {
+                self.save_record(game_id);
+            }
+            self.games.remove(&game_id);
+        }
+    }
+
+    pub(super) fn disconnect(&mut self, id: usize) {
+        self.leave_game(id);
+        self.connections.remove(&id);
+        self.update_listing();
+    }
+
+    fn summaries(&self) -> Vec<GameSummary> {
+        self.games.iter().map(|(&game_id, game)| game.summary(game_id)).collect()
+    }
+
+    /// Sends the games to the clients in the lobby if they changed, returns whether they did.
+    fn update_listing(&mut self) -> bool {
+        let games = self.summaries();
+        if games == self.listed {
+            return false;
+        }
+        for connection in self.connections.values().filter(|connection| connection.name.is_some() && connection.location == Location::Lobby) {
+            _ = connection.sender.send(ServerMessage::Games { games: games.clone() });
+        }
+        self.listed = games;
+        true
+    }
+
+    /// Sends the games to a client that has just come to the lobby, once.
+    fn show_lobby(&mut self, id: usize) {
+        if !self.update_listing() {
+            self.send(id, ServerMessage::Games { games: self.listed.clone() });
+        }
+    }
+
+    fn send(&self, id: usize, message: ServerMessage) {
+        if let Some(connection) = self.connections.get(&id) {
+            _ = connection.sender.send(message);
+        }
+    }
+
+    /// Saves the game and sends its state to the players and spectators. What they see
+    /// survives a restart.
+    fn broadcast_state(&self, game_id: u64) {
+        let Some(game) = self.games.get(&game_id) else { return };
+        self.save_game(game_id);
+        let state = game.state(game_id, self.grace_period);
+        for id in game.audience() {
+            self.send(id, ServerMessage::State(state.clone()));
+        }
+    }
+}
+
+impl ServerGame {
+
+    fn build(game_process: GameProcess) -> ServerGame {
+        ServerGame {
+            clock: game_process.game_config.time_control.map(GameClock::build),
+            game_process,
+            seats: [None, None],
+            spectators: Vec::new(),
+            last_move: None,
+            is_finished: false,
+            forfeit: None,
+            history: Vec::new(),
+            chat: Vec::new(),
+        }
+    }
+
+    fn play(&mut self, player: usize, hole_num: usize) -> Result<(), String> {
+        match self.phase() {
+            Phase::Waiting => return Err("Waiting for the second player".into()),
+            Phase::Finished => return Err("The game is over".into()),
+            Phase::Playing => {}
+        }
+        if player != if self.game_process.is_player_one_turn { 0 } else { 1 } {
+            return Err("It is not your turn".into());
+        }
+
+        if self.game_process.move_stones_from_hole(hole_num)? == GameStatus::Finished {
+            self.is_finished = true;
+        }
+        self.last_move = Some(hole_num);
+        Ok(())
+    }
+
+    /// Ends the move just played on the clock, stopping it at the end of the game.
+    fn press_clock(&mut self, now: Instant) {
+        let Some(clock) = &mut self.clock else { return };
+        if self.is_finished {
+            clock.stop(now);
+        } else {
+            clock.press(if self.game_process.is_player_one_turn { 0 } else { 1 }, now);
+        }
+    }
+
+    /// Connections of the players and spectators.
+    fn audience(&self) -> Vec<usize> {
+        let players = self.seats.iter().flatten().filter_map(|seat| seat.connection);
+        players.chain(self.spectators.iter().copied()).collect()
+    }
+
+    fn seat_named(&self, name: &str) -> Option<usize> {
+        self.seats.iter().position(|seat| seat.as_ref().is_some_and(|seat| seat.name == name))
+    }
+
+    fn latencies(&self) -> [Option<u64>; 2] {
+        [0, 1].map(|player| self.seats[player].as_ref().and_then(|seat| seat.latency_ms))
+    }
+
+    fn phase(&self) -> Phase {
+        if self.is_finished {
+            Phase::Finished
+        } else if self.seats.iter().all(Option::is_some) {
+            Phase::Playing
+        } else {
+            Phase::Waiting
+        }
+    }
+
+    fn seat_infos(&self) -> [Option<SeatInfo>; 2] {
+        [0, 1].map(|player| self.seats[player].as_ref().map(|seat| SeatInfo {
+            name: seat.name.clone(),
+            is_connected: seat.connection.is_some(),
+        }))
+    }
+
+    fn summary(&self, game_id: u64) -> GameSummary {
+        let game_config = &self.game_process.game_config;
+        GameSummary {
+            id: game_id,
+            holes: game_config.hole_nums,
+            stones: game_config.stone_nums_in_hole,
+            pie: game_config.is_check_pipe_rule,
+            phase: self.phase(),
+            players: self.seat_infos(),
+            spectators: self.spectators.len(),
+            time_control: game_config.time_control.map(|time_control| time_control.to_notation()),
+        }
+    }
+
+    fn state(&self, game_id: u64, grace_period: Duration) -> GameState {
+        let is_playing = self.phase() == Phase::Playing;
+        let forfeit_in_ms = [0, 1].map(|player| {
+            let away_since = self.seats[player].as_ref()?.away_since.filter(|_| is_playing)?;
+            Some(grace_period.saturating_sub(away_since.elapsed()).as_millis() as u64)
+        });
+        GameState {
+            game: game_id,
+            phase: self.phase(),
+            players: self.seat_infos(),
+            position: Position::from_process(&self.game_process),
+            scores: [self.game_process.player_one_score(), self.game_process.player_two_score()],
+            last_move: self.last_move,
+            forfeit_in_ms,
+            forfeit: self.forfeit.map(|player| player as u8 + 1),
+            clock: self.clock.as_ref().map(|clock| Box::new(ClockState::from_clock(clock, Instant::now()))),
+            out_of_time: self.clock.as_ref().and_then(GameClock::out_of_time).map(|player| player as u8 + 1),
+        }
+    }
+}
+
+impl RateLimiter {
+
+    fn build() -> RateLimiter {
+        RateLimiter { tokens: CHAT_BURST, refilled: Instant::now() }
+    }
+
+    /// Takes a token if one is left at `now`.
+    fn try_take(&mut self, now: Instant) -> bool {
+        let earned = (now.duration_since(self.refilled).as_millis() / CHAT_INTERVAL.as_millis()) as u32;
+        if earned > 0 {
+            self.tokens = self.tokens.saturating_add(earned).min(CHAT_BURST);
+            // A full bucket earns nothing while it waits
+            self.refilled = if self.tokens == CHAT_BURST { now } else { self.refilled + CHAT_INTERVAL * earned };
+        }
+        if self.tokens == 0 {
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+}
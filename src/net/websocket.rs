@@ -0,0 +1,151 @@
+use std::io::{ self, ErrorKind, Read, Write };
+use std::net::{ SocketAddr, TcpListener, TcpStream, ToSocketAddrs };
+use std::sync::{ Arc, Mutex };
+use std::sync::mpsc::{ self, TryRecvError };
+use std::thread;
+use std::time::Duration;
+
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::{ Role, WebSocket, WebSocketConfig };
+use tungstenite::{ Error, Message };
+
+use super::protocol::MAX_LINE_LEN;
+use super::server::{ lock, Lobby, Server };
+
+/// Path of the WebSocket endpoint.
+pub const WEBSOCKET_PATH: &str = "/ws";
+
+/// Board page served at `/`, playing through the WebSocket endpoint.
+const INDEX_HTML: &str = include_str!("../../web/index.html");
+
+/// Longest HTTP request head read before giving up on a client.
+const MAX_REQUEST_LEN: usize = 8192;
+
+/// Time allowed for the request head, so idle connections do not hold a thread.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a WebSocket connection checks for messages to send while waiting for the browser.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Serves the board page and speaks the server's protocol over WebSocket, one JSON message
+/// per text frame, so browsers share the lobby and games of a `Server`.
+pub struct WebGateway {
+    listener: TcpListener,
+    lobby: Arc<Mutex<Lobby>>,
+}
+
+impl WebGateway {
+
+    /// Listens on `address` for browsers joining the games of `server`.
+    pub fn bind(address: impl ToSocketAddrs, server: &Server) -> io::Result<WebGateway> {
+        Ok(WebGateway { listener: TcpListener::bind(address)?, lobby: Arc::clone(&server.lobby) })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections for as long as the process runs.
+    pub fn run(self) {
+        for stream in self.listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let lobby = Arc::clone(&self.lobby);
+            thread::spawn(move || serve_http(stream, lobby));
+        }
+    }
+}
+
+/// Answers one HTTP request, staying on as a WebSocket connection after an upgrade.
+fn serve_http(mut stream: TcpStream, lobby: Arc<Mutex<Lobby>>) {
+    _ = stream.set_nodelay(true);
+    _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+    let Ok(head) = read_request_head(&mut stream) else { return };
+
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (method, path) = (request_line.next().unwrap_or_default(), request_line.next().unwrap_or_default());
+    let header = |name: &str| lines.clone().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    });
+
+    let response = match (method, path) {
+        ("GET", WEBSOCKET_PATH) if header("Upgrade").is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket")) => {
+            let Some(key) = header("Sec-WebSocket-Key") else { return respond(&mut stream, "400 Bad Request", "text/plain", "Missing Sec-WebSocket-Key") };
+            let accept = derive_accept_key(key.as_bytes());
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                accept
+            );
+            if stream.write_all(response.as_bytes()).is_ok() {
+                serve_websocket(stream, lobby);
+            }
+            return;
+        }
+        ("GET", "/" | "/index.html") => ("200 OK", "text/html; charset=utf-8", INDEX_HTML),
+        ("GET", _) => ("404 Not Found", "text/plain", "Not found"),
+        _ => ("405 Method Not Allowed", "text/plain", "Only GET is supported"),
+    };
+    respond(&mut stream, response.0, response.1, response.2);
+}
+
+/// Reads up to the empty line ending the request head. The browser sends nothing more
+/// before the answer, so nothing past it is lost.
+fn read_request_head(stream: &mut TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut byte = [0];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() == MAX_REQUEST_LEN || stream.read(&mut byte)? == 0 {
+            return Err(ErrorKind::InvalidData.into());
+        }
+        head.push(byte[0]);
+    }
+    String::from_utf8(head).map_err(|_| ErrorKind::InvalidData.into())
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    );
+    _ = stream.write_all(response.as_bytes());
+}
+
+/// Passes text frames to the lobby and its messages back until either side closes. A
+/// WebSocket cannot be read and written from two threads, so reads time out every
+/// `POLL_INTERVAL` to send what the lobby has queued.
+fn serve_websocket(stream: TcpStream, lobby: Arc<Mutex<Lobby>>) {
+    if stream.set_read_timeout(Some(POLL_INTERVAL)).is_err() {
+        return;
+    }
+    // Frames are messages, held to the length of a message line
+    let config = WebSocketConfig::default().max_message_size(Some(MAX_LINE_LEN)).max_frame_size(Some(MAX_LINE_LEN));
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, Some(config));
+    let (sender, receiver) = mpsc::channel();
+    let id = lock(&lobby).connect(sender);
+
+    'connection: loop {
+        loop {
+            match receiver.try_recv() {
+                Ok(message) => {
+                    if socket.send(Message::text(message.to_line())).is_err() {
+                        break 'connection;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => break 'connection,
+            }
+        }
+
+        match socket.read() {
+            Ok(Message::Text(text)) => lock(&lobby).handle_line(id, text.as_str()),
+            Ok(Message::Close(_)) => break,
+            // Pings are answered by tungstenite, binary frames are not part of the protocol
+            Ok(_) => {}
+            Err(Error::Io(err)) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => break,
+        }
+    }
+
+    lock(&lobby).disconnect(id);
+}
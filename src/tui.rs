@@ -0,0 +1,65 @@
+pub mod computer;
+pub mod hints;
+pub mod local;
+pub mod remote;
+pub mod review;
+pub mod setup;
+
+use std::{ io, time::Instant };
+
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute, terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{ backend::CrosstermBackend, Terminal };
+
+use kalah::game::time_control::{ format_clock, GameClock };
+
+pub fn start_terminal() -> io::Result<Terminal<CrosstermBackend<std::io::Stdout>>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    Terminal::new(backend)
+}
+
+pub fn end_terminal(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()
+}
+
+/// Three display rows (player two's holes, stores, player one's holes), with the hole at
+/// `highlighted` (side index, hole index) shown as `<n>`.
+pub fn board_rows(sides: [&[usize]; 2], stores: [usize; 2], highlighted: Option<(usize, usize)>) -> [String; 3] {
+    let side_strs_str: Vec<String> = sides
+        .iter()
+        .enumerate()
+        .map(|(side, ss)| {
+            let holes = ss.iter().enumerate().map(|(index, el)|
+                if highlighted == Some((side, index)) { format!("<{}>", el) } else { format!("[{}]", el) }
+            );
+            // Player two's holes run right to left
+            let holes = if side == 1 { holes.rev().collect::<Vec<_>>() } else { holes.collect::<Vec<_>>() };
+            holes.join(" ")
+        }).collect();
+
+    [
+        format!("{}: {}", "P2", side_strs_str[1]),
+        format!("{}{}{}", stores[1], " ".repeat(side_strs_str[0].len() + 5), stores[0]),
+        format!("{}{}: {}", " ".repeat(4), side_strs_str[0], "P1"),
+    ]
+}
+
+/// Both clocks for the header, the running one marked.
+pub fn clock_str(clock: &GameClock, names: [&str; 2], now: Instant) -> String {
+    let side = |player: usize| {
+        let mark = if clock.running.is_some_and(|(running, _)| running == player) { "▶" } else { "" };
+        format!("{}{} {}", mark, names[player], format_clock(clock.remaining_at(player, now)))
+    };
+    format!("[⏱ {} | {}]", side(0), side(1))
+}
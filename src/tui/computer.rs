@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use kalah::ai::alpha_beta::SearchLimits;
+use kalah::ai::background_search::{ BackgroundSearch, SearchEvent };
+use kalah::ai::compact_board::CompactBoard;
+use kalah::ai::difficulty::Level;
+use kalah::ai::engine::Engine;
+use kalah::game::game_process::GameProcess;
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Computer opponent playing as player two.
+pub struct Computer {
+    level: Level,
+    pub move_time: Duration,
+    engine: Option<Box<dyn Engine>>,
+    pub search: Option<BackgroundSearch<Box<dyn Engine>>>,
+    last_depth: u8,
+    /// Last move played and whether it came from the opening book.
+    last_move: Option<(usize, bool)>,
+}
+
+impl Computer {
+
+    pub fn build(level: Level, move_time: Duration, engine: Box<dyn Engine>) -> Computer {
+        Computer { level, move_time, engine: Some(engine), search: None, last_depth: 0, last_move: None }
+    }
+
+    /// Starts a search of `move_time` on the computer's turn and returns its move once the
+    /// search is over.
+    pub fn poll_move(&mut self, game_process: &GameProcess, move_time: Duration) -> Option<usize> {
+        let Some(search) = &self.search else {
+            let engine = self.engine.take().expect("engine is idle between searches");
+            let board = CompactBoard::from_process(game_process);
+            self.search = Some(BackgroundSearch::start(engine, board, SearchLimits::move_time(move_time)));
+            self.last_depth = 0;
+            return None;
+        };
+
+        while let Some(event) = search.try_next() {
+            match event {
+                SearchEvent::Iteration(result) => self.last_depth = result.depth,
+                SearchEvent::Finished(result) => {
+                    let search = self.search.take().unwrap();
+                    self.engine = Some(search.join());
+                    self.last_move = result.best_move.map(|hole_num| (hole_num, result.is_book_move));
+                    return result.best_move;
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn last_move_str(&self) -> String {
+        match self.last_move {
+            Some((hole_num, true)) => format!(" Computer played {} (book).", hole_num),
+            Some((hole_num, false)) => format!(" Computer played {}.", hole_num),
+            None => String::new(),
+        }
+    }
+
+    pub fn thinking_str(&self) -> String {
+        let elapsed = self.search.as_ref().map_or(Duration::ZERO, |search| search.elapsed());
+        let spinner = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+        format!("Computer ({}) thinking {} depth {} ({:.1}s).", self.level.name(), spinner, self.last_depth, elapsed.as_secs_f32())
+    }
+}
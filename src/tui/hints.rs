@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use kalah::ai::alpha_beta::{ AlphaBeta, SearchLimits };
+use kalah::ai::background_search::{ BackgroundSearch, SearchEvent };
+use kalah::ai::compact_board::CompactBoard;
+use kalah::game::game_process::GameProcess;
+
+const HINT_MOVE_TIME_MS: u64 = 1000;
+const HINT_LINE_LEN: usize = 8;
+
+/// Full-strength suggestions for the human player, asked for with the hint key.
+pub struct Hints {
+    engine: Option<AlphaBeta>,
+    search: Option<BackgroundSearch>,
+    board: CompactBoard,
+    /// Suggested move, its value for the player to move and the expected line starting with it.
+    suggestion: Option<(usize, i32, Vec<usize>)>,
+    /// Hints used by player one and player two.
+    pub counts: (usize, usize),
+}
+
+impl Hints {
+
+    pub fn build(engine: AlphaBeta, board: CompactBoard) -> Hints {
+        Hints { engine: Some(engine), search: None, board, suggestion: None, counts: (0, 0) }
+    }
+
+    /// Starts a hint search for the position unless one is running or already answered it.
+    pub fn start(&mut self, game_process: &GameProcess) {
+        let board = CompactBoard::from_process(game_process);
+        let Some(engine) = self.engine.take().filter(|_| board.legal_moves().next().is_some()) else { return };
+
+        if board == self.board && self.suggestion.is_some() {
+            self.engine = Some(engine);
+            return;
+        }
+
+        if game_process.is_player_one_turn { self.counts.0 += 1 } else { self.counts.1 += 1 }
+        self.board = board;
+        self.suggestion = None;
+        self.search = Some(BackgroundSearch::start(engine, board, SearchLimits::move_time(Duration::from_millis(HINT_MOVE_TIME_MS))));
+    }
+
+    /// Collects a finished hint and drops it once the position has changed.
+    pub fn poll(&mut self, game_process: &GameProcess) {
+        let board = CompactBoard::from_process(game_process);
+
+        if let Some(search) = &self.search {
+            if board != self.board {
+                search.stop();
+            }
+
+            while let Some(event) = search.try_next() {
+                if let SearchEvent::Finished(result) = event {
+                    let engine = self.search.take().unwrap().join();
+                    self.suggestion = result.best_move.map(|hole_num| {
+                        (hole_num, result.value, engine.principal_variation(&self.board, hole_num, HINT_LINE_LEN))
+                    });
+                    self.engine = Some(engine);
+                    break;
+                }
+            }
+        }
+
+        if board != self.board {
+            self.suggestion = None;
+        }
+    }
+
+    pub fn suggested_move(&self) -> Option<usize> {
+        self.suggestion.as_ref().map(|&(hole_num, _, _)| hole_num)
+    }
+
+    pub fn status_str(&self) -> String {
+        if self.search.is_some() {
+            return " Hint: thinking...".to_string();
+        }
+
+        match &self.suggestion {
+            Some((hole_num, value, line)) => format!(
+                " Hint: play {} ({:+}), line {}.",
+                hole_num, value, line.iter().map(|hole_num| hole_num.to_string()).collect::<Vec<_>>().join(" ")
+            ),
+            None => String::new(),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::{ char, io, time::Instant };
+
+use crossterm::event::{ self, Event, KeyCode };
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    widgets::{Block, Borders, Paragraph},
+    Terminal,
+};
+
+use kalah::game::game_process::GameProcess;
+use kalah::game::game_status::GameStatus;
+use kalah::game::time_control::GameClock;
+
+use super::{ board_rows, clock_str };
+use super::computer::Computer;
+use super::hints::Hints;
+
+pub fn run_app(
+    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
+    game_process: &mut GameProcess,
+    mut computer: Option<&mut Computer>,
+    hints: &mut Hints,
+    mut clock: Option<&mut GameClock>,
+) -> io::Result<bool> {
+    let player_to_move = |game_process: &GameProcess| if game_process.is_player_one_turn { 0 } else { 1 };
+    if let Some(clock) = clock.as_deref_mut() {
+        clock.start(player_to_move(game_process), Instant::now());
+    }
+
+    loop {
+        let is_computer_turn = computer.is_some() && !game_process.is_player_one_turn;
+
+        if let Some(clock) = clock.as_deref_mut()
+            && let Some(player) = clock.flag(Instant::now())
+        {
+            if let Some(search) = computer.as_ref().and_then(|computer| computer.search.as_ref()) {
+                search.stop();
+            }
+            return show_game_over(terminal, game_process, Some(player));
+        }
+
+        if let Some(computer) = computer.as_deref_mut().filter(|_| is_computer_turn) {
+            // Keep to a share of the clock
+            let move_time = clock.as_deref().map_or(computer.move_time, |clock| {
+                computer.move_time.min(clock.move_budget(player_to_move(game_process), Instant::now()))
+            });
+            if let Some(hole_num) = computer.poll_move(game_process, move_time) {
+                if let Ok(GameStatus::Finished) = play(game_process, hole_num, clock.as_deref_mut()) {
+                    return show_game_over(terminal, game_process, clock.as_deref().and_then(GameClock::out_of_time));
+                }
+                continue;
+            }
+        }
+
+        hints.poll(game_process);
+
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .margin(2)
+                .constraints([Constraint::Length(2), Constraint::Length(1), Constraint::Min(0)].as_ref())
+                .split(f.area());
+
+            let side_strs: Vec<Vec<usize>> = [&game_process.game_field.side_one, &game_process.game_field.side_two].iter().map(|side|
+                side.holes.iter().map(|hole| hole.stones.len()).collect::<Vec<_>>()
+            ).collect();
+
+            // The suggested hole of the side to move is shown as <n>
+            let highlighted = hints.suggested_move().map(|hole_num| (if game_process.is_player_one_turn { 0 } else { 1 }, hole_num - 1));
+
+            let [top_row, midle_row, bottom_row] = board_rows(
+                [&side_strs[0], &side_strs[1]],
+                [game_process.player_one_score(), game_process.player_two_score()],
+                highlighted,
+            )
+            .map(Paragraph::new);
+
+            let player_turn_str = match game_process.is_player_one_turn {
+                true => format!("Is {} turn", game_process.player_one.name),
+                _ => format!("Is {} turn", game_process.player_two.name),
+            };
+            let clock = clock.as_deref().map_or(String::new(), |clock| {
+                let names = [game_process.player_one.name.as_str(), game_process.player_two.name.as_str()];
+                format!(" {}", clock_str(clock, names, Instant::now()))
+            });
+            let title = match computer.as_deref().filter(|_| is_computer_turn) {
+                Some(computer) => format!("Kalah{} - {} Press m to move now, q to quit.", clock, computer.thinking_str()),
+                None => format!(
+                    "Kalah{} - Press 1–{} to move, h for a hint, q to quit. {}{}{}",
+                    clock,
+                    game_process.game_config.hole_nums,
+                    player_turn_str,
+                    computer.as_deref().map_or(String::new(), |computer| computer.last_move_str()),
+                    hints.status_str(),
+                ),
+            };
+            let block = Block::default()
+                .title(title)
+                .borders(Borders::ALL);
+
+            f.render_widget(block, f.area());
+            f.render_widget(top_row, chunks[0]);
+            f.render_widget(midle_row, chunks[1]);
+            f.render_widget(bottom_row, chunks[2]);
+        })?;
+
+        if event::poll(std::time::Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+        {
+            match key.code {
+
+                KeyCode::Char(c) if !is_computer_turn
+                    && c >= '1'
+                    && c <= char::from_digit(game_process.game_config.hole_nums as u32, 10).unwrap() =>
+                {
+                    let hole_num = c.to_digit(10).unwrap() as usize;
+
+                    // A key pressed after the time ran out loses on time
+                    match play(game_process, hole_num, clock.as_deref_mut()) {
+                        Ok(GameStatus::Finished) => {
+                            return show_game_over(terminal, game_process, clock.as_deref().and_then(GameClock::out_of_time));
+                        }
+
+                        Ok(_) => continue, // Game continues
+
+                        Err(_e) => {
+                            // Optional: Show error message if needed
+                            // eprintln!("Move error: {}", _e);
+                            continue;
+                        }
+                    }
+                }
+
+                KeyCode::Char('h') if !is_computer_turn => hints.start(game_process),
+
+                KeyCode::Char('m') if is_computer_turn => {
+                    if let Some(search) = computer.as_ref().and_then(|computer| computer.search.as_ref()) {
+                        search.stop();
+                    }
+                }
+
+                KeyCode::Char('q') => return Ok(false),
+
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Makes a move, on the clock when the game has one.
+fn play(game_process: &mut GameProcess, hole_num: usize, clock: Option<&mut GameClock>) -> Result<GameStatus, String> {
+    match clock {
+        Some(clock) => game_process.move_stones_on_clock(hole_num, clock, Instant::now()),
+        None => game_process.move_stones_from_hole(hole_num),
+    }
+}
+
+/// Returns whether the player asked to review the game.
+/// `out_of_time` is the player who lost on time.
+fn show_game_over(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, game_process: &GameProcess, out_of_time: Option<usize>) -> io::Result<bool> {
+    // Clear screen and draw final score
+    terminal.draw(|f| {
+        let time_loss = match out_of_time {
+            Some(player) => format!("{} lost on time.\n\n", if player == 0 { &game_process.player_one.name } else { &game_process.player_two.name }),
+            None => String::new(),
+        };
+        let score_text = format!(
+            "🏁 Game Over!\n\n{}Player 1 Score: {}\nPlayer 2 Score: {}\n\nPress r to review the game, any other key to exit.",
+            time_loss,
+            game_process.player_one_score(),
+            game_process.player_two_score()
+        );
+
+        let block = Block::default().title("Kalah").borders(Borders::ALL);
+        let paragraph = Paragraph::new(score_text)
+            .block(block)
+            .alignment(ratatui::layout::Alignment::Center);
+
+        f.render_widget(paragraph, f.area());
+    })?;
+
+    // Wait for user to press any key before quitting
+    loop {
+        if let Event::Key(key) = event::read()? {
+            return Ok(key.code == KeyCode::Char('r'));
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use std::{ io, time::{ Duration, Instant }};
+
+use crossterm::event::{ self, Event, KeyCode };
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, Paragraph},
+    Frame, Terminal,
+};
+
+use kalah::game::game_config::GameConfig;
+use kalah::net::client::{ Client, ClientEvent };
+use kalah::net::protocol::{ ChatLine, ClientMessage, Emote, GameState, GameSummary, Phase, ServerMessage, MAX_CHAT_LEN };
+
+use super::{ board_rows, clock_str };
+
+const CHAT_PANE_HEIGHT: u16 = 8;
+const CHAT_SCROLLBACK: usize = 500;
+
+/// What the networked client knows about the lobby and its game.
+struct RemoteView {
+    games: Vec<GameSummary>,
+    /// Game highlighted in the lobby.
+    selected: usize,
+    /// Game we play or watch, None in the lobby.
+    game: Option<u64>,
+    /// Our seat, 0 for player one, None while watching.
+    player: Option<usize>,
+    state: Option<GameState>,
+    /// When the state came, to count down `forfeit_in_ms`.
+    state_received: Instant,
+    is_connected: bool,
+    /// Why the connection was lost, or the last rejected message.
+    message: Option<String>,
+    round_trip: Option<Duration>,
+    latencies: [Option<u64>; 2],
+    /// Chat of the lobby and the games we were in, oldest first.
+    chat: Vec<ChatLine>,
+    /// Lines scrolled back from the newest.
+    chat_scroll: usize,
+    /// Chat line being typed.
+    input: Option<String>,
+}
+
+impl RemoteView {
+
+    fn handle(&mut self, event: ClientEvent) {
+        match event {
+            ClientEvent::Connected => {
+                self.is_connected = true;
+                self.message = None;
+                // The server sends the lobby chat again after the hello
+                self.chat.retain(|line| line.game.is_some());
+            }
+            ClientEvent::Disconnected(reason) => {
+                self.is_connected = false;
+                self.round_trip = None;
+                self.message = Some(reason);
+            }
+            // Only clients in the lobby get the games
+            ClientEvent::Message(ServerMessage::Games { games }) => {
+                self.selected = self.selected.min(games.len().saturating_sub(1));
+                self.games = games;
+                self.game = None;
+                self.player = None;
+                self.state = None;
+            }
+            ClientEvent::Message(ServerMessage::Welcome { game, player, .. }) => {
+                self.enter_game_chat(game);
+                self.game = Some(game);
+                self.player = Some(player as usize - 1);
+                self.message = None;
+            }
+            ClientEvent::Message(ServerMessage::Watching { game }) => {
+                self.enter_game_chat(game);
+                self.game = Some(game);
+                self.player = None;
+                self.message = None;
+            }
+            ClientEvent::Message(ServerMessage::State(state)) => {
+                if Some(state.game) == self.game {
+                    self.message = None;
+                    self.state = Some(state);
+                    self.state_received = Instant::now();
+                }
+            }
+            ClientEvent::Message(ServerMessage::Chat(line)) => {
+                // Scrolled back text stays in place
+                if self.chat_scroll > 0 && line.game == self.game {
+                    self.chat_scroll += 1;
+                }
+                self.chat.push(line);
+                if self.chat.len() > CHAT_SCROLLBACK {
+                    self.chat.remove(0);
+                }
+            }
+            ClientEvent::Message(ServerMessage::Error { message }) => self.message = Some(message),
+            ClientEvent::Message(ServerMessage::Pong { .. }) => {}
+            ClientEvent::Latency { round_trip, latencies } => {
+                self.round_trip = Some(round_trip);
+                self.latencies = latencies;
+            }
+        }
+    }
+
+    /// Forgets the chat of a game we enter, the server sends it again.
+    fn enter_game_chat(&mut self, game: u64) {
+        self.chat.retain(|line| line.game != Some(game));
+        self.chat_scroll = 0;
+    }
+
+    /// Chat of the lobby or our game.
+    fn channel_chat(&self) -> Vec<&ChatLine> {
+        self.chat.iter().filter(|line| line.game == self.game).collect()
+    }
+
+    /// Turns the typed line into a chat message, `/gg` and the like into emotes.
+    fn take_input(&mut self) -> Option<ClientMessage> {
+        let text = self.input.take()?;
+        let text = text.trim();
+        match text.strip_prefix('/').map(Emote::parse) {
+            _ if text.is_empty() => None,
+            Some(Some(emote)) => Some(ClientMessage::Emote { emote }),
+            Some(None) => {
+                self.message = Some("Emotes are /wave, /gl, /gg, /wp, /think and /oops".to_string());
+                None
+            }
+            None => Some(ClientMessage::Chat { text: text.to_string() }),
+        }
+    }
+
+    /// Whether a move from us would be accepted.
+    fn is_our_turn(&self) -> bool {
+        let Some((player, state)) = self.player.zip(self.state.as_ref()) else { return false };
+        self.is_connected && state.phase == Phase::Playing && state.position.is_player_one_turn == (player == 0)
+    }
+
+    fn status_str(&self, address: &str) -> String {
+        if !self.is_connected {
+            return format!("Reconnecting to {}... {}", address, self.message.as_deref().unwrap_or_default());
+        }
+        let Some(state) = &self.state else { return self.message.clone().unwrap_or_default() };
+
+        let names = state.players.each_ref().map(|seat| seat.as_ref().map_or("?", |seat| seat.name.as_str()));
+        let mut status = match (state.phase, self.player) {
+            (Phase::Waiting, _) => "Waiting for an opponent.".to_string(),
+            (Phase::Finished, _) => format!("Game over, {}.", state.result_str()),
+            (Phase::Playing, Some(player)) if state.players[1 - player].as_ref().is_some_and(|seat| !seat.is_connected) => {
+                match state.forfeit_in_ms[1 - player] {
+                    Some(ms) => {
+                        let left = Duration::from_millis(ms).saturating_sub(self.state_received.elapsed());
+                        format!("{} left, forfeits in {}s.", names[1 - player], left.as_secs())
+                    }
+                    None => format!("{} left.", names[1 - player]),
+                }
+            }
+            (Phase::Playing, Some(_)) if self.is_our_turn() => "Your turn.".to_string(),
+            (Phase::Playing, _) => format!("Is {} turn.", names[if state.position.is_player_one_turn { 0 } else { 1 }]),
+        };
+        if let Some(message) = &self.message {
+            status = format!("{} {}", message, status);
+        }
+        status
+    }
+
+    fn latency_str(&self) -> String {
+        let ms = |latency: Option<u64>| latency.map_or("?".to_string(), |latency| format!("{} ms", latency));
+        match self.player {
+            Some(player) => format!(
+                "Ping: you {}, opponent {}",
+                ms(self.round_trip.map(|round_trip| round_trip.as_millis() as u64)),
+                ms(self.latencies[1 - player]),
+            ),
+            None => format!("Ping: P1 {}, P2 {}", ms(self.latencies[0]), ms(self.latencies[1])),
+        }
+    }
+
+    fn lobby_lines(&self) -> Vec<String> {
+        if self.games.is_empty() {
+            return vec!["No games yet, press c to create one.".to_string()];
+        }
+        self.games.iter().enumerate().map(|(index, game)| {
+            let names = game.players.each_ref().map(|seat| seat.as_ref().map_or("(free)", |seat| seat.name.as_str()));
+            format!(
+                "{} #{:<3} {}x{}{} {:<8} {} vs {}, {} watching",
+                if index == self.selected { '>' } else { ' ' },
+                game.id,
+                game.holes,
+                game.stones,
+                if game.pie { " pie" } else { "    " },
+                format!("{:?}", game.phase).to_lowercase(),
+                names[0],
+                names[1],
+                game.spectators,
+            )
+        }).collect()
+    }
+}
+
+/// The chat of the lobby or our game with the line being typed.
+fn render_chat(f: &mut Frame, area: Rect, view: &RemoteView) {
+    let lines = view.channel_chat();
+    let mut height = area.height.saturating_sub(2) as usize;
+    if view.input.is_some() {
+        height = height.saturating_sub(1);
+    }
+
+    let end = lines.len().saturating_sub(view.chat_scroll);
+    let mut text = lines[end.saturating_sub(height)..end].iter().map(|line| line.display()).collect::<Vec<_>>();
+    if let Some(input) = &view.input {
+        text.push(format!("> {}_", input));
+    }
+
+    let title = match (&view.input, view.chat_scroll) {
+        (Some(_), _) => "Chat - Enter to send, Esc to cancel, /gg and other emotes".to_string(),
+        (None, 0) => "Chat - t to talk, PgUp/PgDn to scroll".to_string(),
+        (None, scroll) => format!("Chat - {} newer lines, PgDn to scroll down", scroll),
+    };
+    f.render_widget(Paragraph::new(text.join("\n")).block(Block::default().title(title).borders(Borders::ALL)), area);
+}
+
+/// Shows the lobby and the game from the server and sends our choices until the player quits.
+/// Created games use `game_config`.
+pub fn run_remote(
+    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
+    client: &Client,
+    address: &str,
+    game_config: &GameConfig,
+) -> io::Result<()> {
+    let mut view = RemoteView {
+        games: Vec::new(),
+        selected: 0,
+        game: None,
+        player: None,
+        state: None,
+        state_received: Instant::now(),
+        is_connected: false,
+        message: None,
+        round_trip: None,
+        latencies: [None, None],
+        chat: Vec::new(),
+        chat_scroll: 0,
+        input: None,
+    };
+
+    loop {
+        while let Some(event) = client.try_next() {
+            // Players get their seat back with the hello, spectators ask again
+            if event == ClientEvent::Connected
+                && let Some(game) = view.game.filter(|_| view.player.is_none())
+            {
+                client.send(ClientMessage::WatchGame { game });
+            }
+            view.handle(event);
+        }
+
+        terminal.draw(|f| {
+            let Some(game) = view.game else {
+                let title = format!(
+                    "Kalah - Lobby: ↑/↓ to choose, Enter to join, w to watch, c to create, q to quit. {}",
+                    view.status_str(address),
+                );
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(3), Constraint::Length(CHAT_PANE_HEIGHT)].as_ref())
+                    .split(f.area());
+                let block = Block::default().title(title).borders(Borders::ALL);
+                f.render_widget(Paragraph::new(view.lobby_lines().join("\n")).block(block), chunks[0]);
+                render_chat(f, chunks[1], &view);
+                return;
+            };
+
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .margin(2)
+                .constraints([
+                    Constraint::Length(2),
+                    Constraint::Length(1),
+                    Constraint::Length(3),
+                    Constraint::Length(2),
+                    Constraint::Min(3),
+                ].as_ref())
+                .split(f.area());
+
+            let hole_nums = view.state.as_ref().map_or(0, |state| state.position.side_one.len());
+            let keys = match view.player {
+                Some(_) => format!("Press 1–{} to move, l to leave, q to quit.", hole_nums),
+                None => "Watching, press l to leave, q to quit.".to_string(),
+            };
+            let clock = view.state.as_ref().and_then(|state| {
+                let clock = state.clock_at(view.state_received)?;
+                let names = state.players.each_ref().map(|seat| seat.as_ref().map_or("?", |seat| seat.name.as_str()));
+                Some(format!(" {}", clock_str(&clock, names, Instant::now())))
+            });
+            let clock = clock.unwrap_or_default();
+            let title = match &view.state {
+                Some(state) if state.phase == Phase::Finished => format!(
+                    "Kalah #{}{} - 🏁 Game over, {}. Press l to leave, q to quit.", game, clock, state.result_str()
+                ),
+                _ => format!("Kalah #{}{} - {} {}", game, clock, keys, view.status_str(address)),
+            };
+            f.render_widget(Block::default().title(title).borders(Borders::ALL), f.area());
+
+            if let Some(state) = &view.state {
+                let rows = board_rows(
+                    [&state.position.side_one, &state.position.side_two],
+                    [state.position.side_one_store, state.position.side_two_store],
+                    None,
+                );
+                f.render_widget(Paragraph::new(rows[0].clone()), chunks[0]);
+                f.render_widget(Paragraph::new(rows[1].clone()), chunks[1]);
+                f.render_widget(Paragraph::new(rows[2].clone()), chunks[2]);
+
+                let names = state.players.each_ref().map(|seat| seat.as_ref().map_or("(free)", |seat| seat.name.as_str()));
+                let seat = view.player.map_or(String::new(), |player| format!(" You are P{}.", player + 1));
+                let info = format!("P1: {}  P2: {}.{}\n{}", names[0], names[1], seat, view.latency_str());
+                f.render_widget(Paragraph::new(info), chunks[3]);
+            }
+            render_chat(f, chunks[4], &view);
+        })?;
+
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+        {
+            if let Some(input) = &mut view.input {
+                match key.code {
+                    KeyCode::Char(c) if input.chars().count() < MAX_CHAT_LEN => input.push(c),
+                    KeyCode::Backspace => _ = input.pop(),
+                    KeyCode::Esc => view.input = None,
+                    KeyCode::Enter => {
+                        if let Some(message) = view.take_input() {
+                            client.send(message);
+                        }
+                        view.chat_scroll = 0;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') => return Ok(()),
+
+                // Chat
+                KeyCode::Char('t') => view.input = Some(String::new()),
+                KeyCode::PageUp => view.chat_scroll = (view.chat_scroll + CHAT_PANE_HEIGHT as usize / 2).min(view.channel_chat().len()),
+                KeyCode::PageDown => view.chat_scroll = view.chat_scroll.saturating_sub(CHAT_PANE_HEIGHT as usize / 2),
+
+                // Lobby
+                KeyCode::Up if view.game.is_none() => view.selected = view.selected.saturating_sub(1),
+                KeyCode::Down if view.game.is_none() => view.selected = (view.selected + 1).min(view.games.len().saturating_sub(1)),
+                KeyCode::Enter | KeyCode::Char('w') if view.game.is_none() && let Some(game) = view.games.get(view.selected) => {
+                    client.send(match key.code {
+                        KeyCode::Enter => ClientMessage::JoinGame { game: game.id },
+                        _ => ClientMessage::WatchGame { game: game.id },
+                    });
+                }
+                KeyCode::Char('c') if view.game.is_none() => client.send(ClientMessage::CreateGame {
+                    holes: Some(game_config.hole_nums),
+                    stones: Some(game_config.stone_nums_in_hole),
+                    pie: Some(game_config.is_check_pipe_rule),
+                    time_control: game_config.time_control.map(|time_control| time_control.to_notation()),
+                }),
+
+                // Game
+                KeyCode::Char('l') if view.game.is_some() => client.send(ClientMessage::LeaveGame),
+                KeyCode::Char(c) if view.is_our_turn() && let Some(hole_num) = c.to_digit(10) => {
+                    let hole_nums = view.state.as_ref().map_or(0, |state| state.position.side_one.len());
+                    if (1..=hole_nums).contains(&(hole_num as usize)) {
+                        client.send(ClientMessage::Move { hole: hole_num as usize });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::{ io, sync::mpsc, thread, time::Duration };
+
+use crossterm::event::{ self, Event, KeyCode };
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    widgets::{Block, Borders, Paragraph, Sparkline},
+    Terminal,
+};
+
+use kalah::ai::alpha_beta::AlphaBeta;
+use kalah::ai::analysis::{ analyze_game, record_boards, MoveAnalysis, MoveClass };
+use kalah::ai::opening_book::search_move_values;
+use kalah::game::game_record::GameRecord;
+
+use super::board_rows;
+
+/// Counts of each move class for one player, e.g. `best 12, good 3, ...`.
+pub fn class_summary(analyses: &[MoveAnalysis], is_player_one: bool) -> String {
+    MoveClass::ALL
+        .iter()
+        .map(|&class| {
+            let count = analyses.iter().filter(|analysis| analysis.is_player_one_move == is_player_one && analysis.class == class).count();
+            format!("{} {}", class, count)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+
+/// Analyzes the game on a background thread while showing progress.
+/// None when the player skips the analysis.
+pub fn run_analysis(
+    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
+    record: &GameRecord,
+    mut alpha_beta: AlphaBeta,
+    depth: u8,
+) -> io::Result<Option<Vec<MoveAnalysis>>> {
+    let (sender, receiver) = mpsc::channel();
+    let thread_record = record.clone();
+    let handle = thread::spawn(move || analyze_game(
+        &thread_record,
+        |board| search_move_values(&mut alpha_beta, board, depth),
+        |done, _| _ = sender.send(done),
+    ));
+
+    let mut done = 0;
+    loop {
+        if let Some(last) = receiver.try_iter().last() {
+            done = last;
+        }
+        if handle.is_finished() {
+            return Ok(handle.join().expect("analysis thread panicked").ok());
+        }
+
+        terminal.draw(|f| {
+            let text = format!("Analyzing move {}/{} at depth {}...", done + 1, record.moves.len(), depth);
+            let block = Block::default().title("Kalah - Press q to skip the review.").borders(Borders::ALL);
+            f.render_widget(Paragraph::new(text).block(block), f.area());
+        })?;
+
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+            && key.code == KeyCode::Char('q')
+        {
+            return Ok(None);
+        }
+    }
+}
+
+/// Steps through the analysed moves with the evaluation graph below the board.
+pub fn run_review(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, record: &GameRecord, analyses: &[MoveAnalysis]) -> io::Result<()> {
+    let Ok(boards) = record_boards(record) else { return Ok(()) };
+    let values = analyses.iter().map(MoveAnalysis::player_one_value).collect::<Vec<_>>();
+    let lowest = values.iter().copied().min().unwrap_or(0);
+    let graph = values.iter().map(|&value| (value - lowest + 1) as u64).collect::<Vec<_>>();
+    let mut index = 0;
+
+    loop {
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .margin(2)
+                .constraints([
+                    Constraint::Length(2),
+                    Constraint::Length(1),
+                    Constraint::Length(2),
+                    Constraint::Length(4),
+                    Constraint::Length(7),
+                    Constraint::Length(1),
+                    Constraint::Min(0),
+                ])
+                .split(f.area());
+
+            let board = &boards[index];
+            let hole_nums = board.hole_nums as usize;
+            let sides = board.holes.map(|side| side[..hole_nums].iter().map(|&count| count as usize).collect::<Vec<_>>());
+            let rows = board_rows([&sides[0], &sides[1]], [board.stores[0] as usize, board.stores[1] as usize], None);
+
+            let info = match index.checked_sub(1).map(|move_index| &analyses[move_index]) {
+                Some(analysis) => format!(
+                    "Move {}: {} played {}, {}",
+                    index,
+                    if analysis.is_player_one_move { &record.player_one_name } else { &record.player_two_name },
+                    analysis.hole_num,
+                    analysis.comment(),
+                ),
+                None => "Start position".to_string(),
+            };
+            let summary = [(true, &record.player_one_name), (false, &record.player_two_name)]
+                .map(|(is_player_one, name)| format!("{}: {}", name, class_summary(analyses, is_player_one)))
+                .join("\n");
+
+            let block = Block::default()
+                .title(format!("Kalah - Review move {}/{}: ←/→ to step, Home/End, q to quit.", index, analyses.len()))
+                .borders(Borders::ALL);
+            let sparkline = Sparkline::default()
+                .block(Block::default().title("Evaluation for player one").borders(Borders::TOP))
+                .data(&graph);
+            let marker = if index > 0 { format!("{}^", " ".repeat(index - 1)) } else { String::new() };
+
+            f.render_widget(block, f.area());
+            f.render_widget(Paragraph::new(rows[0].clone()), chunks[0]);
+            f.render_widget(Paragraph::new(rows[1].clone()), chunks[1]);
+            f.render_widget(Paragraph::new(rows[2].clone()), chunks[2]);
+            f.render_widget(Paragraph::new(format!("{}\n\n{}", info, summary)), chunks[3]);
+            f.render_widget(sparkline, chunks[4]);
+            f.render_widget(Paragraph::new(marker), chunks[5]);
+        })?;
+
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Left => index = index.saturating_sub(1),
+                KeyCode::Right => index = (index + 1).min(analyses.len()),
+                KeyCode::Home => index = 0,
+                KeyCode::End => index = analyses.len(),
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::io;
+
+use crossterm::event::{ self, Event, KeyCode };
+use ratatui::{
+    backend::CrosstermBackend,
+    widgets::{Block, Borders, Paragraph},
+    Terminal,
+};
+use strum::IntoEnumIterator;
+
+use kalah::ai::difficulty::Level;
+
+/// Lets the player pick the computer's level, None when they quit.
+pub fn run_setup(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) -> io::Result<Option<Level>> {
+    let levels = Level::iter().collect::<Vec<_>>();
+    let mut selected = levels.iter().position(|&level| level == Level::Master).unwrap_or(0);
+
+    loop {
+        terminal.draw(|f| {
+            let lines = levels
+                .iter()
+                .enumerate()
+                .map(|(index, level)| format!(
+                    "{} {}. {:<13} {}",
+                    if index == selected { '>' } else { ' ' }, index + 1, level.name(), level.description()
+                ))
+                .collect::<Vec<_>>();
+
+            let block = Block::default()
+                .title(format!("Kalah - Choose the computer's level: ↑/↓ and Enter or 1–{}, q to quit.", levels.len()))
+                .borders(Borders::ALL);
+            f.render_widget(Paragraph::new(lines.join("\n")).block(block), f.area());
+        })?;
+
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Down => selected = (selected + 1).min(levels.len() - 1),
+                KeyCode::Enter => return Ok(Some(levels[selected])),
+                KeyCode::Char(c) if c.to_digit(10).is_some_and(|digit| (1..=levels.len() as u32).contains(&digit)) => {
+                    return Ok(Some(levels[c.to_digit(10).unwrap() as usize - 1]));
+                }
+                KeyCode::Char('q') => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use kalah::ai::analysis::*;
+use kalah::ai::compact_board::*;
+use kalah::ai::opening_book::*;
+use kalah::ai::solver::*;
+use kalah::game::game_config::*;
+use kalah::game::game_field::*;
+use kalah::game::game_process::*;
+use kalah::game::game_record::*;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+
+const TABLE_SIZE: usize = 1 << 16;
+
+/// Finished game on a small board, each move picked by `choose` from the legal moves.
+fn play_game(game_config: &GameConfig, mut choose: impl FnMut(&CompactBoard) -> usize) -> GameRecord {
+    let game_field = GameField::build(game_config);
+    let mut game_process = GameProcess::build(game_field, "P1".to_string(), "P2".to_string(), game_config.clone());
+
+    loop {
+        let board = CompactBoard::from_process(&game_process);
+        if board.is_game_over() {
+            return GameRecord::from_process(&game_process, true);
+        }
+        game_process.move_stones_from_hole(choose(&board)).unwrap();
+    }
+}
+
+#[test]
+fn test_move_classes() {
+    assert_eq!(MoveClass::from_loss(0), MoveClass::Best);
+    assert_eq!(MoveClass::from_loss(1), MoveClass::Good);
+    assert_eq!(MoveClass::from_loss(3), MoveClass::Inaccuracy);
+    assert_eq!(MoveClass::from_loss(4), MoveClass::Mistake);
+    assert_eq!(MoveClass::from_loss(6), MoveClass::Mistake);
+    assert_eq!(MoveClass::from_loss(7), MoveClass::Blunder);
+    assert_eq!(MoveClass::Inaccuracy.to_string(), "inaccuracy");
+    assert!(MoveClass::ALL.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_analyze_random_game() {
+    let game_config = GameConfig::build(3, 3, false).unwrap();
+    let mut rng = StdRng::seed_from_u64(1);
+    let record = play_game(&game_config, |board| *board.legal_moves().collect::<Vec<_>>().choose(&mut rng).unwrap());
+
+    let mut solver = Solver::build(TABLE_SIZE);
+    let mut progress = Vec::new();
+    let analyses = analyze_game(&record, |board| solver_move_values(&mut solver, board), |done, total| progress.push((done, total))).unwrap();
+
+    assert_eq!(analyses.len(), record.moves.len());
+    assert_eq!(progress.last(), Some(&(record.moves.len(), record.moves.len())));
+
+    let boards = record_boards(&record).unwrap();
+    let mut solver = Solver::build(TABLE_SIZE);
+    for (analysis, board) in analyses.iter().zip(&boards) {
+        let move_values = solver_move_values(&mut solver, board);
+        assert_eq!(move_values.iter().map(|&(_, value)| value).max(), Some(analysis.best_value));
+        assert!(move_values.contains(&(analysis.hole_num, analysis.value)));
+        assert!(move_values.contains(&(analysis.best_move, analysis.best_value)));
+
+        assert_eq!(analysis.is_player_one_move, board.is_player_one_turn);
+        assert!(analysis.loss() >= 0);
+        assert_eq!(analysis.class, MoveClass::from_loss(analysis.loss()));
+    }
+
+    // Perfect values add up: the last move's value is the final score difference
+    let last = analyses.last().unwrap();
+    let (one, two) = record.result.unwrap();
+    assert_eq!(last.player_one_value(), one as i32 - two as i32);
+}
+
+#[test]
+fn test_perfect_game_is_all_best() {
+    let game_config = GameConfig::build(2, 4, false).unwrap();
+    let mut solver = Solver::build(TABLE_SIZE);
+    let record = play_game(&game_config, |board| solver.solve(board).unwrap().best_move.unwrap());
+
+    let mut solver = Solver::build(TABLE_SIZE);
+    let analyses = analyze_game(&record, |board| solver_move_values(&mut solver, board), |_, _| {}).unwrap();
+    assert!(analyses.iter().all(|analysis| analysis.class == MoveClass::Best));
+}
+
+#[test]
+fn test_annotated_record() {
+    let game_config = GameConfig::build(3, 3, true).unwrap();
+    let mut rng = StdRng::seed_from_u64(2);
+    let record = play_game(&game_config, |board| *board.legal_moves().collect::<Vec<_>>().choose(&mut rng).unwrap());
+
+    let mut solver = Solver::build(TABLE_SIZE);
+    let analyses = analyze_game(&record, |board| solver_move_values(&mut solver, board), |_, _| {}).unwrap();
+    let annotated = annotate(&record, &analyses);
+
+    assert_eq!(annotated.moves, record.moves);
+    assert_eq!(annotated.comments.len(), record.moves.len());
+    assert_eq!(annotated.comments[0], (1, analyses[0].comment()));
+
+    let text = annotated.to_text();
+    assert!(text.contains(&format!("{} {{{}}}", record.moves[0], analyses[0].comment())));
+    let parsed = GameRecord::parse(&text).unwrap();
+    assert_eq!(parsed, annotated);
+    assert!(parsed.replay().is_ok());
+}
+
+#[test]
+fn test_comments() {
+    let analysis = MoveAnalysis {
+        hole_num: 3,
+        is_player_one_move: false,
+        value: -4,
+        best_move: 2,
+        best_value: 1,
+        class: MoveClass::from_loss(5),
+    };
+    assert_eq!(analysis.comment(), "mistake -4, best 2 +1");
+    assert_eq!(analysis.player_one_value(), 4);
+
+    let best = MoveAnalysis { hole_num: 2, value: 1, class: MoveClass::Best, ..analysis };
+    assert_eq!(best.comment(), "best +1");
+}
+
+#[test]
+fn test_illegal_record() {
+    let text = "[PlayerOne \"P1\"]\n[PlayerTwo \"P2\"]\n[Holes \"2\"]\n[Stones \"1\"]\n\n2 2\n";
+    let record = GameRecord::parse(text).unwrap();
+
+    let mut solver = Solver::build(TABLE_SIZE);
+    let err = analyze_game(&record, |board| solver_move_values(&mut solver, board), |_, _| {}).err().unwrap();
+    assert_eq!(err, "Move 2 (2) is illegal");
+    assert!(record_boards(&record).err().unwrap().starts_with("Move 2 (2) is illegal"));
+}
@@ -0,0 +1,70 @@
+use kalah::ai::alpha_beta::*;
+use kalah::ai::difficulty::*;
+use kalah::ai::transposition_table::*;
+use kalah::game::game_config::*;
+use kalah::net::bot::*;
+use kalah::net::client::*;
+use kalah::net::protocol::*;
+use kalah::net::server::*;
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const TABLE_SIZE: usize = 1 << 16;
+
+/// Next message from the server, skipping connection and latency events.
+fn next_message(client: &Client) -> ServerMessage {
+    loop {
+        match client.next_timeout(TIMEOUT).expect("no event from the client") {
+            ClientEvent::Message(message) => return message,
+            ClientEvent::Disconnected(reason) => panic!("disconnected: {}", reason),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_bot_plays_whoever_joins() {
+    let game_config = GameConfig::build(2, 3, false).unwrap();
+    let server = Server::bind("127.0.0.1:0", game_config.clone()).unwrap();
+    let address = server.local_addr().unwrap().to_string();
+    thread::spawn(move || server.run());
+
+    let (events, bot_events) = mpsc::channel();
+    let bot_address = address.clone();
+    thread::spawn(move || {
+        let engine = LevelEngine::build(AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred), Level::Novice.settings().unwrap()).with_seed(1);
+        let mut bot = Bot::connect(bot_address, "Computer (novice)".to_string(), engine, game_config, Duration::from_millis(50));
+        bot.run(|event| { let _ = events.send(event); })
+    });
+    assert_eq!(bot_events.recv_timeout(TIMEOUT), Ok(BotEvent::Connected));
+    assert_eq!(bot_events.recv_timeout(TIMEOUT), Ok(BotEvent::Playing { game: 1, player: 1 }));
+
+    // The bot's game shows up in the lobby
+    let alice = Client::connect(address, "Alice".to_string());
+    loop {
+        if let ServerMessage::Games { games } = next_message(&alice) && !games.is_empty() {
+            break;
+        }
+    }
+    alice.send(ClientMessage::JoinGame { game: 1 });
+    assert!(matches!(next_message(&alice), ServerMessage::Welcome { game: 1, player: 2, .. }));
+
+    // Alice always sows from her first hole with stones until the game is over
+    let state = loop {
+        if let ServerMessage::State(state) = next_message(&alice) {
+            match state.phase {
+                Phase::Finished => break state,
+                Phase::Playing if !state.position.is_player_one_turn => {
+                    let hole = state.position.side_two.iter().position(|&stones| stones > 0).unwrap();
+                    alice.send(ClientMessage::Move { hole: hole + 1 });
+                }
+                _ => {}
+            }
+        }
+    };
+    assert_eq!(state.scores[0] + state.scores[1], 12);
+    assert!(matches!(bot_events.recv_timeout(TIMEOUT), Ok(BotEvent::Finished { game: 1, .. })));
+}
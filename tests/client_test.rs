@@ -0,0 +1,93 @@
+use kalah::game::game_config::*;
+use kalah::net::client::*;
+use kalah::net::protocol::*;
+use kalah::net::server::*;
+
+use std::io::{ BufRead, BufReader, Write };
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Next event that is not a latency measurement.
+fn next_event(client: &Client) -> ClientEvent {
+    loop {
+        match client.next_timeout(TIMEOUT).expect("no event from the client") {
+            ClientEvent::Latency { .. } => {}
+            event => return event,
+        }
+    }
+}
+
+fn next_state(client: &Client) -> GameState {
+    match next_event(client) {
+        ClientEvent::Message(ServerMessage::State(state)) => state,
+        event => panic!("expected a state, got {:?}", event),
+    }
+}
+
+#[test]
+fn test_client_plays_on_server() {
+    let server = Server::bind("127.0.0.1:0", GameConfig::build(4, 6, true).unwrap()).unwrap();
+    let address = server.local_addr().unwrap().to_string();
+    thread::spawn(move || server.run());
+
+    let alice = Client::connect(address.clone(), "Alice".to_string());
+    assert_eq!(next_event(&alice), ClientEvent::Connected);
+
+    // The first ping goes out right after the hello, so its pong comes after the lobby
+    assert_eq!(alice.next_timeout(TIMEOUT), Some(ClientEvent::Message(ServerMessage::Games { games: vec![] })));
+    assert!(matches!(alice.next_timeout(TIMEOUT), Some(ClientEvent::Latency { latencies: [None, None], .. })));
+
+    alice.send(ClientMessage::CreateGame { holes: None, stones: None, pie: None, time_control: None });
+    assert!(matches!(next_event(&alice), ClientEvent::Message(ServerMessage::Welcome { game: 1, player: 1, .. })));
+    assert_eq!(next_state(&alice).phase, Phase::Waiting);
+
+    let bob = Client::connect(address, "Bob".to_string());
+    assert_eq!(next_event(&bob), ClientEvent::Connected);
+    assert!(matches!(next_event(&bob), ClientEvent::Message(ServerMessage::Games { games }) if games.len() == 1));
+    bob.send(ClientMessage::JoinGame { game: 1 });
+    assert!(matches!(next_event(&bob), ClientEvent::Message(ServerMessage::Welcome { game: 1, player: 2, .. })));
+    assert_eq!(next_state(&bob).phase, Phase::Playing);
+    assert_eq!(next_state(&alice).phase, Phase::Playing);
+
+    alice.send(ClientMessage::Move { hole: 3 });
+    assert_eq!(next_state(&alice).last_move, Some(3));
+    assert_eq!(next_state(&bob).last_move, Some(3));
+
+    // The round trip of the first ping goes out with the next one
+    let latencies = loop {
+        if let ClientEvent::Latency { latencies, .. } = alice.next_timeout(TIMEOUT).expect("no latency from the client") {
+            break latencies;
+        }
+    };
+    assert!(latencies[0].is_some());
+}
+
+#[test]
+fn test_client_reconnects() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    let client = Client::connect(address, "Alice".to_string());
+
+    // The first connection is dropped after seating the client
+    let (stream, _) = listener.accept().unwrap();
+    let mut writer = stream.try_clone().unwrap();
+    let mut lines = BufReader::new(stream).lines();
+    assert_eq!(ClientMessage::parse(&lines.next().unwrap().unwrap()), Ok(ClientMessage::Hello { name: "Alice".to_string() }));
+    let welcome = ServerMessage::Welcome { game: 1, player: 1, token: "5f0c".to_string() };
+    writeln!(writer, "{}", welcome.to_line()).unwrap();
+    assert_eq!(next_event(&client), ClientEvent::Connected);
+    assert_eq!(next_event(&client), ClientEvent::Message(welcome));
+    drop((writer, lines));
+    assert!(matches!(next_event(&client), ClientEvent::Disconnected(_)));
+
+    // The client comes back for its seat
+    let (stream, _) = listener.accept().unwrap();
+    let mut lines = BufReader::new(stream).lines();
+    let resume = ClientMessage::Resume { name: "Alice".to_string(), token: "5f0c".to_string() };
+    assert_eq!(ClientMessage::parse(&lines.next().unwrap().unwrap()), Ok(resume));
+    assert_eq!(next_event(&client), ClientEvent::Connected);
+}
@@ -0,0 +1,83 @@
+use kalah::ai::compact_board::*;
+use kalah::game;
+
+use game::game_config::*;
+use game::game_field::*;
+use game::game_process::*;
+use game::game_status::*;
+use game::position::*;
+
+use proptest::prelude::*;
+
+fn build_process(game_config: &GameConfig) -> GameProcess {
+    let game_field = GameField::build(game_config);
+    GameProcess::build(game_field, "P1".to_string(), "P2".to_string(), game_config.clone())
+}
+
+#[test]
+fn test_compact_board_conversion() {
+    let game_config = GameConfig::build(4, 6, true).unwrap();
+    let mut game_process = build_process(&game_config);
+    _ = game_process.move_stones_from_hole(2);
+
+    let board = CompactBoard::from_process(&game_process);
+    assert_eq!(board.holes[0][..6], [4, 0, 5, 5, 5, 5]);
+    assert_eq!(board.holes[1][..6], [4, 4, 4, 4, 4, 4]);
+    assert!(!board.is_player_one_turn);
+    assert_eq!(board.total_turns, 1);
+    assert_eq!(board.to_position(), Position::from_process(&game_process));
+
+    let converted = board.to_process("P1".to_string(), "P2".to_string(), game_config).unwrap();
+    assert_eq!(CompactBoard::from_process(&converted), board);
+}
+
+#[test]
+fn test_compact_board_errors() {
+    let mut board = CompactBoard::build(&GameConfig::build(4, 6, false).unwrap());
+
+    assert_eq!(board.apply_move(0).unwrap_err(), "hole_num must be in range 1..=6 (got 0)");
+    assert_eq!(board.apply_move(3), Ok(GameStatus::Run));
+    assert_eq!(board.apply_move(7).unwrap_err(), "hole_num must be in range 1..=6 (got 7)");
+
+    board.is_player_one_turn = true;
+    assert_eq!(board.apply_move(3).unwrap_err(), "Selected hole is empty");
+    assert_eq!(board.legal_moves().collect::<Vec<_>>(), vec![1, 2, 4, 5, 6]);
+
+    // The turn counter stops at its maximum
+    let mut position = Position::parse("1:0/1:0 1 4294967295").unwrap();
+    let mut board = CompactBoard::from_position(&position, false).unwrap();
+    assert_eq!(board.apply_move(1), Ok(GameStatus::Finished));
+    assert_eq!(board.total_turns, u32::MAX);
+    position.total_turns += 1;
+    assert_eq!(CompactBoard::from_position(&position, false).unwrap().total_turns, u32::MAX);
+}
+
+proptest! {
+
+    #[test]
+    fn compact_board_matches_game_process(
+        stone_nums_in_hole in 1..=9usize,
+        hole_nums in 1..=9usize,
+        is_pie_rule in any::<bool>(),
+        choices in prop::collection::vec(0..=10usize, 0..300),
+    ) {
+        let game_config = GameConfig::build(stone_nums_in_hole, hole_nums, is_pie_rule).unwrap();
+        let mut game_process = build_process(&game_config);
+        let mut board = CompactBoard::build(&game_config);
+        prop_assert_eq!(board, CompactBoard::from_process(&game_process));
+
+        for hole_num in choices {
+            let process_status = game_process.move_stones_from_hole(hole_num);
+            let board_status = board.apply_move(hole_num);
+
+            prop_assert_eq!(&board_status, &process_status);
+            prop_assert_eq!(board, CompactBoard::from_process(&game_process));
+            prop_assert_eq!(board.score_diff(), game_process.player_one_score() as i32 - game_process.player_two_score() as i32);
+
+            if process_status == Ok(GameStatus::Finished) {
+                prop_assert!(board.is_game_over());
+                break;
+            }
+        }
+    }
+}
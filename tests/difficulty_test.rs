@@ -0,0 +1,134 @@
+use kalah::ai::alpha_beta::*;
+use kalah::ai::compact_board::*;
+use kalah::ai::difficulty::*;
+use kalah::ai::engine::*;
+use kalah::ai::opening_book::*;
+use kalah::ai::transposition_table::*;
+use kalah::game::game_config::*;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use strum::IntoEnumIterator;
+
+const TABLE_SIZE: usize = 1 << 16;
+
+fn level_engine(settings: LevelSettings, seed: u64) -> LevelEngine {
+    LevelEngine::build(AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred), settings).with_seed(seed)
+}
+
+fn think(engine: &mut impl Engine, board: &CompactBoard) -> SearchResult {
+    engine.think(board, &SearchLimits::depth(MAX_DEPTH), &mut |_| {})
+}
+
+/// Start position after a few random moves, so games differ.
+fn random_opening(game_config: &GameConfig, plies: usize, rng: &mut StdRng) -> CompactBoard {
+    let mut board = CompactBoard::build(game_config);
+    for _ in 0..plies {
+        let hole_num = *board.legal_moves().collect::<Vec<_>>().choose(rng).unwrap();
+        board.apply_move(hole_num).unwrap();
+    }
+    board
+}
+
+#[test]
+fn test_levels() {
+    let levels = Level::iter().collect::<Vec<_>>();
+    assert_eq!(levels.first(), Some(&Level::Novice));
+    assert_eq!(levels.last(), Some(&Level::Perfect));
+
+    for level in &levels {
+        assert_eq!(Level::parse(level.name()), Ok(*level));
+    }
+    assert!(Level::parse("grandmaster").err().unwrap().contains("novice, casual"));
+
+    // Weaker levels search less deep and slip more often
+    let settings = levels.iter().filter_map(Level::settings).collect::<Vec<_>>();
+    assert_eq!(settings.len(), 4);
+    assert!(settings.windows(2).all(|pair| pair[0].depth < pair[1].depth && pair[0].blunder_chance > pair[1].blunder_chance));
+    assert_eq!(Level::Master.settings(), None);
+    assert_eq!(Level::Perfect.settings(), None);
+}
+
+#[test]
+fn test_without_handicaps_plays_best_move() {
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let settings = LevelSettings { depth: 4, noise: 0, blunder_chance: 0.0, blunder_depth: 1 };
+    let mut engine = level_engine(settings, 1);
+    let mut alpha_beta = AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred);
+    let mut rng = StdRng::seed_from_u64(1);
+
+    for _ in 0..5 {
+        let board = random_opening(&game_config, 3, &mut rng);
+        let best = search_move_values(&mut alpha_beta, &board, 4).into_iter().map(|(_, value)| value).max().unwrap();
+
+        let result = think(&mut engine, &board);
+        assert_eq!(result.value, best);
+        assert_eq!(result.depth, 4);
+        assert!(!result.is_exact && !result.is_book_move);
+    }
+}
+
+#[test]
+fn test_blunders_are_greedy() {
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let settings = LevelSettings { depth: 6, noise: 0, blunder_chance: 1.0, blunder_depth: 1 };
+    let mut engine = level_engine(settings, 2);
+    let mut alpha_beta = AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred);
+    let mut rng = StdRng::seed_from_u64(2);
+
+    for _ in 0..5 {
+        let board = random_opening(&game_config, 4, &mut rng);
+        let greedy = search_move_values(&mut alpha_beta, &board, 1).into_iter().map(|(_, value)| value).max().unwrap();
+
+        let result = think(&mut engine, &board);
+        assert_eq!((result.depth, result.value), (1, greedy));
+    }
+}
+
+#[test]
+fn test_noise_keeps_moves_close() {
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let settings = LevelSettings { depth: 3, noise: 2, blunder_chance: 0.0, blunder_depth: 1 };
+    let mut engine = level_engine(settings, 3);
+    let mut alpha_beta = AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred);
+    let mut rng = StdRng::seed_from_u64(3);
+
+    for _ in 0..10 {
+        let board = random_opening(&game_config, 2, &mut rng);
+        let move_values = search_move_values(&mut alpha_beta, &board, 3);
+        let best = move_values.iter().map(|&(_, value)| value).max().unwrap();
+
+        let result = think(&mut engine, &board);
+        let value = move_values.iter().find(|&&(hole_num, _)| Some(hole_num) == result.best_move).unwrap().1;
+        assert!(best - value <= 2 * settings.noise);
+    }
+}
+
+#[test]
+fn test_stronger_level_wins() {
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let mut rng = StdRng::seed_from_u64(4);
+    let mut engines = [
+        level_engine(Level::Novice.settings().unwrap(), 5),
+        level_engine(Level::Intermediate.settings().unwrap(), 6),
+    ];
+    let mut intermediate_wins = 0;
+
+    for game in 0..10 {
+        let intermediate_side = game % 2;
+        let mut board = random_opening(&game_config, 2, &mut rng);
+
+        while !board.is_game_over() {
+            let engine = &mut engines[(board.mover() == intermediate_side) as usize];
+            let hole_num = think(engine, &board).best_move.unwrap();
+            board.apply_move(hole_num).unwrap();
+        }
+
+        if board.stores[intermediate_side] > board.stores[1 - intermediate_side] {
+            intermediate_wins += 1;
+        }
+    }
+
+    assert!(intermediate_wins >= 7, "intermediate won {} of 10", intermediate_wins);
+}
@@ -0,0 +1,168 @@
+use kalah::ai::alpha_beta::*;
+use kalah::ai::compact_board::*;
+use kalah::ai::endgame::*;
+use kalah::ai::solver::*;
+use kalah::ai::transposition_table::*;
+use kalah::game::game_config::*;
+
+use proptest::prelude::*;
+
+use std::sync::{ Arc, OnceLock };
+
+const TABLE_SIZE: usize = 1 << 16;
+
+/// Shared 4-hole database, generating it once keeps the tests fast.
+fn four_hole_database() -> Arc<EndgameDatabase> {
+    static DATABASE: OnceLock<Arc<EndgameDatabase>> = OnceLock::new();
+    Arc::clone(DATABASE.get_or_init(|| Arc::new(EndgameDatabase::generate(4, 8, |_, _| {}).unwrap())))
+}
+
+fn board_with(hole_nums: usize, mover: &[u8], opponent: &[u8], stores: [u8; 2], is_player_one_turn: bool) -> CompactBoard {
+    let mut board = CompactBoard::build(&GameConfig::build(1, hole_nums, false).unwrap());
+    let (mover_index, opponent_index) = if is_player_one_turn { (0, 1) } else { (1, 0) };
+
+    board.holes = [[0; MAX_HOLES]; 2];
+    board.holes[mover_index][..hole_nums].copy_from_slice(mover);
+    board.holes[opponent_index][..hole_nums].copy_from_slice(opponent);
+    board.stores = stores;
+    board.is_player_one_turn = is_player_one_turn;
+    board.total_turns = 10;
+    board
+}
+
+#[test]
+fn test_generate() {
+    let mut classes = Vec::new();
+    let database = EndgameDatabase::generate(2, 3, |seeds, positions| classes.push((seeds, positions))).unwrap();
+
+    // Distributions of 0..=3 seeds over 4 holes
+    assert_eq!(classes, vec![(0, 1), (1, 4), (2, 10), (3, 20)]);
+    assert_eq!(database.len(), 35);
+    assert_eq!(database.hole_nums(), 2);
+    assert_eq!(database.max_seeds(), 3);
+
+    assert!(EndgameDatabase::generate(0, 3, |_, _| {}).is_err());
+    assert!(EndgameDatabase::generate(2, MAX_ENDGAME_SEEDS + 1, |_, _| {}).is_err());
+}
+
+#[test]
+fn test_probe_known_positions() {
+    let database = four_hole_database();
+
+    // Mover's last seed reaches the store, then the mover's side is empty and the opponent keeps 3
+    let board = board_with(4, &[0, 0, 0, 1], &[1, 1, 1, 0], [5, 2], true);
+    assert_eq!(database.probe(&board), Some(5 + 1 - 2 - 3));
+
+    // Same position with player two to move and the stores swapped
+    let board = board_with(4, &[0, 0, 0, 1], &[1, 1, 1, 0], [2, 5], false);
+    assert_eq!(database.probe(&board), Some(5 + 1 - 2 - 3));
+}
+
+#[test]
+fn test_probe_outside_database() {
+    let database = four_hole_database();
+
+    // Too many seeds
+    let board = CompactBoard::build(&GameConfig::build(4, 4, false).unwrap());
+    assert_eq!(database.probe(&board), None);
+
+    // Other hole count
+    let board = board_with(3, &[1, 0, 0], &[1, 0, 0], [0, 0], true);
+    assert_eq!(database.probe(&board), None);
+
+    // The pie rule can still swap sides
+    let mut board = CompactBoard::build(&GameConfig::build(1, 4, true).unwrap());
+    assert_eq!(database.probe(&board), None);
+    board.total_turns = 1;
+    assert!(database.probe(&board).is_some());
+}
+
+#[test]
+fn test_file_round_trip() {
+    let database = EndgameDatabase::generate(3, 6, |_, _| {}).unwrap();
+    let mut bytes = Vec::new();
+    database.write_to(&mut bytes).unwrap();
+
+    let loaded = EndgameDatabase::read_from(&mut bytes.as_slice()).unwrap();
+    assert_eq!(loaded.hole_nums(), 3);
+    assert_eq!(loaded.max_seeds(), 6);
+    assert_eq!(loaded.len(), database.len());
+
+    let board = board_with(3, &[2, 0, 1], &[0, 2, 1], [7, 3], false);
+    assert_eq!(loaded.probe(&board), database.probe(&board));
+
+    let path = std::env::temp_dir().join(format!("kalah-endgame-test-{}.egdb", std::process::id()));
+    database.save(&path).unwrap();
+    let loaded = EndgameDatabase::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.probe(&board), database.probe(&board));
+}
+
+#[test]
+fn test_corrupted_files() {
+    let database = EndgameDatabase::generate(3, 4, |_, _| {}).unwrap();
+    let mut bytes = Vec::new();
+    database.write_to(&mut bytes).unwrap();
+
+    let read = |bytes: &[u8]| EndgameDatabase::read_from(&mut &bytes[..]).err().unwrap();
+
+    let mut value_flipped = bytes.clone();
+    *value_flipped.last_mut().unwrap() ^= 1;
+    assert!(read(&value_flipped).contains("checksum mismatch"));
+
+    let mut header_flipped = bytes.clone();
+    header_flipped[10] ^= 1;
+    assert!(read(&header_flipped).contains("header checksum mismatch"));
+
+    let mut magic_changed = bytes.clone();
+    magic_changed[0] = b'X';
+    assert_eq!(read(&magic_changed), "Not an endgame database");
+
+    assert!(read(&bytes[..bytes.len() - 1]).starts_with("Truncated"));
+    assert!(EndgameDatabase::load("/nonexistent/kalah.egdb").is_err());
+}
+
+#[test]
+fn test_engines_probe_database() {
+    let database = four_hole_database();
+    let board = board_with(4, &[2, 1, 0, 2], &[1, 0, 1, 1], [14, 16], true);
+
+    let mut solver = Solver::build(TABLE_SIZE);
+    let expected = solver.solve(&board).unwrap();
+
+    let mut solver = Solver::build(TABLE_SIZE).with_endgame(Arc::clone(&database));
+    let solution = solver.solve(&board).unwrap();
+    assert_eq!(solution.value, expected.value);
+    assert!(solution.stats.endgame_hits > 0);
+    assert!(solution.stats.nodes < expected.stats.nodes);
+    assert!(board.is_legal_move(solution.best_move.unwrap()));
+
+    // Every child is in the database, so a shallow search is already exact
+    let mut alpha_beta = AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred).with_endgame(database);
+    let result = alpha_beta.search(&board, 1);
+    assert!(result.is_exact);
+    assert_eq!(result.value, expected.value);
+    assert!(result.stats.endgame_hits > 0);
+}
+
+proptest! {
+
+    #[test]
+    fn probe_matches_solver(
+        hole_nums in 1..=4usize,
+        mover in prop::collection::vec(0..=3u8, 4),
+        opponent in prop::collection::vec(0..=3u8, 4),
+        stores in prop::array::uniform2(0..=20u8),
+        is_player_one_turn in any::<bool>(),
+    ) {
+        let seeds = mover[..hole_nums].iter().chain(&opponent[..hole_nums]).map(|&count| count as usize).sum::<usize>();
+        prop_assume!(seeds <= 8);
+
+        let database = EndgameDatabase::generate(hole_nums, 8, |_, _| {}).unwrap();
+        let board = board_with(hole_nums, &mover[..hole_nums], &opponent[..hole_nums], stores, is_player_one_turn);
+        prop_assume!(!board.is_game_over());
+
+        let expected = Solver::build(TABLE_SIZE).solve(&board).unwrap().value;
+        prop_assert_eq!(database.probe(&board), Some(expected));
+    }
+}
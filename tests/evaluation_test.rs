@@ -0,0 +1,142 @@
+use kalah::ai::alpha_beta::*;
+use kalah::ai::compact_board::*;
+use kalah::ai::evaluation::*;
+use kalah::ai::transposition_table::*;
+use kalah::ai::tuning::*;
+use kalah::game::game_config::*;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+
+use std::sync::Arc;
+
+const TABLE_SIZE: usize = 1 << 16;
+
+struct Constant(i32);
+
+impl Evaluator for Constant {
+
+    fn evaluate(&self, _board: &CompactBoard) -> i32 {
+        self.0
+    }
+}
+
+fn three_hole_board(is_player_one_turn: bool) -> CompactBoard {
+    let mut board = CompactBoard::build(&GameConfig::build(1, 3, false).unwrap());
+    board.holes[0][..3].copy_from_slice(&[1, 2, 0]);
+    board.holes[1][..3].copy_from_slice(&[1, 0, 3]);
+    board.stores = [4, 5];
+    board.is_player_one_turn = is_player_one_turn;
+    board.total_turns = 6;
+    board
+}
+
+#[test]
+fn test_features() {
+    // Player one: 3 seeds in 2 holes, hole 2 ends in the store, no capture
+    // Player two: 4 seeds in 2 holes, hole 1 captures 2, 3 seeds next to the store
+    assert_eq!(features(&three_hole_board(true)), [-1.0, -1.0, 0.0, 1.0, -2.0, -3.0]);
+    assert_eq!(features(&three_hole_board(false)), [1.0, 1.0, 0.0, -1.0, 2.0, 3.0]);
+
+    let evaluator = LinearEvaluator { weights: [1.0, 0.5, 0.0, 2.0, 1.0, 0.25] };
+    assert_eq!(evaluator.score(&features(&three_hole_board(true))), -1.0 - 0.5 + 2.0 - 2.0 - 0.75);
+    assert_eq!(evaluator.evaluate(&three_hole_board(true)), -2);
+
+    // Ten seeds from hole 1 go round the board once and end in the store
+    let mut board = three_hole_board(true);
+    board.holes[0][..3].copy_from_slice(&[10, 0, 0]);
+    board.holes[1][..3].copy_from_slice(&[0, 0, 0]);
+    assert_eq!(features(&board)[3], 1.0);
+    board.apply_move(1).unwrap();
+    assert!(board.is_player_one_turn);
+}
+
+#[test]
+fn test_default_scores_store_diff() {
+    let game_config = GameConfig::build(4, 6, true).unwrap();
+    let mut rng = StdRng::seed_from_u64(11);
+    let mut board = CompactBoard::build(&game_config);
+
+    while !board.is_game_over() {
+        assert_eq!(LinearEvaluator::default().evaluate(&board), StoreDiff.evaluate(&board));
+        assert_eq!(StoreDiff.evaluate(&board), AlphaBeta::evaluate(&board));
+
+        let hole_num = *board.legal_moves().collect::<Vec<_>>().choose(&mut rng).unwrap();
+        board.apply_move(hole_num).unwrap();
+    }
+}
+
+#[test]
+fn test_weights_text() {
+    let evaluator = LinearEvaluator { weights: [1.0, 0.25, -0.5, 0.0, 2.0, 0.125] };
+    let text = evaluator.to_text();
+    assert!(text.starts_with("store 1\nseeds 0.25\nmobility -0.5\n"));
+    assert_eq!(LinearEvaluator::parse(&text).unwrap(), evaluator);
+
+    let partial = LinearEvaluator::parse("# tuned\nstore 1\n\ncaptures  0.5\n").unwrap();
+    assert_eq!(partial.weights, [1.0, 0.0, 0.0, 0.0, 0.5, 0.0]);
+
+    assert!(LinearEvaluator::parse("store").err().unwrap().contains("'<feature> <weight>'"));
+    assert!(LinearEvaluator::parse("stones 1").err().unwrap().contains("Unknown feature 'stones'"));
+    assert!(LinearEvaluator::parse("seeds x").err().unwrap().contains("Invalid weight for seeds"));
+    assert!(LinearEvaluator::parse("seeds NaN").is_err());
+    assert!(LinearEvaluator::load("/nonexistent/kalah.weights").is_err());
+}
+
+#[test]
+fn test_search_uses_evaluator() {
+    let board = CompactBoard::build(&GameConfig::build(4, 6, false).unwrap());
+
+    // Hole 3 ends in the store, so the mover keeps the constant's sign
+    let mut alpha_beta = AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred).with_evaluator(Arc::new(Constant(100)));
+    let result = alpha_beta.search(&board, 1);
+    assert_eq!((result.best_move, result.value), (Some(3), 100));
+
+    // Values that reach the end of the game do not depend on it
+    let board = CompactBoard::build(&GameConfig::build(3, 3, false).unwrap());
+    let expected = AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred).search(&board, MAX_DEPTH);
+    let result = AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred)
+        .with_evaluator(Arc::new(Constant(100)))
+        .search(&board, MAX_DEPTH);
+    assert!(expected.is_exact && result.is_exact);
+    assert_eq!(result.value, expected.value);
+}
+
+#[test]
+fn test_self_play_positions() {
+    let game_config = GameConfig::build(3, 4, false).unwrap();
+    let positions = self_play_positions(&game_config, 10, 2, 2, Arc::new(StoreDiff), &mut StdRng::seed_from_u64(2));
+
+    assert!(positions.len() >= 10);
+    assert!(positions.iter().all(|position| [0.0, 0.5, 1.0].contains(&position.result)));
+
+    // Without the pie rule every game starts from the start position
+    let start = features(&CompactBoard::build(&game_config));
+    assert!(positions.iter().filter(|position| position.features == start).count() >= 10);
+}
+
+#[test]
+fn test_tuner() {
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let positions = self_play_positions(&game_config, 40, 4, 2, Arc::new(StoreDiff), &mut StdRng::seed_from_u64(3));
+
+    let tuner = Tuner { passes: 10, ..Tuner::default() };
+    let start = LinearEvaluator::default();
+    let mut errors = Vec::new();
+    let tuned = tuner.tune(start, &positions, |_, error| errors.push(error));
+
+    assert!(!errors.is_empty());
+    assert!(errors.windows(2).all(|pair| pair[1] <= pair[0]));
+    assert!(tuner.error(&tuned, &positions) < tuner.error(&start, &positions));
+    assert_eq!(tuned.weights[0], 1.0);
+}
+
+#[test]
+fn test_shipped_weights() {
+    for stone_nums_in_hole in [4, 6] {
+        let path = format!("{}/weights/kalah-6-{}.weights", env!("CARGO_MANIFEST_DIR"), stone_nums_in_hole);
+        let evaluator = LinearEvaluator::load(&path).unwrap();
+        assert_eq!(evaluator.weights[0], 1.0);
+    }
+}
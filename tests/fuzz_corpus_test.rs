@@ -0,0 +1,33 @@
+use kalah::fuzzing;
+
+use std::fs;
+use std::path::Path;
+
+fn replay_corpus(target: &str, fuzz: fn(&[u8])) {
+    let corpus_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus").join(target);
+    let entries = fs::read_dir(&corpus_dir).unwrap();
+
+    let mut replayed = 0;
+    for entry in entries {
+        let data = fs::read(entry.unwrap().path()).unwrap();
+        fuzz(&data);
+        replayed += 1;
+    }
+
+    assert!(replayed > 0, "corpus for {} is empty", target);
+}
+
+#[test]
+fn test_position_corpus() {
+    replay_corpus("position", fuzzing::fuzz_position);
+}
+
+#[test]
+fn test_game_record_corpus() {
+    replay_corpus("game_record", fuzzing::fuzz_game_record);
+}
+
+#[test]
+fn test_moves_corpus() {
+    replay_corpus("moves", fuzzing::fuzz_moves);
+}
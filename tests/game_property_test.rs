@@ -0,0 +1,229 @@
+use kalah::game;
+
+use game::game_config::*;
+use game::game_field::*;
+use game::game_process::*;
+use game::game_status::*;
+
+use proptest::prelude::*;
+
+const MAX_GAME_TURNS: usize = 10_000;
+
+/// Count-only reimplementation of the rules, kept independent from `GameProcess`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReferenceGame {
+    holes: [Vec<usize>; 2],
+    stores: [usize; 2],
+    to_move: usize,
+    total_turns: usize,
+    is_pie_rule: bool,
+}
+
+impl ReferenceGame {
+
+    fn new(hole_nums: usize, stone_nums_in_hole: usize, is_pie_rule: bool) -> ReferenceGame {
+        ReferenceGame {
+            holes: [vec![stone_nums_in_hole; hole_nums], vec![stone_nums_in_hole; hole_nums]],
+            stores: [0, 0],
+            to_move: 0,
+            total_turns: 0,
+            is_pie_rule,
+        }
+    }
+
+    fn from_process(game_process: &GameProcess) -> ReferenceGame {
+        let counts = |side: &Side| side.holes.iter().map(|hole| hole.stones.len()).collect::<Vec<_>>();
+
+        ReferenceGame {
+            holes: [counts(&game_process.game_field.side_one), counts(&game_process.game_field.side_two)],
+            stores: [
+                game_process.game_field.side_one.store.stones.len(),
+                game_process.game_field.side_two.store.stones.len(),
+            ],
+            to_move: if game_process.is_player_one_turn { 0 } else { 1 },
+            total_turns: game_process.total_turns,
+            is_pie_rule: game_process.game_config.is_check_pipe_rule,
+        }
+    }
+
+    fn legal_holes(&self) -> Vec<usize> {
+        (0..self.holes[self.to_move].len())
+            .filter(|&hole_index| self.holes[self.to_move][hole_index] > 0)
+            .map(|hole_index| hole_index + 1)
+            .collect()
+    }
+
+    /// Returns true when the game finished with this move.
+    fn play(&mut self, hole_num: usize) -> bool {
+        let hole_nums = self.holes[0].len();
+        let mover = self.to_move;
+        let opponent = 1 - mover;
+
+        let mut stones = std::mem::take(&mut self.holes[mover][hole_num - 1]);
+        assert!(stones > 0);
+
+        // Positions 0..hole_nums are own holes, hole_nums is own store, then opponent holes
+        let cycle = 2 * hole_nums + 1;
+        let mut position = hole_num - 1;
+        while stones > 0 {
+            position = (position + 1) % cycle;
+            stones -= 1;
+            if position < hole_nums {
+                self.holes[mover][position] += 1;
+            } else if position == hole_nums {
+                self.stores[mover] += 1;
+            } else {
+                self.holes[opponent][position - hole_nums - 1] += 1;
+            }
+        }
+
+        if position < hole_nums && self.holes[mover][position] == 1 {
+            let opposite = hole_nums - position - 1;
+            self.stores[mover] += std::mem::take(&mut self.holes[opponent][opposite]);
+        }
+
+        self.total_turns += 1;
+
+        if self.holes.iter().any(|side| side.iter().all(|&stones| stones == 0)) {
+            for side in 0..2 {
+                self.stores[side] += self.holes[side].iter().sum::<usize>();
+                self.holes[side].iter_mut().for_each(|stones| *stones = 0);
+            }
+            return true;
+        }
+
+        let is_extra_turn = position == hole_nums;
+        if !is_extra_turn {
+            self.to_move = opponent;
+        }
+
+        if self.is_pie_rule && is_extra_turn && self.total_turns == 1 && mover == 0 {
+            self.holes.swap(0, 1);
+            self.stores.swap(0, 1);
+        }
+
+        false
+    }
+}
+
+fn config_strategy() -> impl Strategy<Value = (usize, usize, bool)> {
+    (1..=9usize, 1..=9usize, any::<bool>())
+}
+
+fn build_process(stone_nums_in_hole: usize, hole_nums: usize, is_pie_rule: bool) -> GameProcess {
+    let game_config = GameConfig::build(stone_nums_in_hole, hole_nums, is_pie_rule).unwrap();
+    let game_field = GameField::build(&game_config);
+    GameProcess::build(game_field, "P1".to_string(), "P2".to_string(), game_config)
+}
+
+fn pick_hole(reference: &ReferenceGame, choice: usize) -> usize {
+    let legal_holes = reference.legal_holes();
+    legal_holes[choice % legal_holes.len()]
+}
+
+proptest! {
+
+    #[test]
+    fn moves_match_reference(
+        (stone_nums_in_hole, hole_nums, is_pie_rule) in config_strategy(),
+        choices in prop::collection::vec(any::<usize>(), 0..200),
+    ) {
+        let mut game_process = build_process(stone_nums_in_hole, hole_nums, is_pie_rule);
+        let mut reference = ReferenceGame::new(hole_nums, stone_nums_in_hole, is_pie_rule);
+
+        for choice in choices {
+            let hole_num = pick_hole(&reference, choice);
+            let is_reference_finished = reference.play(hole_num);
+
+            let status = game_process.move_stones_from_hole(hole_num);
+            prop_assert!(status.is_ok());
+            prop_assert_eq!(&ReferenceGame::from_process(&game_process), &reference);
+            prop_assert!(game_process.validate().is_ok());
+
+            if is_reference_finished {
+                prop_assert!(matches!(status, Ok(GameStatus::Finished)));
+                break;
+            }
+            prop_assert!(matches!(status, Ok(GameStatus::Run)));
+        }
+    }
+
+    #[test]
+    fn game_terminates(
+        (stone_nums_in_hole, hole_nums, is_pie_rule) in config_strategy(),
+        choices in prop::collection::vec(any::<usize>(), 0..100),
+    ) {
+        let mut game_process = build_process(stone_nums_in_hole, hole_nums, is_pie_rule);
+        let mut choices = choices.into_iter();
+
+        let mut is_finished = false;
+        for _ in 0..MAX_GAME_TURNS {
+            let reference = ReferenceGame::from_process(&game_process);
+            let hole_num = pick_hole(&reference, choices.next().unwrap_or(0));
+
+            if let Ok(GameStatus::Finished) = game_process.move_stones_from_hole(hole_num) {
+                is_finished = true;
+                break;
+            }
+        }
+
+        prop_assert!(is_finished);
+
+        let total_stones = 2 * hole_nums * stone_nums_in_hole;
+        prop_assert_eq!(game_process.player_one_score() + game_process.player_two_score(), total_stones);
+    }
+
+    #[test]
+    fn side_to_move_rules(
+        (stone_nums_in_hole, hole_nums, is_pie_rule) in config_strategy(),
+        choices in prop::collection::vec(any::<usize>(), 1..100),
+    ) {
+        let mut game_process = build_process(stone_nums_in_hole, hole_nums, is_pie_rule);
+
+        for choice in choices {
+            let before = ReferenceGame::from_process(&game_process);
+            let hole_num = pick_hole(&before, choice);
+
+            // The player to move only owns the store on their side
+            let mover_store = before.stores[before.to_move];
+            let stones = before.holes[before.to_move][hole_num - 1];
+            let positions = (1..=stones).map(|step| (hole_num - 1 + step) % (2 * hole_nums + 1));
+            let store_passes = positions.filter(|&position| position == hole_nums).count();
+            let is_last_in_store = (hole_num - 1 + stones) % (2 * hole_nums + 1) == hole_nums;
+
+            if let Ok(GameStatus::Finished) = game_process.move_stones_from_hole(hole_num) {
+                break;
+            }
+
+            let after = ReferenceGame::from_process(&game_process);
+            let is_pie_swap = is_pie_rule && before.total_turns == 0 && is_last_in_store;
+            prop_assert_eq!(after.to_move == before.to_move, is_last_in_store);
+            prop_assert_eq!(after.stores[1 - before.to_move], if is_pie_swap { mover_store + store_passes } else { before.stores[1 - before.to_move] });
+        }
+    }
+
+    #[test]
+    fn illegal_moves_do_not_change_state(
+        (stone_nums_in_hole, hole_nums, is_pie_rule) in config_strategy(),
+        choices in prop::collection::vec(any::<usize>(), 0..50),
+        hole_num in 0..=10usize,
+    ) {
+        let mut game_process = build_process(stone_nums_in_hole, hole_nums, is_pie_rule);
+
+        for choice in choices {
+            let reference = ReferenceGame::from_process(&game_process);
+            if let Ok(GameStatus::Finished) = game_process.move_stones_from_hole(pick_hole(&reference, choice)) {
+                return Ok(());
+            }
+        }
+
+        let before = ReferenceGame::from_process(&game_process);
+        let is_legal = before.legal_holes().contains(&hole_num);
+        let status = game_process.move_stones_from_hole(hole_num);
+
+        prop_assert_eq!(status.is_ok(), is_legal);
+        if !is_legal {
+            prop_assert_eq!(ReferenceGame::from_process(&game_process), before);
+        }
+    }
+}
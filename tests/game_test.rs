@@ -3,6 +3,7 @@ use kalah::game;
 use game::game_config::*;
 use game::game_field::*;
 use game::game_process::*;
+use game::scoring_mode::*;
 
 #[test]
 fn start_game() {
@@ -43,7 +44,7 @@ fn test_make_move() {
     assert_eq!(game_process.game_field.side_one.holes[4].stones.len(), 7);
     assert_eq!(game_process.game_field.side_one.holes[5].stones.len(), 7);
     assert_eq!(game_process.game_field.side_one.holes[0].stones.len(), 0);
-    assert_eq!(game_process.player_one.score, 1);
+    assert_eq!(game_process.player_one_score(), 1);
     assert_eq!(game_process.total_turns, 1);
     assert!(game_process.is_player_one_turn);
 
@@ -60,7 +61,7 @@ fn test_make_move() {
     assert_eq!(game_process.game_field.side_one.holes[5].stones.len(), 7);
     assert_eq!(game_process.game_field.side_one.holes[0].stones.len(), 0);
     assert_eq!(game_process.total_turns, 1);
-    assert_eq!(game_process.player_one.score, 1);
+    assert_eq!(game_process.player_one_score(), 1);
     assert!(game_process.is_player_one_turn);
 
     assert_eq!(game_process.game_field.side_two.holes[0].stones.len(), 6);
@@ -78,14 +79,14 @@ fn test_make_move() {
     assert_eq!(game_process.game_field.side_two.holes[0].stones.len(), 7);
 
     assert_eq!(game_process.total_turns, 2);
-    assert_eq!(game_process.player_one.score, 2);
+    assert_eq!(game_process.player_one_score(), 2);
     assert!(!game_process.is_player_one_turn);
 
     _ = game_process.move_stones_from_hole(1);
 
     // single stone rule
     _ = game_process.move_stones_from_hole(1);
-    assert_eq!(game_process.player_one.score, 9);
+    assert_eq!(game_process.player_one_score(), 9);
 
 }
 
@@ -110,7 +111,7 @@ fn test_swap_methods_called() {
     assert_eq!(game_process.game_field.side_one.holes[4].stones.len(), 6);
     assert_eq!(game_process.game_field.side_one.holes[5].stones.len(), 6);
 
-    assert_eq!(game_process.player_one.score, 0);
+    assert_eq!(game_process.player_one_score(), 0);
 
     assert_eq!(game_process.game_field.side_two.holes[0].stones.len(), 0);
     assert_eq!(game_process.game_field.side_two.holes[1].stones.len(), 7);
@@ -118,7 +119,7 @@ fn test_swap_methods_called() {
     assert_eq!(game_process.game_field.side_two.holes[3].stones.len(), 7);
     assert_eq!(game_process.game_field.side_two.holes[4].stones.len(), 7);
     assert_eq!(game_process.game_field.side_two.holes[5].stones.len(), 7);
-    assert_eq!(game_process.player_two.score, 1);
+    assert_eq!(game_process.player_two_score(), 1);
 
     assert!(game_process.is_player_one_turn);
 
@@ -150,4 +151,107 @@ fn test_game_finish() {
     let _ = game_process.move_stones_from_hole(1);
 
     assert!(game_process.game_finished);
+
+    // remaining stone on side two goes to its own store
+    assert_eq!(game_process.game_field.side_two.holes[0].stones.len(), 0);
+    assert_eq!(game_process.game_field.side_one.store.stones.len(), 1);
+    assert_eq!(game_process.game_field.side_two.store.stones.len(), 1);
+    assert_eq!(game_process.player_one_score(), 1);
+    assert_eq!(game_process.player_two_score(), 1);
+}
+
+fn build_uniform_field(game_config: &GameConfig, color: Color, size: Size) -> GameField {
+    let build_side = || Side {
+        holes: (0..game_config.hole_nums)
+            .map(|_| Hole {
+                stones: (0..game_config.stone_nums_in_hole).map(|_| Stone { color, size }).collect(),
+            })
+            .collect(),
+        store: Hole { stones: Vec::new() },
+    };
+
+    GameField { side_one: build_side(), side_two: build_side() }
+}
+
+#[test]
+fn test_stone_points() {
+    let small = Stone { color: Color::Blue, size: Size::Small };
+    let large_red = Stone { color: Color::Red, size: Size::Large };
+
+    assert_eq!(ScoringMode::StoneCount.stone_points(&large_red), 1);
+    assert_eq!(ScoringMode::BySize.stone_points(&small), 1);
+    assert_eq!(ScoringMode::BySize.stone_points(&large_red), 3);
+
+    let bonus_red = ScoringMode::BonusColor { color: Color::Red, bonus: 2 };
+    assert_eq!(bonus_red.stone_points(&small), 1);
+    assert_eq!(bonus_red.stone_points(&large_red), 3);
+    assert_eq!(bonus_red.stones_points(&[small, large_red]), 4);
+}
+
+#[test]
+fn test_scoring_by_size() {
+    let game_config = GameConfig::build(2, 2, false).unwrap().with_scoring_mode(ScoringMode::BySize);
+    let game_field = build_uniform_field(&game_config, Color::Black, Size::Large);
+
+    let mut game_process = GameProcess::build(game_field, "P1".to_string(), "P2".to_string(), game_config);
+
+    _ = game_process.move_stones_from_hole(2);
+
+    assert_eq!(game_process.game_field.side_one.store.stones.len(), 1);
+    assert_eq!(game_process.player_one_score(), 3);
+    assert_eq!(game_process.game_field.side_two.holes[0].stones.len(), 3);
+    assert!(!game_process.is_player_one_turn);
+}
+
+#[test]
+fn test_scoring_bonus_color() {
+    let scoring_mode = ScoringMode::BonusColor { color: Color::Yellow, bonus: 4 };
+    let game_config = GameConfig::build(6, 6, false).unwrap().with_scoring_mode(scoring_mode);
+    let game_field = build_uniform_field(&game_config, Color::Yellow, Size::Small);
+
+    let mut game_process = GameProcess::build(game_field, "P1".to_string(), "P2".to_string(), game_config);
+
+    _ = game_process.move_stones_from_hole(1);
+
+    assert_eq!(game_process.game_field.side_one.store.stones.len(), 1);
+    assert_eq!(game_process.player_one_score(), 5);
+    assert!(game_process.is_player_one_turn);
+}
+
+#[test]
+fn test_validate() {
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let game_field = GameField::build(&game_config);
+
+    let mut game_process = GameProcess::build(game_field, "P1".to_string(), "P2".to_string(), game_config);
+
+    assert!(game_process.validate().is_ok());
+
+    _ = game_process.move_stones_from_hole(3);
+    _ = game_process.move_stones_from_hole(2);
+    assert!(game_process.validate().is_ok());
+
+    let stone = game_process.game_field.side_two.holes[0].stones.pop().unwrap();
+    let err = game_process.validate().unwrap_err();
+    assert!(err.starts_with("stone conservation violated: expected 48 stones, found 47"));
+
+    game_process.game_field.side_one.store.stones.push(stone);
+    assert!(game_process.validate().is_ok());
+
+    game_process.game_field.side_one.holes.pop();
+    assert!(game_process.validate().unwrap_err().starts_with("side 1 has 5 holes, expected 6"));
+}
+
+#[test]
+fn test_last_stone_in_store_does_not_capture() {
+    let game_config = GameConfig::build(1, 4, false).unwrap();
+    let game_field = GameField::build(&game_config);
+
+    let mut game_process = GameProcess::build(game_field, "P1".to_string(), "P2".to_string(), game_config);
+
+    _ = game_process.move_stones_from_hole(4);
+
+    assert_eq!(game_process.player_one_score(), 1);
+    assert_eq!(game_process.game_field.side_two.holes[3].stones.len(), 1);
+    assert!(game_process.is_player_one_turn);
 }
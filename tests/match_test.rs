@@ -0,0 +1,168 @@
+use kalah::ai::alpha_beta::*;
+use kalah::ai::compact_board::*;
+use kalah::ai::difficulty::*;
+use kalah::ai::external_engine::*;
+use kalah::ai::match_runner::*;
+use kalah::ai::transposition_table::*;
+use kalah::game::game_config::*;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use std::time::{ Duration, Instant };
+
+const TABLE_SIZE: usize = 1 << 16;
+const ENGINE: &str = env!("CARGO_BIN_EXE_kalah");
+
+fn level_controller(level: Level, seed: u64) -> EngineController<LevelEngine> {
+    let engine = LevelEngine::build(AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred), level.settings().unwrap()).with_seed(seed);
+    EngineController::build(level.name().to_string(), engine, SearchLimits::depth(MAX_DEPTH))
+}
+
+/// Plays the first legal move, or always `fixed_move` when set.
+struct FirstMove {
+    fixed_move: Option<usize>,
+}
+
+impl PlayerController for FirstMove {
+
+    fn name(&self) -> &str {
+        "first move"
+    }
+
+    fn new_game(&mut self, _game_config: &GameConfig) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn choose_move(&mut self, board: &CompactBoard) -> Result<usize, String> {
+        Ok(self.fixed_move.unwrap_or_else(|| board.legal_moves().next().unwrap()))
+    }
+}
+
+/// Engine written as a shell script answering the handshake, `isready` and `go` with `go_reply`.
+#[cfg(unix)]
+fn script_engine(name: &str, go_reply: &str) -> String {
+    let script = format!(
+        "while read line; do case $line in kalah) echo 'id name script'; echo kalahok;; isready) echo readyok;; go*) {};; esac; done",
+        go_reply
+    );
+    let path = std::env::temp_dir().join(format!("kalah-{}-engine-{}.sh", name, std::process::id()));
+    std::fs::write(&path, script).unwrap();
+    format!("sh {}", path.display())
+}
+
+#[test]
+fn test_winner() {
+    let result = GameResult { player_one: 1, moves: vec![], stores: [20, 28], forfeit: None };
+    assert_eq!(result.winner(), Some(0));
+    assert_eq!(GameResult { stores: [24, 24], ..result.clone() }.winner(), None);
+    // A forfeit loses whatever the stores say
+    assert_eq!(GameResult { forfeit: Some((1, "timed out".to_string())), ..result }.winner(), Some(1));
+}
+
+#[test]
+fn test_match_swaps_sides() {
+    let game_config = GameConfig::build(3, 4, true).unwrap();
+    let mut novice = level_controller(Level::Novice, 1);
+    let mut intermediate = level_controller(Level::Intermediate, 2);
+    let runner = MatchRunner { games: 6, random_plies: 2 };
+
+    let mut results = Vec::new();
+    let score = runner.run(&game_config, [&mut novice, &mut intermediate], &mut StdRng::seed_from_u64(3), |game, result| {
+        results.push((game, result.clone()));
+    });
+
+    assert_eq!(score.wins[0] + score.wins[1] + score.draws, 6);
+    assert!(score.wins[1] > score.wins[0], "{:?}", score);
+
+    for (index, (game, result)) in results.iter().enumerate() {
+        assert_eq!(*game, index + 1);
+        assert_eq!(result.player_one, index % 2);
+        assert_eq!(result.forfeit, None);
+        assert_eq!(result.stores.iter().map(|&store| store as usize).sum::<usize>(), 2 * 4 * 3);
+    }
+
+    // Each pair of games starts from the same opening
+    for pair in results.chunks(2) {
+        assert_eq!(pair[0].1.moves[..2], pair[1].1.moves[..2]);
+    }
+}
+
+#[test]
+fn test_illegal_move_forfeits() {
+    let game_config = GameConfig::build(3, 4, false).unwrap();
+    let mut first = FirstMove { fixed_move: None };
+    let mut cheat = FirstMove { fixed_move: Some(7) };
+
+    let result = play_game(&game_config, &[], [&mut first, &mut cheat], 0);
+    assert_eq!(result.moves, vec![1]);
+    assert_eq!(result.forfeit, Some((1, "first move played illegal move 7: hole_num must be in range 1..=4 (got 7)".to_string())));
+    assert_eq!(result.winner(), Some(0));
+}
+
+#[test]
+fn test_external_engine_plays_match() {
+    let game_config = GameConfig::build(3, 4, true).unwrap();
+    let mut external = ExternalEngine::spawn(&format!("{} engine --level casual", ENGINE))
+        .unwrap()
+        .with_limits(SearchLimits::move_time(Duration::from_millis(20)));
+    assert_eq!(external.name(), format!("kalah {} (casual)", env!("CARGO_PKG_VERSION")));
+
+    let mut first = FirstMove { fixed_move: None };
+    let runner = MatchRunner { games: 2, random_plies: 1 };
+    let mut results = Vec::new();
+    let score = runner.run(&game_config, [&mut first, &mut external], &mut StdRng::seed_from_u64(4), |_, result| results.push(result.clone()));
+
+    assert_eq!(score.wins[0] + score.wins[1] + score.draws, 2);
+    assert!(results.iter().all(|result| result.forfeit.is_none()), "{:?}", results);
+}
+
+#[test]
+fn test_missing_engine() {
+    let err = ExternalEngine::spawn("/nonexistent/kalah-engine").err().unwrap();
+    assert!(err.starts_with("Cannot start engine '/nonexistent/kalah-engine'"), "{}", err);
+    assert_eq!(ExternalEngine::spawn("  ").err().unwrap(), "Engine command is empty");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_external_illegal_move() {
+    let game_config = GameConfig::build(3, 4, false).unwrap();
+    let mut external = ExternalEngine::spawn(&script_engine("illegal", "echo 'bestmove 9'")).unwrap();
+    let mut first = FirstMove { fixed_move: None };
+
+    let result = play_game(&game_config, &[], [&mut external, &mut first], 0);
+    assert_eq!(result.forfeit, Some((0, "script played illegal move 9".to_string())));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_external_crash() {
+    let game_config = GameConfig::build(3, 4, false).unwrap();
+    let mut external = ExternalEngine::spawn(&script_engine("crash", "exit 3")).unwrap();
+    let mut first = FirstMove { fixed_move: None };
+
+    let result = play_game(&game_config, &[], [&mut external, &mut first], 0);
+    let (player, reason) = result.forfeit.unwrap();
+    assert_eq!(player, 0);
+    assert!(reason.starts_with("script exited") && reason.contains('3'), "{}", reason);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_external_timeout() {
+    let game_config = GameConfig::build(3, 4, false).unwrap();
+    let mut external = ExternalEngine::spawn(&script_engine("slow", "sleep 0.1"))
+        .unwrap()
+        .with_limits(SearchLimits::move_time(Duration::from_millis(50)))
+        .with_timeout(Duration::from_millis(100));
+    let mut first = FirstMove { fixed_move: None };
+
+    let started = Instant::now();
+    let result = play_game(&game_config, &[], [&mut first, &mut external], 0);
+    assert_eq!(result.forfeit, Some((1, "script timed out".to_string())));
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    // Still answering, so the next game starts normally
+    assert_eq!(external.new_game(&game_config), Ok(()));
+}
@@ -0,0 +1,121 @@
+use kalah::game;
+
+use game::game_config::*;
+use game::game_field::*;
+use game::game_process::*;
+use game::game_record::*;
+use game::position::*;
+
+#[test]
+fn test_position_notation() {
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let game_field = GameField::build(&game_config);
+    let mut game_process = GameProcess::build(game_field, "P1".to_string(), "P2".to_string(), game_config);
+
+    assert_eq!(Position::from_process(&game_process).to_notation(), "4,4,4,4,4,4:0/4,4,4,4,4,4:0 1 0");
+
+    _ = game_process.move_stones_from_hole(2);
+    let notation = Position::from_process(&game_process).to_notation();
+    assert_eq!(notation, "4,0,5,5,5,5:0/4,4,4,4,4,4:0 2 1");
+
+    let position = Position::parse(&notation).unwrap();
+    assert_eq!(position, Position::from_process(&game_process));
+    assert_eq!(position.total_stones(), Some(48));
+
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let game_process = GameProcess::from_position(&position, "P1".to_string(), "P2".to_string(), game_config).unwrap();
+    assert!(!game_process.is_player_one_turn);
+    assert_eq!(game_process.game_field.side_one.holes[2].stones.len(), 5);
+}
+
+#[test]
+fn test_position_parse_errors() {
+    assert!(Position::parse("").is_err());
+    assert!(Position::parse("4,4:0/4,4:0").is_err());
+    assert!(Position::parse("4,4:0/4,4:0 3 0").is_err());
+    assert!(Position::parse("4,4:0/4:0 1 0").is_err());
+    assert!(Position::parse("4,x:0/4,4:0 1 0").is_err());
+    assert!(Position::parse("4,4/4,4:0 1 0").is_err());
+    assert!(Position::parse("4,4:0/4,4:0 1 0 extra").is_err());
+
+    let position = Position::parse("4,4:0/4,4:1 1 0").unwrap();
+    let game_config = GameConfig::build(4, 2, false).unwrap();
+    let err = GameProcess::from_position(&position, "P1".to_string(), "P2".to_string(), game_config).err().unwrap();
+    assert_eq!(err, "Position must contain 16 stones");
+}
+
+#[test]
+fn test_game_record_round_trip() {
+    let game_config = GameConfig::build(4, 6, true).unwrap();
+    let game_field = GameField::build(&game_config);
+    let mut game_process = GameProcess::build(game_field, "Alice".to_string(), "Bob".to_string(), game_config);
+
+    for hole_num in [3, 6, 1, 2] {
+        _ = game_process.move_stones_from_hole(hole_num);
+    }
+
+    let record = GameRecord::from_process(&game_process, false);
+    let text = record.to_text();
+    assert!(text.starts_with("[PlayerOne \"Alice\"]\n[PlayerTwo \"Bob\"]\n[Holes \"6\"]\n[Stones \"4\"]\n[PieRule \"on\"]\n[Result \"*\"]\n"));
+
+    let parsed = GameRecord::parse(&text).unwrap();
+    assert_eq!(parsed, record);
+
+    let replayed = parsed.replay().unwrap();
+    assert_eq!(Position::from_process(&replayed), Position::from_process(&game_process));
+}
+
+#[test]
+fn test_game_record_parse() {
+    let text = "[PlayerOne \"P1\"]\n[PlayerTwo \"P2\"]\n[Holes \"1\"]\n[Stones \"1\"]\n[Result \"1-1\"]\n\n1 {last stone in store}\n";
+    let record = GameRecord::parse(text).unwrap();
+
+    assert_eq!(record.moves, vec![1]);
+    assert_eq!(record.comments, vec![(1, "last stone in store".to_string())]);
+    assert!(!record.is_check_pipe_rule);
+    assert_eq!(record.result, Some((1, 1)));
+    assert!(record.replay().is_ok());
+
+    let wrong_result = text.replace("1-1", "2-0");
+    assert!(GameRecord::parse(&wrong_result).unwrap().replay().err().unwrap().starts_with("Recorded result 2-0"));
+
+    let illegal_move = text.replace("\n1 {", "\n2 {");
+    assert!(GameRecord::parse(&illegal_move).unwrap().replay().err().unwrap().starts_with("Move 1 (2) is illegal"));
+
+    assert_eq!(record.hints, (0, 0));
+    let with_hints = text.replace("[Result", "[Hints \"2-1\"]\n[Result");
+    let hinted = GameRecord::parse(&with_hints).unwrap();
+    assert_eq!(hinted.hints, (2, 1));
+    assert!(hinted.to_text().contains("[Hints \"2-1\"]\n"));
+    assert_eq!(GameRecord::parse(&hinted.to_text()).unwrap(), hinted);
+    assert!(GameRecord::parse(&text.replace("[Result", "[Hints \"two\"]\n[Result")).is_err());
+
+    assert!(GameRecord::parse("[PlayerOne \"P1\"]\n").is_err());
+    assert!(GameRecord::parse(&text.replace("}", "")).is_err());
+}
+
+#[test]
+fn test_game_record_chat() {
+    let text = "[PlayerOne \"P1\"]\n[PlayerTwo \"P2\"]\n[Holes \"1\"]\n[Stones \"1\"]\n[Result \"1-1\"]\n\n\
+        {chat: P1: good luck} 1 {last stone in store} {chat: * P2 says good game}\n";
+    let record = GameRecord::parse(text).unwrap();
+
+    assert_eq!(record.comments, vec![(1, "last stone in store".to_string())]);
+    assert_eq!(record.chat, vec![(0, "P1: good luck".to_string()), (1, "* P2 says good game".to_string())]);
+    assert_eq!(GameRecord::parse(&record.to_text()).unwrap(), record);
+    assert!(record.replay().is_ok());
+}
+
+#[test]
+fn test_game_record_time_control() {
+    let text = "[PlayerOne \"P1\"]\n[PlayerTwo \"P2\"]\n[Holes \"1\"]\n[Stones \"1\"]\n[Result \"1-1\"]\n\n1\n";
+    assert_eq!(GameRecord::parse(text).unwrap().time_control, None);
+
+    let timed = GameRecord::parse(&text.replace("[Result", "[TimeControl \"300+5\"]\n[Result")).unwrap();
+    assert_eq!(timed.time_control.map(|time_control| time_control.to_notation()), Some("300+5".to_string()));
+    assert!(timed.to_text().contains("[TimeControl \"300+5\"]\n"));
+    assert_eq!(GameRecord::parse(&timed.to_text()).unwrap(), timed);
+    assert_eq!(timed.replay().unwrap().game_config.time_control, timed.time_control);
+
+    assert!(GameRecord::parse(&text.replace("[Result", "[TimeControl \"5 minutes\"]\n[Result")).is_err());
+}
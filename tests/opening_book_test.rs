@@ -0,0 +1,192 @@
+use kalah::ai::alpha_beta::*;
+use kalah::ai::background_search::*;
+use kalah::ai::compact_board::*;
+use kalah::ai::opening_book::*;
+use kalah::ai::solver::*;
+use kalah::ai::transposition_table::*;
+use kalah::game::game_config::*;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+const TABLE_SIZE: usize = 1 << 16;
+
+#[test]
+fn test_add_moves() {
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let board = CompactBoard::build(&game_config);
+    let mut book = OpeningBook::build(&game_config);
+    assert!(book.is_empty());
+
+    book.add_move(&board, 3, 2).unwrap();
+    book.add_move(&board, 5, 4).unwrap();
+    book.add_move(&board, 3, 3).unwrap();
+
+    assert_eq!(book.len(), 1);
+    assert_eq!(book.moves(&board), &[BookMove { hole_num: 3, weight: 5 }, BookMove { hole_num: 5, weight: 4 }]);
+
+    let mut child = board;
+    child.apply_move(1).unwrap();
+    assert!(book.moves(&child).is_empty());
+    assert!(book.add_move(&child, 7, 1).is_err());
+
+    // Same holes with another stone count or pie rule are not in the book
+    let other_stones = CompactBoard::build(&GameConfig::build(5, 6, false).unwrap());
+    assert!(book.moves(&other_stones).is_empty());
+    let mut with_pie = board;
+    with_pie.is_check_pipe_rule = true;
+    assert!(book.moves(&with_pie).is_empty());
+    assert!(book.add_move(&with_pie, 3, 1).is_err());
+}
+
+#[test]
+fn test_text_round_trip() {
+    let game_config = GameConfig::build(4, 6, true).unwrap();
+    let mut board = CompactBoard::build(&game_config);
+    let mut book = OpeningBook::build(&game_config);
+
+    book.add_move(&board, 6, 3).unwrap();
+    book.add_move(&board, 2, 1).unwrap();
+    board.apply_move(6).unwrap();
+    book.add_move(&board, 4, 7).unwrap();
+
+    let text = book.to_text();
+    assert!(text.starts_with("[Holes \"6\"]\n[Stones \"4\"]\n[PieRule \"on\"]\n\n4,4,4,4,4,4:0/4,4,4,4,4,4:0 1 0 6:3 2:1\n"));
+
+    let parsed = OpeningBook::parse(&format!("# comment\n{}", text)).unwrap();
+    assert_eq!(parsed.len(), 2);
+    assert!(parsed.is_check_pipe_rule);
+    assert_eq!(parsed.moves(&board), &[BookMove { hole_num: 4, weight: 7 }]);
+    assert_eq!(parsed.to_text(), text);
+}
+
+#[test]
+fn test_parse_errors() {
+    let header = "[Holes \"2\"]\n[Stones \"1\"]\n";
+
+    assert_eq!(OpeningBook::parse("[Stones \"1\"]").err().unwrap(), "Missing [Holes] tag");
+    assert!(OpeningBook::parse(&format!("{}1,1:0/1,1:0 1 0", header)).err().unwrap().contains("at least one move"));
+    assert!(OpeningBook::parse(&format!("{}1,1:0/1,1:0 1 0 2", header)).err().unwrap().contains("'<hole>:<weight>'"));
+    assert!(OpeningBook::parse(&format!("{}1,1:0/1,1:0 1 0 x:1", header)).err().unwrap().contains("Invalid book move"));
+    assert!(OpeningBook::parse(&format!("{}1,1:0/1,1:0 1 0 3:1", header)).err().unwrap().contains("illegal"));
+    assert!(OpeningBook::parse(&format!("{}2,2:0/2,2:0 1 0 1:1", header)).err().unwrap().contains("configuration"));
+
+    // Repeated moves add up without overflowing
+    let book = OpeningBook::parse(&format!("{}1,1:0/1,1:0 1 0 1:4294967295 1:1", header)).unwrap();
+    let board = CompactBoard::build(&GameConfig::build(1, 2, false).unwrap());
+    assert_eq!(book.moves(&board), &[BookMove { hole_num: 1, weight: u32::MAX }]);
+}
+
+#[test]
+fn test_choose() {
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let board = CompactBoard::build(&game_config);
+    let mut book = OpeningBook::build(&game_config);
+    book.add_move(&board, 3, 9).unwrap();
+    book.add_move(&board, 1, 1).unwrap();
+
+    let mut rng = StdRng::seed_from_u64(7);
+    assert_eq!(book.choose(&board, 0.0, &mut rng), Some(3));
+
+    let picks = (0..1000).map(|_| book.choose(&board, 1.0, &mut rng).unwrap()).collect::<Vec<_>>();
+    let ones = picks.iter().filter(|&&hole_num| hole_num == 1).count();
+    assert!((50..=150).contains(&ones));
+    assert!(picks.iter().all(|&hole_num| hole_num == 1 || hole_num == 3));
+
+    // Less randomness favours the heavier move more
+    let ones_less_random = (0..1000).filter(|_| book.choose(&board, 0.5, &mut rng) == Some(1)).count();
+    assert!(ones_less_random < ones);
+
+    let mut child = board;
+    child.apply_move(3).unwrap();
+    assert_eq!(book.choose(&child, 1.0, &mut rng), None);
+}
+
+#[test]
+fn test_expand_from_solver() {
+    let game_config = GameConfig::build(2, 4, false).unwrap();
+    let root = CompactBoard::build(&game_config);
+    let mut book = OpeningBook::build(&game_config);
+    let mut solver = Solver::build(TABLE_SIZE);
+
+    let builder = BookBuilder { plies: 3, margin: 1 };
+    builder.expand(&mut book, &root, |board| solver_move_values(&mut solver, board)).unwrap();
+    assert!(book.len() > 1);
+
+    // Book moves are within the margin of the best move and heavier the closer they are
+    let mut solver = Solver::build(TABLE_SIZE);
+    let best = solver.solve(&root).unwrap().value;
+    for book_move in book.moves(&root) {
+        let value = solver_move_values(&mut solver, &root)
+            .into_iter()
+            .find(|&(hole_num, _)| hole_num == book_move.hole_num)
+            .unwrap()
+            .1;
+        assert!(best - value <= 1);
+        assert_eq!(book_move.weight as i32, 1 - (best - value) + 1);
+    }
+}
+
+#[test]
+fn test_self_play() {
+    let game_config = GameConfig::build(3, 4, false).unwrap();
+    let root = CompactBoard::build(&game_config);
+    let mut book = OpeningBook::build(&game_config);
+    let mut alpha_beta = AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred);
+
+    let builder = BookBuilder { plies: 4, margin: 2 };
+    let mut rng = StdRng::seed_from_u64(1);
+    builder.self_play(&mut book, &root, 20, &mut rng, |board| search_move_values(&mut alpha_beta, board, 4)).unwrap();
+
+    // Every game starts at the root, so its weights count the games
+    assert_eq!(book.moves(&root).iter().map(|book_move| book_move.weight).sum::<u32>(), 20);
+}
+
+#[test]
+fn test_book_engine() {
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let board = CompactBoard::build(&game_config);
+    let mut book = OpeningBook::build(&game_config);
+    book.add_move(&board, 2, 1).unwrap();
+
+    let alpha_beta = AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred);
+    let engine = BookEngine::build(Arc::new(book), alpha_beta).with_seed(3).with_randomness(1.0);
+
+    let search = BackgroundSearch::start(engine, board, SearchLimits::move_time(Duration::from_secs(60)));
+    let Some(SearchEvent::Finished(result)) = search.next() else { panic!("book moves finish at once") };
+    assert_eq!(result.best_move, Some(2));
+    assert!(result.is_book_move);
+    let engine = search.join();
+
+    // Out of book the wrapped engine searches
+    let mut child = board;
+    child.apply_move(2).unwrap();
+    let search = BackgroundSearch::start(engine, child, SearchLimits::depth(3));
+    let result = loop {
+        if let Some(SearchEvent::Finished(result)) = search.next() {
+            break result;
+        }
+    };
+    assert!(!result.is_book_move);
+    assert_eq!(result.depth, 3);
+    assert!(child.is_legal_move(result.best_move.unwrap()));
+}
+
+#[test]
+fn test_shipped_books() {
+    for (stone_nums_in_hole, is_pie_rule) in [(4, true), (4, false), (6, true), (6, false)] {
+        let path = format!(
+            "{}/books/kalah-6-{}-{}.book",
+            env!("CARGO_MANIFEST_DIR"), stone_nums_in_hole, if is_pie_rule { "pie" } else { "nopie" }
+        );
+        let book = OpeningBook::load(&path).unwrap();
+        let board = CompactBoard::build(&GameConfig::build(stone_nums_in_hole, 6, is_pie_rule).unwrap());
+
+        assert_eq!(book.stone_nums_in_hole, stone_nums_in_hole);
+        assert_eq!(book.is_check_pipe_rule, is_pie_rule);
+        assert!(!book.moves(&board).is_empty());
+    }
+}
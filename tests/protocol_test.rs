@@ -0,0 +1,238 @@
+use kalah::ai::alpha_beta::*;
+use kalah::ai::compact_board::*;
+use kalah::ai::protocol::*;
+use kalah::ai::transposition_table::*;
+use kalah::game::game_config::*;
+use kalah::game::position::*;
+
+use std::io::Cursor;
+use std::time::Duration;
+
+const TABLE_SIZE: usize = 1 << 16;
+
+fn session(game_config: GameConfig) -> EngineSession<AlphaBeta> {
+    EngineSession::build("test engine".to_string(), AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred), game_config)
+}
+
+fn handle(session: &mut EngineSession<AlphaBeta>, line: &str) -> Vec<EngineReply> {
+    let mut replies = Vec::new();
+    assert!(session.handle_line(line, &mut replies));
+    replies
+}
+
+#[test]
+fn test_commands_round_trip() {
+    let lines = [
+        "kalah",
+        "isready",
+        "newgame holes=6 stones=4 pie=on",
+        "position startpos",
+        "position startpos moves 3 1 6",
+        "position 4,0,4:1/4,4,4:0 2 1 moves 2",
+        "go",
+        "go depth 10",
+        "go depth 8 movetime 1000 nodes 50000",
+        "go infinite",
+        "stop",
+        "quit",
+    ];
+
+    for line in lines {
+        let command = EngineCommand::parse(line).unwrap();
+        assert_eq!(command.to_line(), line);
+        assert_eq!(EngineCommand::parse(&command.to_line()), Ok(command));
+    }
+
+    assert_eq!(
+        EngineCommand::parse("newgame pie=off stones=3 holes=4"),
+        Ok(EngineCommand::NewGame(GameConfig::build(3, 4, false).unwrap()))
+    );
+    assert_eq!(
+        EngineCommand::parse("position 4,0,4:1/4,4,4:0 2 1"),
+        Ok(EngineCommand::Position { position: Some(Position::parse("4,0,4:1/4,4,4:0 2 1").unwrap()), moves: vec![] })
+    );
+    assert_eq!(EngineCommand::parse("go"), Ok(EngineCommand::Go { limits: SearchLimits::depth(MAX_DEPTH), is_infinite: false }));
+    assert_eq!(
+        EngineCommand::parse("go movetime 250"),
+        Ok(EngineCommand::Go { limits: SearchLimits::move_time(Duration::from_millis(250)), is_infinite: false })
+    );
+    assert_eq!(EngineCommand::parse("go infinite"), Ok(EngineCommand::Go { limits: SearchLimits::depth(MAX_DEPTH), is_infinite: true }));
+}
+
+#[test]
+fn test_invalid_commands() {
+    assert_eq!(EngineCommand::parse("castle"), Err("Unknown command 'castle'".to_string()));
+    assert!(EngineCommand::parse("newgame holes=6 stones=4").err().unwrap().contains("holes, stones and pie"));
+    assert!(EngineCommand::parse("newgame holes=6 stones=4 pie=maybe").is_err());
+    assert!(EngineCommand::parse("newgame holes=12 stones=4 pie=on").is_err());
+    assert!(EngineCommand::parse("position").is_err());
+    assert!(EngineCommand::parse("position 4,4:0/4,4:0 1").is_err());
+    assert!(EngineCommand::parse("position startpos 1 2").is_err());
+    assert!(EngineCommand::parse("position startpos moves 1 x").is_err());
+    assert!(EngineCommand::parse("go depth 0").is_err());
+    assert!(EngineCommand::parse("go movetime").is_err());
+    assert!(EngineCommand::parse("go wtime 100").is_err());
+    assert!(EngineCommand::parse("isready now").is_err());
+}
+
+#[test]
+fn test_replies_round_trip() {
+    let info = EngineReply::Info {
+        result: SearchResult {
+            best_move: Some(2),
+            value: -3,
+            depth: 8,
+            is_exact: true,
+            is_book_move: false,
+            stats: SearchStats { nodes: 51234, ..SearchStats::default() },
+        },
+        time: Duration::from_millis(120),
+    };
+    assert_eq!(info.to_line(), "info depth 8 score -3 move 2 nodes 51234 time 120 exact");
+
+    let replies = [
+        EngineReply::Id { name: "kalah 0.1.0 (master)".to_string() },
+        EngineReply::KalahOk,
+        EngineReply::ReadyOk,
+        info,
+        EngineReply::BestMove(Some(4)),
+        EngineReply::BestMove(None),
+        EngineReply::Error("Unknown command 'castle'".to_string()),
+    ];
+    for reply in replies {
+        assert_eq!(EngineReply::parse(&reply.to_line()), Ok(reply));
+    }
+
+    // Other engines may send more than we read
+    let EngineReply::Info { result, time } = EngineReply::parse("info depth 3 seldepth 9 score 1 hashfull 12 book").unwrap() else {
+        panic!("expected an info reply");
+    };
+    assert_eq!((result.depth, result.value, result.is_book_move, time), (3, 1, true, Duration::ZERO));
+
+    assert!(EngineReply::parse("bestmove x").is_err());
+    assert!(EngineReply::parse("info depth").is_err());
+    assert!(EngineReply::parse("hello").is_err());
+}
+
+#[test]
+fn test_session_searches_position() {
+    let mut session = session(GameConfig::build(6, 6, true).unwrap());
+
+    assert_eq!(handle(&mut session, "kalah"), vec![EngineReply::Id { name: "test engine".to_string() }, EngineReply::KalahOk]);
+    assert_eq!(handle(&mut session, "isready"), vec![EngineReply::ReadyOk]);
+    assert!(handle(&mut session, "newgame holes=4 stones=3 pie=off").is_empty());
+    assert!(handle(&mut session, "position startpos moves 1 4").is_empty());
+
+    let game_config = GameConfig::build(3, 4, false).unwrap();
+    let mut board = CompactBoard::build(&game_config);
+    board.apply_move(1).unwrap();
+    board.apply_move(4).unwrap();
+    assert_eq!(session.board(), &board);
+
+    assert!(handle(&mut session, "go depth 5").is_empty());
+    let mut replies = Vec::new();
+    session.wait(&mut replies);
+    assert!(!session.is_searching());
+
+    let expected = AlphaBeta::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred).iterative_deepening(&board, &SearchLimits::depth(5), |_| {});
+    let (last, infos) = replies.split_last().unwrap();
+    assert_eq!(last, &EngineReply::BestMove(expected.best_move));
+    assert_eq!(infos.len(), 5);
+    for (depth, info) in (1..).zip(infos) {
+        let EngineReply::Info { result, .. } = info else { panic!("expected info, got {:?}", info) };
+        assert_eq!(result.depth, depth);
+    }
+}
+
+#[test]
+fn test_session_rejects_illegal_input() {
+    let mut session = session(GameConfig::build(3, 4, false).unwrap());
+    assert!(handle(&mut session, "position startpos moves 2").is_empty());
+    let board = *session.board();
+
+    // The board only has four holes
+    let replies = handle(&mut session, "position startpos moves 2 5");
+    assert!(matches!(&replies[..], [EngineReply::Error(err)] if err.starts_with("Move 2 (5) is illegal")));
+    assert_eq!(session.board(), &board);
+
+    // Positions must fit the board of newgame
+    let replies = handle(&mut session, "position 3,3,3:0/3,3,3:0 1 0");
+    assert_eq!(replies, vec![EngineReply::Error("Position has 3 holes and 18 stones, the game has 4 and 24".to_string())]);
+    let replies = handle(&mut session, "position 3,3,3,3:0/3,3,3,3:1 1 0");
+    assert!(matches!(&replies[..], [EngineReply::Error(err)] if err.starts_with("Position has 4 holes and 25 stones")));
+    assert!(handle(&mut session, "position 3,3,3,3:0/3,3,3,2:1 1 0").is_empty());
+
+    // Counts that overflow when added up are rejected, not a crash
+    assert!(handle(&mut session, "newgame holes=1 stones=1 pie=off").is_empty());
+    let replies = handle(&mut session, "position 18446744073709551615:0/1:1 1 0");
+    assert_eq!(replies, vec![EngineReply::Error("Position has too many stones".to_string())]);
+    assert!(handle(&mut session, "position 1:0/1:0 1 4294967295 moves 1").is_empty());
+
+    let replies = handle(&mut session, "go nodes");
+    assert!(matches!(&replies[..], [EngineReply::Error(_)]));
+    assert!(!session.is_searching());
+}
+
+#[test]
+fn test_session_stops_infinite_search() {
+    let mut session = session(GameConfig::build(6, 6, true).unwrap());
+    assert!(handle(&mut session, "go infinite").is_empty());
+    assert!(session.is_searching());
+
+    let replies = handle(&mut session, "position startpos moves 3");
+    assert!(matches!(&replies[..], [EngineReply::Error(err)] if err.contains("while searching")));
+
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(handle(&mut session, "stop").is_empty());
+    let mut replies = Vec::new();
+    session.wait(&mut replies);
+
+    let board = CompactBoard::build(&GameConfig::build(6, 6, true).unwrap());
+    match replies.last() {
+        Some(EngineReply::BestMove(Some(hole_num))) => assert!(board.is_legal_move(*hole_num)),
+        reply => panic!("expected a best move, got {:?}", reply),
+    }
+
+    // The engine is back and can search again
+    assert!(handle(&mut session, "go depth 2").is_empty());
+    session.wait(&mut Vec::new());
+    assert!(!session.is_searching());
+
+    // A search that is over before the stop still answers only then
+    assert!(handle(&mut session, "newgame holes=1 stones=1 pie=off").is_empty());
+    assert!(handle(&mut session, "go infinite").is_empty());
+    let mut replies = Vec::new();
+    session.wait(&mut replies);
+    assert!(replies.iter().all(|reply| matches!(reply, EngineReply::Info { .. })));
+    assert!(session.is_searching());
+    assert!(matches!(&handle(&mut session, "go depth 1")[..], [EngineReply::Error(_)]));
+    assert_eq!(handle(&mut session, "stop"), vec![EngineReply::BestMove(Some(1))]);
+    assert!(!session.is_searching());
+
+    // Without infinite the bestmove comes as soon as the search is over
+    for line in ["go", "go depth 64"] {
+        assert!(handle(&mut session, line).is_empty());
+        let mut replies = Vec::new();
+        session.wait(&mut replies);
+        assert_eq!(replies.last(), Some(&EngineReply::BestMove(Some(1))));
+        assert!(!session.is_searching());
+    }
+}
+
+#[test]
+fn test_run_over_streams() {
+    let input = "kalah\nnewgame holes=3 stones=2 pie=on\n\nposition 0,0,1:5/1,0,0:5 1 9\ngo depth 4\n";
+    let mut output = Vec::new();
+    session(GameConfig::build(6, 6, true).unwrap()).run(Cursor::new(input), &mut output).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines[..2], ["id name test engine", "kalahok"]);
+    assert!(lines[2..lines.len() - 1].iter().all(|line| line.starts_with("info ")));
+    // Sowing hole 3 into the store ends the game
+    assert_eq!(lines.last(), Some(&"bestmove 3"));
+
+    let mut output = Vec::new();
+    session(GameConfig::build(6, 6, true).unwrap()).run(Cursor::new("isready\nquit\nisready\n"), &mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "readyok\n");
+}
@@ -0,0 +1,280 @@
+use kalah::ai::alpha_beta::*;
+use kalah::ai::background_search::*;
+use kalah::ai::compact_board::*;
+use kalah::ai::transposition_table::*;
+use kalah::game::game_config::*;
+
+use proptest::prelude::*;
+
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{ Duration, Instant };
+
+/// Plain minimax without pruning or table, the reference for the search tests.
+fn minimax(board: &CompactBoard, depth: u8) -> i32 {
+    if depth == 0 || board.is_game_over() {
+        return AlphaBeta::evaluate(board);
+    }
+
+    board
+        .legal_moves()
+        .map(|hole_num| {
+            let mut child = *board;
+            child.apply_move(hole_num).unwrap();
+            let value = minimax(&child, depth - 1);
+            if child.is_player_one_turn == board.is_player_one_turn { value } else { -value }
+        })
+        .max()
+        .unwrap()
+}
+
+fn board_after(game_config: &GameConfig, moves: &[usize]) -> CompactBoard {
+    let mut board = CompactBoard::build(game_config);
+    for &hole_num in moves {
+        board.apply_move(hole_num).unwrap();
+    }
+    board
+}
+
+#[test]
+fn test_zobrist_hash() {
+    let game_config = GameConfig::build(4, 6, true).unwrap();
+    let board = CompactBoard::build(&game_config);
+
+    assert_eq!(board.zobrist_hash(), CompactBoard::build(&game_config).zobrist_hash());
+
+    let mut other_side = board;
+    other_side.is_player_one_turn = false;
+    assert_ne!(board.zobrist_hash(), other_side.zobrist_hash());
+
+    // The pie rule only matters before the first move
+    let mut no_pie = board;
+    no_pie.is_check_pipe_rule = false;
+    assert_ne!(board.zobrist_hash(), no_pie.zobrist_hash());
+
+    let mut later = board;
+    later.total_turns = 2;
+    let mut later_no_pie = no_pie;
+    later_no_pie.total_turns = 2;
+    assert_eq!(later.zobrist_hash(), later_no_pie.zobrist_hash());
+}
+
+#[test]
+fn test_zobrist_transposition() {
+    let game_config = GameConfig::build(2, 4, false).unwrap();
+
+    // Different move orders reaching the same position
+    let first = board_after(&game_config, &[1, 2, 4]);
+    let second = board_after(&game_config, &[4, 2, 1]);
+    assert_eq!(first.is_player_one_turn, second.is_player_one_turn);
+    assert_eq!(first.holes, second.holes);
+    assert_eq!(first.stores, second.stores);
+    assert_eq!(first.zobrist_hash(), second.zobrist_hash());
+}
+
+#[test]
+fn test_replacement_policy() {
+    let entry = |key, depth| TableEntry { key, depth, value: -7, bound: Bound::Lower, best_move: Some(3) };
+
+    let table = TranspositionTable::build(6, ReplacementPolicy::DepthPreferred);
+    assert_eq!(table.len(), 4);
+    assert_eq!(table.probe(1), None);
+
+    assert!(table.store(entry(1, 5)));
+    assert!(!table.store(entry(5, 3)));
+    assert_eq!(table.probe(1), Some(entry(1, 5)));
+    assert_eq!(table.probe(5), None);
+
+    assert!(table.store(entry(1, 2)));
+    assert_eq!(table.probe(1).map(|e| e.depth), Some(2));
+
+    let table = TranspositionTable::build(4, ReplacementPolicy::AlwaysReplace);
+    table.store(entry(1, 5));
+    table.store(entry(5, 3));
+    assert_eq!(table.probe(1), None);
+    assert_eq!(table.probe(5), Some(entry(5, 3)));
+
+    table.store(TableEntry { key: 2, depth: 255, value: i32::MIN + 1, bound: Bound::Upper, best_move: None });
+    assert_eq!(table.probe(2).map(|e| (e.value, e.bound, e.best_move)), Some((i32::MIN + 1, Bound::Upper, None)));
+
+    table.clear();
+    assert_eq!(table.probe(5), None);
+}
+
+#[test]
+fn test_search_statistics() {
+    let board = CompactBoard::build(&GameConfig::build(4, 6, false).unwrap());
+    let mut alpha_beta = AlphaBeta::build(DEFAULT_TABLE_SIZE, ReplacementPolicy::DepthPreferred);
+
+    let result = alpha_beta.search(&board, 8);
+
+    assert!(result.best_move.is_some());
+    assert_eq!(result.value, minimax(&board, 8));
+    assert!(result.stats.nodes > 0);
+    assert!(result.stats.table.hits > 0);
+    assert!(result.stats.table.cutoffs > 0);
+    assert!(result.stats.to_string().starts_with("nodes "));
+}
+
+#[test]
+fn test_iterative_deepening() {
+    let board = CompactBoard::build(&GameConfig::build(4, 6, false).unwrap());
+    let mut alpha_beta = AlphaBeta::build(DEFAULT_TABLE_SIZE, ReplacementPolicy::DepthPreferred);
+
+    let mut depths = Vec::new();
+    let result = alpha_beta.iterative_deepening(&board, &SearchLimits::depth(6), |iteration| depths.push(iteration.depth));
+
+    assert_eq!(depths, vec![1, 2, 3, 4, 5, 6]);
+    assert_eq!(result.depth, 6);
+    assert_eq!(result.value, minimax(&board, 6));
+    assert!(!result.is_exact);
+}
+
+#[test]
+fn test_iterative_deepening_stops_when_solved() {
+    let board = CompactBoard::build(&GameConfig::build(2, 2, false).unwrap());
+    let mut alpha_beta = AlphaBeta::build(DEFAULT_TABLE_SIZE, ReplacementPolicy::DepthPreferred);
+
+    let result = alpha_beta.iterative_deepening(&board, &SearchLimits::depth(MAX_DEPTH), |_| {});
+
+    assert!(result.is_exact);
+    assert!(result.depth < MAX_DEPTH);
+    assert_eq!(result.value, minimax(&board, MAX_DEPTH));
+}
+
+#[test]
+fn test_search_budgets() {
+    let board = CompactBoard::build(&GameConfig::build(6, 6, false).unwrap());
+    let mut alpha_beta = AlphaBeta::build(DEFAULT_TABLE_SIZE, ReplacementPolicy::DepthPreferred);
+
+    let result = alpha_beta.iterative_deepening(&board, &SearchLimits::nodes(50_000), |_| {});
+    assert!(board.is_legal_move(result.best_move.unwrap()));
+    assert!(result.stats.nodes <= 50_000 + 1024);
+
+    let started = Instant::now();
+    let result = alpha_beta.iterative_deepening(&board, &SearchLimits::move_time(Duration::from_millis(100)), |_| {});
+    assert!(board.is_legal_move(result.best_move.unwrap()));
+    assert!(result.depth > 1);
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn test_stop_from_another_thread() {
+    let board = CompactBoard::build(&GameConfig::build(6, 6, false).unwrap());
+    let mut alpha_beta = AlphaBeta::build(DEFAULT_TABLE_SIZE, ReplacementPolicy::DepthPreferred);
+    let stop_handle = alpha_beta.stop_handle();
+
+    let search = thread::spawn(move || alpha_beta.iterative_deepening(&board, &SearchLimits::depth(MAX_DEPTH), |_| {}));
+    thread::sleep(Duration::from_millis(100));
+    stop_handle.store(true, Ordering::Relaxed);
+
+    let result = search.join().unwrap();
+    assert!(board.is_legal_move(result.best_move.unwrap()));
+    assert!(!stop_handle.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_background_search_move_now() {
+    let board = CompactBoard::build(&GameConfig::build(6, 6, false).unwrap());
+    let alpha_beta = AlphaBeta::build(DEFAULT_TABLE_SIZE, ReplacementPolicy::DepthPreferred);
+
+    let search = BackgroundSearch::start(alpha_beta, board, SearchLimits::move_time(Duration::from_secs(60)));
+    assert!(matches!(search.next(), Some(SearchEvent::Iteration(_))));
+    search.stop();
+
+    let result = loop {
+        if let Some(SearchEvent::Finished(result)) = search.next() {
+            break result;
+        }
+    };
+    assert!(board.is_legal_move(result.best_move.unwrap()));
+    assert!(search.elapsed() < Duration::from_secs(10));
+
+    // The engine comes back with its table for the next move
+    let alpha_beta = search.join();
+    let mut child = board;
+    child.apply_move(result.best_move.unwrap()).unwrap();
+    assert!(alpha_beta.table.probe(child.zobrist_hash()).is_some());
+}
+
+#[test]
+fn test_principal_variation() {
+    // Large enough that no entry on the line is overwritten
+    let board = CompactBoard::build(&GameConfig::build(3, 3, false).unwrap());
+    let mut alpha_beta = AlphaBeta::build(1 << 20, ReplacementPolicy::DepthPreferred);
+    let result = alpha_beta.search(&board, MAX_DEPTH);
+    assert!(result.is_exact);
+
+    // Solved, so the line plays out to the end with the searched value
+    let line = alpha_beta.principal_variation(&board, result.best_move.unwrap(), 100);
+    assert_eq!(line[0], result.best_move.unwrap());
+
+    let mut end = board;
+    for &hole_num in &line {
+        end.apply_move(hole_num).unwrap();
+    }
+    assert!(end.is_game_over());
+    assert_eq!(end.score_diff(), result.value);
+
+    assert_eq!(alpha_beta.principal_variation(&board, result.best_move.unwrap(), 2), line[..2]);
+    assert!(alpha_beta.principal_variation(&board, 9, 10).is_empty());
+}
+
+#[test]
+fn test_parallel_search() {
+    let board = CompactBoard::build(&GameConfig::build(4, 6, false).unwrap());
+    let mut alpha_beta = AlphaBeta::build(DEFAULT_TABLE_SIZE, ReplacementPolicy::DepthPreferred).with_threads(4);
+    assert_eq!(alpha_beta.threads(), 4);
+
+    let mut main_nodes = 0;
+    let result = alpha_beta.iterative_deepening(&board, &SearchLimits::depth(7), |iteration| main_nodes = iteration.stats.nodes);
+
+    assert_eq!(result.depth, 7);
+    assert_eq!(result.value, minimax(&board, 7));
+    assert!(result.stats.nodes > main_nodes);
+
+    let started = Instant::now();
+    let result = alpha_beta.iterative_deepening(&board, &SearchLimits::move_time(Duration::from_millis(100)), |_| {});
+    assert!(board.is_legal_move(result.best_move.unwrap()));
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
+
+proptest! {
+
+    #[test]
+    fn alpha_beta_matches_minimax(
+        stone_nums_in_hole in 1..=4usize,
+        hole_nums in 1..=5usize,
+        is_pie_rule in any::<bool>(),
+        choices in prop::collection::vec(any::<usize>(), 0..20),
+        depth in 1..=5u8,
+        table_size in prop::sample::select(vec![1usize, 16, 4096]),
+        is_depth_preferred in any::<bool>(),
+    ) {
+        let game_config = GameConfig::build(stone_nums_in_hole, hole_nums, is_pie_rule).unwrap();
+        let mut board = CompactBoard::build(&game_config);
+        for choice in choices {
+            let moves = board.legal_moves().collect::<Vec<_>>();
+            if moves.is_empty() || board.is_game_over() {
+                break;
+            }
+            board.apply_move(moves[choice % moves.len()]).unwrap();
+        }
+        prop_assume!(!board.is_game_over());
+
+        let replacement_policy = if is_depth_preferred { ReplacementPolicy::DepthPreferred } else { ReplacementPolicy::AlwaysReplace };
+        let mut alpha_beta = AlphaBeta::build(table_size, replacement_policy);
+
+        // Searching twice checks that table entries from the first search stay correct
+        for _ in 0..2 {
+            let result = alpha_beta.search(&board, depth);
+            prop_assert_eq!(result.value, minimax(&board, depth));
+
+            let mut child = board;
+            child.apply_move(result.best_move.unwrap()).unwrap();
+            let child_value = minimax(&child, depth - 1);
+            let best_move_value = if child.is_player_one_turn == board.is_player_one_turn { child_value } else { -child_value };
+            prop_assert_eq!(best_move_value, result.value);
+        }
+    }
+}
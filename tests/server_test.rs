@@ -0,0 +1,745 @@
+use kalah::ai::compact_board::*;
+use kalah::game::game_config::*;
+use kalah::game::game_record::*;
+use kalah::net::protocol::*;
+use kalah::net::server::*;
+
+use std::io::{ BufRead, BufReader, Write };
+use std::net::{ SocketAddr, TcpStream };
+use std::thread;
+use std::time::Duration;
+
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// Token of the seat taken last.
+    token: Option<String>,
+}
+
+impl Client {
+
+    fn connect(address: SocketAddr) -> Client {
+        let stream = TcpStream::connect(address).unwrap();
+        stream.set_nodelay(true).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        Client { reader: BufReader::new(stream.try_clone().unwrap()), writer: stream, token: None }
+    }
+
+    fn send_line(&mut self, line: &str) {
+        writeln!(self.writer, "{}", line).unwrap();
+    }
+
+    fn send(&mut self, message: ClientMessage) {
+        self.send_line(&message.to_line());
+    }
+
+    fn receive(&mut self) -> ServerMessage {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        ServerMessage::parse(&line).unwrap()
+    }
+
+    fn receive_state(&mut self) -> GameState {
+        match self.receive() {
+            ServerMessage::State(state) => state,
+            message => panic!("expected a state, got {:?}", message),
+        }
+    }
+
+    fn receive_error(&mut self) -> String {
+        match self.receive() {
+            ServerMessage::Error { message } => message,
+            message => panic!("expected an error, got {:?}", message),
+        }
+    }
+
+    fn receive_games(&mut self) -> Vec<GameSummary> {
+        match self.receive() {
+            ServerMessage::Games { games } => games,
+            message => panic!("expected the games, got {:?}", message),
+        }
+    }
+
+    fn receive_welcome(&mut self) -> (u64, u8) {
+        match self.receive() {
+            ServerMessage::Welcome { game, player, token } => {
+                self.token = Some(token);
+                (game, player)
+            }
+            message => panic!("expected a welcome, got {:?}", message),
+        }
+    }
+
+    /// Says hello and returns the games in the lobby.
+    fn hello(&mut self, name: &str) -> Vec<GameSummary> {
+        self.send(ClientMessage::Hello { name: name.to_string() });
+        self.receive_games()
+    }
+
+    /// Creates a game with the server's settings and returns its id, with the first state read.
+    fn create(&mut self) -> u64 {
+        self.send(ClientMessage::CreateGame { holes: None, stones: None, pie: None, time_control: None });
+        let (game, player) = self.receive_welcome();
+        assert_eq!(player, 1);
+        assert_eq!(self.receive_state().phase, Phase::Waiting);
+        game
+    }
+}
+
+fn start_server(game_config: GameConfig) -> SocketAddr {
+    let server = Server::bind("127.0.0.1:0", game_config).unwrap();
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    address
+}
+
+/// Two players seated in game 1, with the states of the join already read.
+fn start_game(game_config: GameConfig) -> (SocketAddr, Client, Client) {
+    let address = start_server(game_config);
+    let (alice, bob) = seat_players(address);
+    (address, alice, bob)
+}
+
+fn seat_players(address: SocketAddr) -> (Client, Client) {
+    let mut alice = Client::connect(address);
+    let mut bob = Client::connect(address);
+
+    assert_eq!(alice.hello("Alice"), vec![]);
+    assert_eq!(alice.create(), 1);
+    assert_eq!(bob.hello("Bob").len(), 1);
+    bob.send(ClientMessage::JoinGame { game: 1 });
+    assert_eq!(bob.receive_welcome(), (1, 2));
+    assert_eq!(bob.receive_state().phase, Phase::Playing);
+    assert_eq!(alice.receive_state().phase, Phase::Playing);
+
+    (alice, bob)
+}
+
+#[test]
+fn test_messages() {
+    assert_eq!(ClientMessage::Hello { name: "Alice".to_string() }.to_line(), r#"{"type":"hello","name":"Alice"}"#);
+    assert_eq!(ClientMessage::parse(r#"{"type":"move","hole":3}"#), Ok(ClientMessage::Move { hole: 3 }));
+    assert_eq!(
+        ServerMessage::Welcome { game: 3, player: 2, token: "5f0c".to_string() }.to_line(),
+        r#"{"type":"welcome","game":3,"player":2,"token":"5f0c"}"#
+    );
+    assert_eq!(
+        ClientMessage::parse(r#"{"type":"resume","name":"Alice","token":"5f0c"}"#),
+        Ok(ClientMessage::Resume { name: "Alice".to_string(), token: "5f0c".to_string() })
+    );
+    assert_eq!(
+        ServerMessage::parse(r#"{"type":"error","message":"It is not your turn"}"#),
+        Ok(ServerMessage::Error { message: "It is not your turn".to_string() })
+    );
+    assert_eq!(
+        ClientMessage::parse(r#"{"type":"create_game","holes":4}"#),
+        Ok(ClientMessage::CreateGame { holes: Some(4), stones: None, pie: None, time_control: None })
+    );
+    assert_eq!(ClientMessage::CreateGame { holes: None, stones: Some(3), pie: None, time_control: None }.to_line(), r#"{"type":"create_game","stones":3}"#);
+    assert_eq!(ClientMessage::WatchGame { game: 2 }.to_line(), r#"{"type":"watch_game","game":2}"#);
+    assert_eq!(ClientMessage::parse(r#"{"type":"leave_game"}"#), Ok(ClientMessage::LeaveGame));
+
+    let board = CompactBoard::build(&GameConfig::build(4, 6, true).unwrap());
+    let state = ServerMessage::State(GameState {
+        game: 1,
+        phase: Phase::Waiting,
+        players: [Some(SeatInfo { name: "Alice".to_string(), is_connected: true }), None],
+        position: board.to_position(),
+        scores: [0, 0],
+        last_move: None,
+        forfeit_in_ms: [None, None],
+        forfeit: None,
+        clock: None,
+        out_of_time: None,
+    });
+    let line = state.to_line();
+    assert!(line.starts_with(r#"{"type":"state","game":1,"phase":"waiting","players":[{"name":"Alice","is_connected":true},null],"#), "{}", line);
+    assert_eq!(ServerMessage::parse(&line), Ok(state));
+
+    assert!(ClientMessage::parse(r#"{"type":"move","hole":-1}"#).is_err());
+    assert!(ClientMessage::parse(r#"{"type":"resign"}"#).is_err());
+}
+
+#[test]
+fn test_moves_are_validated_and_broadcast() {
+    let game_config = GameConfig::build(4, 6, true).unwrap();
+    let (_, mut alice, mut bob) = start_game(game_config.clone());
+
+    bob.send(ClientMessage::Move { hole: 1 });
+    assert_eq!(bob.receive_error(), "It is not your turn");
+    alice.send(ClientMessage::Move { hole: 7 });
+    assert_eq!(alice.receive_error(), "hole_num must be in range 1..=6 (got 7)");
+
+    alice.send(ClientMessage::Move { hole: 1 });
+    let mut board = CompactBoard::build(&game_config);
+    board.apply_move(1).unwrap();
+    for state in [alice.receive_state(), bob.receive_state()] {
+        assert_eq!(state.position, board.to_position());
+        assert_eq!(state.last_move, Some(1));
+        assert_eq!(state.players[0].as_ref().unwrap().name, "Alice");
+    }
+
+    bob.send(ClientMessage::Move { hole: 2 });
+    board.apply_move(2).unwrap();
+    assert_eq!(bob.receive_state().position, board.to_position());
+    assert_eq!(alice.receive_state().position, board.to_position());
+}
+
+#[test]
+fn test_game_to_the_end() {
+    let game_config = GameConfig::build(1, 2, false).unwrap();
+    let (_, mut alice, mut bob) = start_game(game_config.clone());
+    let mut board = CompactBoard::build(&game_config);
+
+    loop {
+        let hole_num = board.legal_moves().next().unwrap();
+        let client = if board.is_player_one_turn { &mut alice } else { &mut bob };
+        client.send(ClientMessage::Move { hole: hole_num });
+        board.apply_move(hole_num).unwrap();
+
+        let state = alice.receive_state();
+        assert_eq!(bob.receive_state(), state);
+        if board.is_game_over() {
+            assert_eq!(state.phase, Phase::Finished);
+            assert_eq!(state.scores, [board.stores[0] as usize, board.stores[1] as usize]);
+            break;
+        }
+        assert_eq!(state.phase, Phase::Playing);
+    }
+
+    alice.send(ClientMessage::Move { hole: 1 });
+    assert_eq!(alice.receive_error(), "The game is over");
+}
+
+#[test]
+fn test_joining() {
+    let address = start_server(GameConfig::build(4, 6, true).unwrap());
+    let mut alice = Client::connect(address);
+
+    alice.send(ClientMessage::Move { hole: 1 });
+    assert_eq!(alice.receive_error(), "Say hello first");
+    alice.send(ClientMessage::Hello { name: " ".to_string() });
+    assert!(alice.receive_error().starts_with("Name must have 1 to"));
+    alice.send_line("not json");
+    assert!(alice.receive_error().starts_with("Invalid message"));
+    for name in ["Al\nice", "\u{1b}[2JAlice", "Alice\"]"] {
+        alice.send(ClientMessage::Hello { name: name.to_string() });
+        assert_eq!(alice.receive_error(), "Names cannot have control characters or quotes");
+    }
+
+    assert_eq!(alice.hello("Alice"), vec![]);
+    alice.send(ClientMessage::Hello { name: "Alice".to_string() });
+    assert_eq!(alice.receive_error(), "You are already known as 'Alice'");
+    alice.send(ClientMessage::Move { hole: 1 });
+    assert_eq!(alice.receive_error(), "Join a game first");
+    alice.send(ClientMessage::JoinGame { game: 1 });
+    assert_eq!(alice.receive_error(), "There is no game 1");
+
+    assert_eq!(alice.create(), 1);
+    alice.send(ClientMessage::Move { hole: 1 });
+    assert_eq!(alice.receive_error(), "Waiting for the second player");
+    alice.send(ClientMessage::CreateGame { holes: None, stones: None, pie: None, time_control: None });
+    assert_eq!(alice.receive_error(), "Leave the game first");
+
+    let mut other = Client::connect(address);
+    other.send(ClientMessage::Hello { name: "Alice".to_string() });
+    assert_eq!(other.receive_error(), "The name 'Alice' is taken");
+
+    // Leaving before the game starts frees the name, and the empty game goes away
+    drop(alice);
+    let games = loop {
+        other.send(ClientMessage::Hello { name: "Alice".to_string() });
+        match other.receive() {
+            ServerMessage::Games { games } => break games,
+            _ => thread::sleep(Duration::from_millis(10)),
+        }
+    };
+    assert_eq!(games, vec![]);
+
+    assert_eq!(other.create(), 2);
+    let mut bob = Client::connect(address);
+    bob.hello("Bob");
+    bob.send(ClientMessage::JoinGame { game: 2 });
+    assert_eq!(bob.receive_welcome(), (2, 2));
+    let mut late = Client::connect(address);
+    late.hello("Dave");
+    late.send(ClientMessage::JoinGame { game: 2 });
+    assert_eq!(late.receive_error(), "The game is full");
+}
+
+#[test]
+fn test_disconnect_keeps_seat() {
+    let (_, alice, mut bob) = start_game(GameConfig::build(4, 6, true).unwrap());
+
+    drop(alice);
+    let state = bob.receive_state();
+    assert_eq!(state.phase, Phase::Playing);
+    assert_eq!(state.players[0], Some(SeatInfo { name: "Alice".to_string(), is_connected: false }));
+    assert!(state.players[1].as_ref().unwrap().is_connected);
+}
+
+#[test]
+fn test_reconnect_reclaims_seat() {
+    let (address, mut alice, mut bob) = start_game(GameConfig::build(4, 6, true).unwrap());
+    alice.send(ClientMessage::Move { hole: 1 });
+    alice.receive_state();
+    bob.receive_state();
+    let token = alice.token.clone().unwrap();
+
+    drop(alice);
+    let state = bob.receive_state();
+    assert!(!state.players[0].as_ref().unwrap().is_connected);
+    assert!(state.forfeit_in_ms[0].is_some_and(|ms| ms <= DEFAULT_GRACE_PERIOD.as_millis() as u64));
+    assert_eq!(state.forfeit_in_ms[1], None);
+    bob.send(ClientMessage::Move { hole: 2 });
+    let missed = bob.receive_state();
+
+    // The name is kept for the player with the token
+    let mut alice = Client::connect(address);
+    alice.send(ClientMessage::Hello { name: "Alice".to_string() });
+    assert_eq!(alice.receive_error(), "The name 'Alice' is taken");
+    alice.send(ClientMessage::Resume { name: "Alice".to_string(), token: "wrong".to_string() });
+    assert_eq!(alice.receive_error(), "The name 'Alice' is taken");
+
+    alice.send(ClientMessage::Resume { name: "Alice".to_string(), token: token.clone() });
+    assert_eq!(alice.receive_welcome(), (1, 1));
+    assert_eq!(alice.token, Some(token));
+    // The move played while away comes first, then the game as it is
+    assert_eq!(alice.receive_state(), missed);
+    let state = alice.receive_state();
+    assert_eq!(state.phase, Phase::Playing);
+    assert_eq!(state.last_move, Some(2));
+    assert_eq!(state.players[0], Some(SeatInfo { name: "Alice".to_string(), is_connected: true }));
+    assert_eq!(state.forfeit_in_ms, [None, None]);
+    assert_eq!(bob.receive_state(), state);
+
+    // The game goes on with the new connection
+    alice.send(ClientMessage::Move { hole: 3 });
+    assert_eq!(alice.receive_state().last_move, Some(3));
+
+    // Leaving the game keeps the seat too, and joining takes it back
+    alice.send(ClientMessage::LeaveGame);
+    assert_eq!(alice.receive_games()[0].players[0], Some(SeatInfo { name: "Alice".to_string(), is_connected: false }));
+    alice.send(ClientMessage::JoinGame { game: 1 });
+    assert_eq!(alice.receive_welcome(), (1, 1));
+}
+
+#[test]
+fn test_ping_reports_latencies() {
+    let (_, mut alice, mut bob) = start_game(GameConfig::build(4, 6, true).unwrap());
+
+    alice.send(ClientMessage::Ping { id: 1, latency_ms: None });
+    assert_eq!(alice.receive(), ServerMessage::Pong { id: 1, latencies: [None, None] });
+    alice.send(ClientMessage::Ping { id: 2, latency_ms: Some(12) });
+    assert_eq!(alice.receive(), ServerMessage::Pong { id: 2, latencies: [Some(12), None] });
+    bob.send(ClientMessage::Ping { id: 1, latency_ms: Some(30) });
+    assert_eq!(bob.receive(), ServerMessage::Pong { id: 1, latencies: [Some(12), Some(30)] });
+
+    assert_eq!(ClientMessage::Ping { id: 2, latency_ms: Some(12) }.to_line(), r#"{"type":"ping","id":2,"latency_ms":12}"#);
+}
+
+#[test]
+fn test_lobby_lists_games() {
+    let address = start_server(GameConfig::build(4, 6, true).unwrap());
+    let mut carol = Client::connect(address);
+    assert_eq!(carol.hello("Carol"), vec![]);
+
+    let mut alice = Client::connect(address);
+    alice.hello("Alice");
+    alice.send(ClientMessage::CreateGame { holes: Some(4), stones: Some(3), pie: Some(false), time_control: None });
+    assert_eq!(alice.receive_welcome(), (1, 1));
+    let state = alice.receive_state();
+    assert_eq!(state.position.side_one, vec![3; 4]);
+
+    // Everyone in the lobby sees the new game
+    let games = carol.receive_games();
+    assert_eq!(games, vec![GameSummary {
+        id: 1,
+        holes: 4,
+        stones: 3,
+        pie: false,
+        phase: Phase::Waiting,
+        players: [Some(SeatInfo { name: "Alice".to_string(), is_connected: true }), None],
+        spectators: 0,
+        time_control: None,
+    }]);
+
+    let mut bob = Client::connect(address);
+    bob.hello("Bob");
+    bob.send(ClientMessage::CreateGame { holes: Some(10), stones: None, pie: None, time_control: None });
+    assert_eq!(bob.receive_error(), "Max hole numbers are 9");
+    bob.send(ClientMessage::CreateGame { holes: Some(0), stones: None, pie: None, time_control: None });
+    assert_eq!(bob.receive_error(), "A game needs at least one hole and one stone");
+    assert_eq!(bob.create(), 2);
+
+    // Games go on side by side
+    let games = carol.receive_games();
+    assert_eq!(games.iter().map(|game| (game.id, game.holes, game.stones)).collect::<Vec<_>>(), vec![(1, 4, 3), (2, 6, 4)]);
+    carol.send(ClientMessage::JoinGame { game: 1 });
+    assert_eq!(carol.receive_welcome(), (1, 2));
+    assert_eq!(carol.receive_state().phase, Phase::Playing);
+    assert_eq!(alice.receive_state().phase, Phase::Playing);
+
+    bob.send(ClientMessage::LeaveGame);
+    let games = bob.receive_games();
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].phase, Phase::Playing);
+    bob.send(ClientMessage::ListGames);
+    assert_eq!(bob.receive_games(), games);
+}
+
+#[test]
+fn test_spectators() {
+    let game_config = GameConfig::build(4, 6, true).unwrap();
+    let (address, mut alice, mut bob) = start_game(game_config.clone());
+
+    let mut carol = Client::connect(address);
+    carol.hello("Carol");
+    carol.send(ClientMessage::WatchGame { game: 1 });
+    assert_eq!(carol.receive(), ServerMessage::Watching { game: 1 });
+    assert_eq!(carol.receive_state().position, CompactBoard::build(&game_config).to_position());
+
+    let mut dave = Client::connect(address);
+    assert_eq!(dave.hello("Dave")[0].spectators, 1);
+
+    // Spectators get every state but cannot move
+    alice.send(ClientMessage::Move { hole: 1 });
+    let state = alice.receive_state();
+    assert_eq!(bob.receive_state(), state);
+    assert_eq!(carol.receive_state(), state);
+    carol.send(ClientMessage::Move { hole: 1 });
+    assert_eq!(carol.receive_error(), "Join a game first");
+    carol.send(ClientMessage::Ping { id: 1, latency_ms: Some(5) });
+    assert_eq!(carol.receive(), ServerMessage::Pong { id: 1, latencies: [None, None] });
+
+    carol.send(ClientMessage::LeaveGame);
+    assert_eq!(carol.receive_games()[0].spectators, 0);
+    assert_eq!(dave.receive_games()[0].spectators, 0);
+    carol.send(ClientMessage::LeaveGame);
+    assert_eq!(carol.receive_error(), "You are not in a game");
+}
+
+fn receive_chat(client: &mut Client) -> ChatLine {
+    match client.receive() {
+        ServerMessage::Chat(line) => line,
+        message => panic!("expected a chat line, got {:?}", message),
+    }
+}
+
+#[test]
+fn test_chat_channels() {
+    let (address, mut alice, mut bob) = start_game(GameConfig::build(4, 6, true).unwrap());
+    let mut carol = Client::connect(address);
+    carol.hello("Carol");
+
+    // The game chat goes to the players and spectators, the lobby chat to the lobby
+    alice.send(ClientMessage::Chat { text: "good luck".to_string() });
+    let line = ChatLine { game: Some(1), from: "Alice".to_string(), text: "good luck".to_string(), is_emote: false };
+    assert_eq!(receive_chat(&mut alice), line);
+    assert_eq!(receive_chat(&mut bob), line);
+    assert_eq!(line.display(), "Alice: good luck");
+
+    carol.send(ClientMessage::Chat { text: "anyone up for 4x4?".to_string() });
+    assert_eq!(receive_chat(&mut carol).game, None);
+
+    // A spectator gets the game's chat so far
+    carol.send(ClientMessage::WatchGame { game: 1 });
+    assert_eq!(carol.receive(), ServerMessage::Watching { game: 1 });
+    carol.receive_state();
+    assert_eq!(receive_chat(&mut carol), line);
+
+    carol.send(ClientMessage::Emote { emote: Emote::Wave });
+    let wave = ChatLine { game: Some(1), from: "Carol".to_string(), text: "waves".to_string(), is_emote: true };
+    for client in [&mut alice, &mut bob, &mut carol] {
+        assert_eq!(receive_chat(client), wave);
+    }
+    assert_eq!(wave.display(), "* Carol waves");
+
+    // Newcomers to the lobby get its chat so far
+    let mut dave = Client::connect(address);
+    dave.hello("Dave");
+    assert_eq!(receive_chat(&mut dave).text, "anyone up for 4x4?");
+}
+
+#[test]
+fn test_chat_limits() {
+    let (_, mut alice, mut bob) = start_game(GameConfig::build(4, 6, true).unwrap());
+
+    alice.send(ClientMessage::Chat { text: "  ".to_string() });
+    assert_eq!(alice.receive_error(), format!("Chat messages must have 1 to {} characters", MAX_CHAT_LEN));
+    alice.send(ClientMessage::Chat { text: "x".repeat(MAX_CHAT_LEN + 1) });
+    assert!(alice.receive_error().starts_with("Chat messages must have"));
+    alice.send(ClientMessage::Chat { text: "hi\u{1b}[2J there".to_string() });
+    assert_eq!(receive_chat(&mut alice).text, "hi[2J there");
+    assert_eq!(receive_chat(&mut bob).text, "hi[2J there");
+
+    for _ in 1..CHAT_BURST {
+        alice.send(ClientMessage::Emote { emote: Emote::Thinking });
+        receive_chat(&mut alice);
+    }
+    alice.send(ClientMessage::Chat { text: "one too many".to_string() });
+    assert_eq!(alice.receive_error(), "You are chatting too fast, wait a moment");
+
+    // Moves are not limited
+    alice.send(ClientMessage::Move { hole: 1 });
+    assert_eq!(alice.receive_state().last_move, Some(1));
+
+    assert_eq!(Emote::parse("gg"), Some(Emote::GoodGame));
+    assert_eq!(ClientMessage::Emote { emote: Emote::GoodGame }.to_line(), r#"{"type":"emote","emote":"good_game"}"#);
+}
+
+fn read_record_until(path: &std::path::Path, is_done: impl Fn(&GameRecord) -> bool) -> GameRecord {
+    for _ in 0..500 {
+        if let Ok(record) = std::fs::read_to_string(path).map_err(|err| err.to_string()).and_then(|text| GameRecord::parse(&text))
+            && is_done(&record)
+        {
+            return record;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("no record at {}", path.display());
+}
+
+#[test]
+fn test_records_with_chat() {
+    let directory = std::env::temp_dir().join(format!("kalah-records-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let game_config = GameConfig::build(1, 2, false).unwrap();
+    let server = Server::bind("127.0.0.1:0", game_config.clone()).unwrap().with_records(&directory, true);
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    let mut alice = Client::connect(address);
+    alice.hello("Alice");
+    alice.create();
+    alice.send(ClientMessage::Chat { text: "good {luck}".to_string() });
+    receive_chat(&mut alice);
+    let mut bob = Client::connect(address);
+    bob.hello("Bob");
+    bob.send(ClientMessage::JoinGame { game: 1 });
+    bob.receive_welcome();
+    bob.receive_state();
+    assert_eq!(receive_chat(&mut bob).text, "good {luck}");
+    alice.receive_state();
+
+    let mut board = CompactBoard::build(&game_config);
+    while !board.is_game_over() {
+        let hole_num = board.legal_moves().next().unwrap();
+        let client = if board.is_player_one_turn { &mut alice } else { &mut bob };
+        client.send(ClientMessage::Move { hole: hole_num });
+        alice.receive_state();
+        bob.receive_state();
+        board.apply_move(hole_num).unwrap();
+    }
+    // The record is written right after the last state goes out
+    let path = directory.join("game-1.txt");
+    let record = read_record_until(&path, |record| record.result.is_some());
+    assert_eq!(record.player_one_name, "Alice");
+    assert_eq!(record.chat, vec![(0, "Alice: good (luck)".to_string())]);
+    assert!(record.replay().is_ok());
+
+    // What is said after the end is in the record once the game closes
+    bob.send(ClientMessage::Emote { emote: Emote::GoodGame });
+    drop(alice);
+    drop(bob);
+    let moves = record.moves.len();
+    let record = read_record_until(&path, |record| record.chat.len() == 2);
+    assert_eq!(record.chat[1], (moves, "* Bob says good game".to_string()));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_forfeit_after_grace_period() {
+    let directory = std::env::temp_dir().join(format!("kalah-forfeit-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let server = Server::bind("127.0.0.1:0", GameConfig::build(4, 6, true).unwrap())
+        .unwrap()
+        .with_records(&directory, false)
+        .with_grace_period(Duration::from_millis(200));
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    let (alice, mut bob) = seat_players(address);
+    let token = alice.token.clone().unwrap();
+    drop(alice);
+    let state = bob.receive_state();
+    assert!(state.forfeit_in_ms[0].is_some_and(|ms| ms <= 200));
+
+    // Nobody came back in time
+    let state = bob.receive_state();
+    assert_eq!(state.phase, Phase::Finished);
+    assert_eq!(state.forfeit, Some(1));
+    assert_eq!(state.forfeit_in_ms, [None, None]);
+    let record = read_record_until(&directory.join("game-1.txt"), |_| true);
+    assert_eq!(record.result, None);
+    assert_eq!(record.comments, vec![(0, "Alice forfeits".to_string())]);
+
+    // The token of a game that is over only says hello
+    let mut alice = Client::connect(address);
+    alice.send(ClientMessage::Resume { name: "Alice".to_string(), token });
+    assert_eq!(alice.receive_games()[0].phase, Phase::Finished);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_saved_games_resume_after_restart() {
+    let directory = std::env::temp_dir().join(format!("kalah-saved-games-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let game_config = GameConfig::build(4, 6, true).unwrap();
+    let start = || {
+        let server = Server::bind("127.0.0.1:0", game_config.clone()).unwrap().with_saved_games(&directory).unwrap();
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        address
+    };
+
+    let (mut alice, mut bob) = seat_players(start());
+    alice.send(ClientMessage::Move { hole: 1 });
+    alice.receive_state();
+    bob.receive_state();
+    alice.send(ClientMessage::Chat { text: "back soon".to_string() });
+    receive_chat(&mut alice);
+    receive_chat(&mut bob);
+    let tokens = [&alice, &bob].map(|client| client.token.clone().unwrap());
+    assert!(directory.join("game-1.json").exists());
+
+    // A second server on the directory stands in for the restarted one
+    let address = start();
+    let mut alice = Client::connect(address);
+    alice.send(ClientMessage::Resume { name: "Alice".to_string(), token: tokens[0].clone() });
+    assert_eq!(alice.receive_welcome(), (1, 1));
+    let state = alice.receive_state();
+    let mut board = CompactBoard::build(&game_config);
+    board.apply_move(1).unwrap();
+    assert_eq!(state.phase, Phase::Playing);
+    assert_eq!(state.position, board.to_position());
+    assert!(!state.players[1].as_ref().unwrap().is_connected);
+    assert!(state.forfeit_in_ms[1].is_some());
+    assert_eq!(receive_chat(&mut alice).text, "back soon");
+
+    let mut bob = Client::connect(address);
+    bob.send(ClientMessage::Resume { name: "Bob".to_string(), token: tokens[1].clone() });
+    assert_eq!(bob.receive_welcome(), (1, 2));
+    bob.receive_state();
+    receive_chat(&mut bob);
+    assert!(alice.receive_state().players[1].as_ref().unwrap().is_connected);
+    bob.send(ClientMessage::Move { hole: 2 });
+    assert_eq!(bob.receive_state().last_move, Some(2));
+    assert_eq!(alice.receive_state().last_move, Some(2));
+
+    // New games do not take the ids of resumed ones
+    let mut carol = Client::connect(address);
+    assert_eq!(carol.hello("Carol").len(), 1);
+    assert_eq!(carol.create(), 2);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_loss_on_time() {
+    let directory = std::env::temp_dir().join(format!("kalah-time-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let server = Server::bind("127.0.0.1:0", GameConfig::build(4, 6, true).unwrap()).unwrap().with_records(&directory, false);
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    let mut alice = Client::connect(address);
+    alice.hello("Alice");
+    alice.send(ClientMessage::CreateGame { holes: None, stones: None, pie: None, time_control: Some("5 minutes".to_string()) });
+    assert!(alice.receive_error().starts_with("Time control must be"));
+    alice.send(ClientMessage::CreateGame { holes: None, stones: None, pie: None, time_control: Some("1".to_string()) });
+    alice.receive_welcome();
+    let clock = *alice.receive_state().clock.unwrap();
+    assert_eq!(clock, ClockState { time_control: "1".to_string(), remaining_ms: [1000, 1000], elapsed_ms: None });
+
+    let mut bob = Client::connect(address);
+    assert_eq!(bob.hello("Bob")[0].time_control.as_deref(), Some("1"));
+    bob.send(ClientMessage::JoinGame { game: 1 });
+    bob.receive_welcome();
+    // Alice's clock starts with the game
+    assert!(bob.receive_state().clock.unwrap().elapsed_ms.is_some());
+    alice.receive_state();
+    alice.send(ClientMessage::Move { hole: 1 });
+    let state = alice.receive_state();
+    assert!(state.clock.as_ref().unwrap().remaining_ms[0] <= 1000);
+    assert_eq!(state.clock.unwrap().remaining_ms[1], 1000);
+    bob.receive_state();
+
+    // Bob lets his second run out
+    for client in [&mut alice, &mut bob] {
+        let state = client.receive_state();
+        assert_eq!(state.phase, Phase::Finished);
+        assert_eq!(state.out_of_time, Some(2));
+        assert_eq!(state.clock.unwrap().remaining_ms[1], 0);
+    }
+    bob.send(ClientMessage::Move { hole: 1 });
+    assert_eq!(bob.receive_error(), "The game is over");
+
+    let record = read_record_until(&directory.join("game-1.txt"), |_| true);
+    assert_eq!(record.result, None);
+    assert_eq!(record.time_control.map(|time_control| time_control.to_notation()), Some("1".to_string()));
+    assert_eq!(record.comments, vec![(1, "Bob loses on time".to_string())]);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_absurd_time_control_is_rejected() {
+    let address = start_server(GameConfig::build(4, 6, true).unwrap());
+    let mut alice = Client::connect(address);
+    alice.hello("Alice");
+    alice.send(ClientMessage::CreateGame { holes: None, stones: None, pie: None, time_control: Some("300+18446744073709551615".to_string()) });
+    assert!(alice.receive_error().starts_with("Times of a time control must be at most 86400 seconds"));
+
+    // The server goes on serving
+    assert_eq!(alice.create(), 1);
+    let mut bob = Client::connect(address);
+    assert_eq!(bob.hello("Bob").len(), 1);
+}
+
+#[test]
+fn test_overlong_line_disconnects() {
+    let address = start_server(GameConfig::build(4, 6, true).unwrap());
+    let mut client = Client::connect(address);
+    client.send_line(&"x".repeat(MAX_LINE_LEN));
+    assert!(client.receive_error().starts_with("Invalid message"));
+
+    // A line that never ends is not read to its end
+    client.send_line(&"x".repeat(MAX_LINE_LEN + 1));
+    let mut line = String::new();
+    // The server hangs up, which may reset the connection
+    assert!(client.reader.read_line(&mut line).is_err() || line.is_empty());
+}
+
+#[test]
+fn test_restored_games_wait_for_a_player() {
+    let directory = std::env::temp_dir().join(format!("kalah-restored-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let start = || {
+        let server = Server::bind("127.0.0.1:0", GameConfig::build(4, 6, true).unwrap())
+            .unwrap()
+            .with_grace_period(Duration::from_millis(200))
+            .with_saved_games(&directory)
+            .unwrap();
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        address
+    };
+
+    let (alice, bob) = seat_players(start());
+    let tokens = [&alice, &bob].map(|client| client.token.clone().unwrap());
+
+    // Nobody forfeits while both players are away from the restarted server
+    let address = start();
+    thread::sleep(Duration::from_millis(500));
+    let mut bob = Client::connect(address);
+    bob.send(ClientMessage::Resume { name: "Bob".to_string(), token: tokens[1].clone() });
+    assert_eq!(bob.receive_welcome(), (1, 2));
+    let state = bob.receive_state();
+    assert_eq!(state.phase, Phase::Playing);
+    assert_eq!(state.forfeit_in_ms[1], None);
+    assert!(state.forfeit_in_ms[0].is_some_and(|ms| ms <= 200));
+
+    // The grace period of the player still away starts with the first one back
+    let state = bob.receive_state();
+    assert_eq!(state.forfeit, Some(1));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
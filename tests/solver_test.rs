@@ -0,0 +1,192 @@
+use kalah::ai::alpha_beta::*;
+use kalah::ai::background_search::*;
+use kalah::ai::compact_board::*;
+use kalah::ai::engine::*;
+use kalah::ai::solver::*;
+use kalah::ai::transposition_table::*;
+use kalah::game::game_config::*;
+
+use proptest::prelude::*;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+
+const TABLE_SIZE: usize = 1 << 16;
+
+/// Exhaustive minimax to the end of the game, the reference for the solver tests.
+fn perfect_value(board: &CompactBoard) -> i32 {
+    fn memoized(board: &CompactBoard, values: &mut HashMap<CompactBoard, i32>) -> i32 {
+        if board.is_game_over() {
+            return AlphaBeta::evaluate(board);
+        }
+        if let Some(&value) = values.get(board) {
+            return value;
+        }
+
+        let value = board
+            .legal_moves()
+            .map(|hole_num| {
+                let mut child = *board;
+                child.apply_move(hole_num).unwrap();
+                let value = memoized(&child, values);
+                if child.is_player_one_turn == board.is_player_one_turn { value } else { -value }
+            })
+            .max()
+            .unwrap();
+        values.insert(*board, value);
+        value
+    }
+
+    memoized(board, &mut HashMap::new())
+}
+
+fn play_random(board: &mut CompactBoard, choices: &[usize]) {
+    for &choice in choices {
+        let moves = board.legal_moves().collect::<Vec<_>>();
+        if moves.is_empty() || board.is_game_over() {
+            break;
+        }
+        board.apply_move(moves[choice % moves.len()]).unwrap();
+    }
+}
+
+#[test]
+fn test_solve_start_positions() {
+    for (stone_nums_in_hole, hole_nums, is_pie_rule) in [(2, 3, false), (2, 3, true), (3, 3, false), (1, 4, true)] {
+        let board = CompactBoard::build(&GameConfig::build(stone_nums_in_hole, hole_nums, is_pie_rule).unwrap());
+        let mut solver = Solver::build(TABLE_SIZE);
+
+        let solution = solver.solve(&board).unwrap();
+
+        assert_eq!(solution.value, perfect_value(&board));
+        assert!(solution.passes > 0);
+        assert!(solution.stats.nodes > 0);
+
+        let mut child = board;
+        child.apply_move(solution.best_move.unwrap()).unwrap();
+        let child_value = perfect_value(&child);
+        let best_move_value = if child.is_player_one_turn == board.is_player_one_turn { child_value } else { -child_value };
+        assert_eq!(best_move_value, solution.value);
+    }
+}
+
+#[test]
+fn test_solved_game_over_position() {
+    let mut board = CompactBoard::build(&GameConfig::build(1, 1, false).unwrap());
+    board.apply_move(1).unwrap();
+    assert!(board.is_game_over());
+
+    let solution = Solver::build(TABLE_SIZE).solve(&board).unwrap();
+
+    assert_eq!(solution.best_move, None);
+    assert_eq!(solution.value, AlphaBeta::evaluate(&board));
+}
+
+#[test]
+fn test_verdict() {
+    assert_eq!(Verdict(10).to_string(), "First player wins by 10");
+    assert_eq!(Verdict(-2).to_string(), "Second player wins by 2");
+    assert_eq!(Verdict(0).to_string(), "Draw");
+
+    // Player two to move after 2 on a 3x3 board
+    let mut board = CompactBoard::build(&GameConfig::build(3, 3, false).unwrap());
+    board.apply_move(2).unwrap();
+    assert!(!board.is_player_one_turn);
+
+    let solution = Solver::build(TABLE_SIZE).solve(&board).unwrap();
+    assert_eq!(solution.player_one_value(&board), -perfect_value(&board));
+}
+
+#[test]
+fn test_table_shared_with_alpha_beta() {
+    let board = CompactBoard::build(&GameConfig::build(3, 3, false).unwrap());
+    let table = Arc::new(TranspositionTable::build(TABLE_SIZE, ReplacementPolicy::DepthPreferred));
+
+    let mut alpha_beta = AlphaBeta::with_table(Arc::clone(&table));
+    alpha_beta.iterative_deepening(&board, &SearchLimits::depth(6), |_| {});
+
+    // Depth-limited entries only order moves for the solver
+    let solution = Solver::with_table(Arc::clone(&table)).solve(&board).unwrap();
+    assert_eq!(solution.value, perfect_value(&board));
+}
+
+#[test]
+fn test_perfect_opponent_in_background() {
+    let board = CompactBoard::build(&GameConfig::build(3, 3, true).unwrap());
+
+    let search = BackgroundSearch::start(Solver::build(TABLE_SIZE), board, SearchLimits::move_time(Duration::from_secs(10)));
+    let result = loop {
+        if let Some(SearchEvent::Finished(result)) = search.next() {
+            break result;
+        }
+    };
+
+    // Solved within the time limit, the move is perfect
+    assert!(result.is_exact);
+    assert_eq!(result.value, perfect_value(&board));
+    assert!(board.is_legal_move(result.best_move.unwrap()));
+    search.join();
+
+    // Stopped before the position is solved it still answers with a legal move
+    let board = CompactBoard::build(&GameConfig::build(6, 6, false).unwrap());
+    let search = BackgroundSearch::start(Solver::build(TABLE_SIZE), board, SearchLimits::depth(MAX_DEPTH));
+    search.stop();
+    let result = loop {
+        if let Some(SearchEvent::Finished(result)) = search.next() {
+            break result;
+        }
+    };
+    assert!(!result.is_exact);
+    assert!(board.is_legal_move(result.best_move.unwrap()));
+    search.join();
+
+    // Out of time it plays the move of the guess search
+    let started = Instant::now();
+    let search = BackgroundSearch::start(Solver::build(TABLE_SIZE), board, SearchLimits::move_time(Duration::from_millis(100)));
+    let result = loop {
+        if let Some(SearchEvent::Finished(result)) = search.next() {
+            break result;
+        }
+    };
+    assert!(started.elapsed() < Duration::from_secs(2), "took {:?}", started.elapsed());
+    assert!(!result.is_exact);
+    assert!(result.depth > 0);
+    assert!(board.is_legal_move(result.best_move.unwrap()));
+
+    let mut solver = Solver::build(TABLE_SIZE);
+    let result = solver.think(&board, &SearchLimits::nodes(5000), &mut |_| {});
+    assert!(!result.is_exact);
+    assert!(result.stats.nodes <= 5000 + 1024);
+}
+
+proptest! {
+
+    #[test]
+    fn solver_matches_minimax(
+        stone_nums_in_hole in 1..=3usize,
+        hole_nums in 1..=3usize,
+        is_pie_rule in any::<bool>(),
+        choices in prop::collection::vec(any::<usize>(), 0..20),
+        table_size in prop::sample::select(vec![1usize, 16, 4096]),
+    ) {
+        let mut board = CompactBoard::build(&GameConfig::build(stone_nums_in_hole, hole_nums, is_pie_rule).unwrap());
+        play_random(&mut board, &choices);
+
+        let mut solver = Solver::build(table_size);
+
+        // Solving twice checks that table entries from the first solve stay correct
+        for _ in 0..2 {
+            let solution = solver.solve(&board).unwrap();
+            prop_assert_eq!(solution.value, perfect_value(&board));
+
+            if let Some(hole_num) = solution.best_move {
+                let mut child = board;
+                child.apply_move(hole_num).unwrap();
+                let child_value = perfect_value(&child);
+                let best_move_value = if child.is_player_one_turn == board.is_player_one_turn { child_value } else { -child_value };
+                prop_assert_eq!(best_move_value, solution.value);
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use kalah::game::game_config::*;
+use kalah::game::game_field::*;
+use kalah::game::game_process::*;
+use kalah::game::game_status::*;
+use kalah::game::time_control::*;
+
+use std::time::{ Duration, Instant };
+
+const fn secs(seconds: u64) -> Duration {
+    Duration::from_secs(seconds)
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(TimeControl::parse("300"), Ok(Some(TimeControl::SuddenDeath { base: secs(300) })));
+    assert_eq!(TimeControl::parse("300+5"), Ok(Some(TimeControl::Fischer { base: secs(300), increment: secs(5) })));
+    assert_eq!(TimeControl::parse("60d2"), Ok(Some(TimeControl::Bronstein { base: secs(60), delay: secs(2) })));
+    assert_eq!(TimeControl::parse("-"), Ok(None));
+    for notation in ["300", "300+5", "60d2"] {
+        assert_eq!(TimeControl::parse(notation).unwrap().unwrap().to_notation(), notation);
+    }
+
+    assert!(TimeControl::parse("").is_err());
+    assert!(TimeControl::parse("5m").is_err());
+    assert!(TimeControl::parse("300+").is_err());
+    assert!(TimeControl::parse("0+5").is_err());
+    assert!(TimeControl::parse("86400+86400").is_ok());
+    assert!(TimeControl::parse("86401").is_err());
+    assert!(TimeControl::parse("300+18446744073709551615").is_err());
+    assert!(TimeControl::parse("300d86401").is_err());
+}
+
+#[test]
+fn test_sudden_death() {
+    let start = Instant::now();
+    let mut clock = GameClock::build(TimeControl::SuddenDeath { base: secs(10) });
+    assert_eq!(clock.remaining_at(0, start), secs(10));
+
+    clock.start(0, start);
+    assert_eq!(clock.remaining_at(0, start + secs(3)), secs(7));
+    assert_eq!(clock.remaining_at(1, start + secs(3)), secs(10));
+    clock.press(1, start + secs(3));
+    assert_eq!(clock.remaining, [secs(7), secs(10)]);
+    assert_eq!(clock.running, Some((1, start + secs(3))));
+
+    // An extra turn keeps the same clock running
+    clock.press(1, start + secs(4));
+    assert_eq!(clock.remaining, [secs(7), secs(9)]);
+    assert_eq!(clock.flag(start + secs(12)), None);
+    assert_eq!(clock.out_of_time(), None);
+
+    assert_eq!(clock.flag(start + secs(13)), Some(1));
+    assert_eq!(clock.remaining, [secs(7), Duration::ZERO]);
+    assert_eq!(clock.running, None);
+    assert_eq!(clock.out_of_time(), Some(1));
+}
+
+#[test]
+fn test_fischer_increment() {
+    let start = Instant::now();
+    let mut clock = GameClock::build(TimeControl::Fischer { base: secs(10), increment: secs(2) });
+    clock.start(0, start);
+    clock.press(1, start + secs(1));
+    assert_eq!(clock.remaining, [secs(11), secs(10)]);
+
+    // No increment for a player whose time is up
+    clock.stop(start + secs(20));
+    assert_eq!(clock.remaining, [secs(11), Duration::ZERO]);
+    assert_eq!(clock.out_of_time(), Some(1));
+}
+
+#[test]
+fn test_bronstein_delay() {
+    let start = Instant::now();
+    let mut clock = GameClock::build(TimeControl::Bronstein { base: secs(10), delay: secs(2) });
+    clock.start(0, start);
+    assert_eq!(clock.remaining_at(0, start + secs(2)), secs(10));
+    clock.press(1, start + secs(1));
+    assert_eq!(clock.remaining, [secs(10), secs(10)]);
+    clock.press(0, start + secs(6));
+    assert_eq!(clock.remaining, [secs(10), secs(7)]);
+}
+
+#[test]
+fn test_moves_on_the_clock() {
+    let start = Instant::now();
+    let game_config = GameConfig::build(4, 6, false).unwrap();
+    let mut game_process = GameProcess::build(GameField::build(&game_config), "P1".to_string(), "P2".to_string(), game_config.clone());
+    let mut clock = GameClock::build(TimeControl::Fischer { base: secs(10), increment: secs(2) });
+    clock.start(0, start);
+
+    assert_eq!(game_process.move_stones_on_clock(1, &mut clock, start + secs(1)), Ok(GameStatus::Run));
+    assert_eq!(clock.remaining, [secs(11), secs(10)]);
+    assert_eq!(clock.running, Some((1, start + secs(1))));
+
+    // A move after the flag fell is not made
+    let mut game_process = GameProcess::build(GameField::build(&game_config), "P1".to_string(), "P2".to_string(), game_config);
+    let mut clock = GameClock::build(TimeControl::SuddenDeath { base: Duration::ZERO });
+    clock.start(0, start);
+    assert_eq!(game_process.move_stones_on_clock(1, &mut clock, start), Ok(GameStatus::Finished));
+    assert!(game_process.move_history.is_empty());
+    assert_eq!(clock.out_of_time(), Some(0));
+    assert_eq!(clock.running, None);
+}
+
+#[test]
+fn test_move_budget() {
+    let start = Instant::now();
+    let clock = GameClock::build(TimeControl::SuddenDeath { base: secs(100) });
+    assert_eq!(clock.move_budget(0, start), secs(5));
+
+    let mut clock = GameClock::build(TimeControl::Fischer { base: secs(100), increment: secs(3) });
+    assert_eq!(clock.move_budget(0, start), secs(8));
+    // Never more than half of what is left
+    clock.remaining[1] = secs(4);
+    assert_eq!(clock.move_budget(1, start), secs(2));
+}
+
+#[test]
+fn test_format_clock() {
+    assert_eq!(format_clock(secs(300)), "5:00");
+    assert_eq!(format_clock(secs(65)), "1:05");
+    assert_eq!(format_clock(secs(10)), "0:10");
+    assert_eq!(format_clock(Duration::from_millis(9_340)), "0:09.3");
+    assert_eq!(format_clock(Duration::ZERO), "0:00.0");
+}
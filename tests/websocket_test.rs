@@ -0,0 +1,166 @@
+use kalah::ai::compact_board::*;
+use kalah::game::game_config::*;
+use kalah::net::protocol::*;
+use kalah::net::server::*;
+use kalah::net::websocket::*;
+
+use std::io::{ BufRead, BufReader, Read, Write };
+use std::net::{ SocketAddr, TcpStream };
+use std::process::{ Command, Stdio };
+use std::thread;
+use std::time::Duration;
+
+use tungstenite::{ Message, WebSocket };
+
+const ENGINE: &str = env!("CARGO_BIN_EXE_kalah");
+
+/// Returns the addresses of the TCP server and the gateway.
+fn start_gateway(game_config: GameConfig) -> (SocketAddr, SocketAddr) {
+    let server = Server::bind("127.0.0.1:0", game_config).unwrap();
+    let gateway = WebGateway::bind("127.0.0.1:0", &server).unwrap();
+    let addresses = (server.local_addr().unwrap(), gateway.local_addr().unwrap());
+    thread::spawn(move || server.run());
+    thread::spawn(move || gateway.run());
+    addresses
+}
+
+fn http_get(address: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, address).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+struct Browser {
+    socket: WebSocket<TcpStream>,
+}
+
+impl Browser {
+
+    fn connect(address: SocketAddr) -> Browser {
+        let stream = TcpStream::connect(address).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let (socket, response) = tungstenite::client(format!("ws://{}{}", address, WEBSOCKET_PATH), stream).unwrap();
+        assert_eq!(response.status(), 101);
+        Browser { socket }
+    }
+
+    fn send(&mut self, message: ClientMessage) {
+        self.socket.send(Message::text(message.to_line())).unwrap();
+    }
+
+    fn receive(&mut self) -> ServerMessage {
+        loop {
+            if let Message::Text(text) = self.socket.read().unwrap() {
+                return ServerMessage::parse(text.as_str()).unwrap();
+            }
+        }
+    }
+
+    fn receive_state(&mut self) -> GameState {
+        match self.receive() {
+            ServerMessage::State(state) => state,
+            message => panic!("expected a state, got {:?}", message),
+        }
+    }
+}
+
+#[test]
+fn test_board_page() {
+    let (_, web_address) = start_gateway(GameConfig::build(4, 6, true).unwrap());
+
+    let page = http_get(web_address, "/");
+    assert!(page.starts_with("HTTP/1.1 200 OK\r\n"), "{}", page);
+    assert!(page.contains("Content-Type: text/html"));
+    assert!(page.contains("<title>Kalah</title>"));
+    assert!(page.contains("/ws`"));
+
+    assert!(http_get(web_address, "/missing").starts_with("HTTP/1.1 404 Not Found"));
+    assert!(http_get(web_address, WEBSOCKET_PATH).starts_with("HTTP/1.1 404 Not Found"));
+}
+
+#[test]
+fn test_browser_plays_tcp_client() {
+    let game_config = GameConfig::build(4, 6, true).unwrap();
+    let (address, web_address) = start_gateway(game_config.clone());
+
+    let mut browser = Browser::connect(web_address);
+    browser.send(ClientMessage::Hello { name: "Alice".to_string() });
+    assert_eq!(browser.receive(), ServerMessage::Games { games: vec![] });
+    browser.send(ClientMessage::CreateGame { holes: None, stones: None, pie: None, time_control: None });
+    assert!(matches!(browser.receive(), ServerMessage::Welcome { game: 1, player: 1, .. }));
+    browser.receive_state();
+
+    // The TCP client shares the lobby with the browser
+    let stream = TcpStream::connect(address).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut writer = stream.try_clone().unwrap();
+    let mut lines = BufReader::new(stream).lines();
+    let mut receive = || ServerMessage::parse(&lines.next().unwrap().unwrap()).unwrap();
+    writeln!(writer, "{}", ClientMessage::Hello { name: "Bob".to_string() }.to_line()).unwrap();
+    let ServerMessage::Games { games } = receive() else { panic!("expected the games") };
+    assert_eq!(games[0].players[0].as_ref().unwrap().name, "Alice");
+    writeln!(writer, "{}", ClientMessage::JoinGame { game: 1 }.to_line()).unwrap();
+    assert!(matches!(receive(), ServerMessage::Welcome { game: 1, player: 2, .. }));
+    receive();
+    assert_eq!(browser.receive_state().phase, Phase::Playing);
+
+    browser.send(ClientMessage::Move { hole: 2 });
+    let mut board = CompactBoard::build(&game_config);
+    board.apply_move(2).unwrap();
+    assert_eq!(browser.receive_state().position, board.to_position());
+    let ServerMessage::State(state) = receive() else { panic!("expected a state") };
+    assert_eq!(state.position, board.to_position());
+
+    browser.send(ClientMessage::Chat { text: "hello from the browser".to_string() });
+    let ServerMessage::Chat(line) = receive() else { panic!("expected a chat line") };
+    assert_eq!(line.display(), "Alice: hello from the browser");
+    browser.socket.send(Message::text("not json")).unwrap();
+    let ServerMessage::Chat(_) = browser.receive() else { panic!("expected the chat line back") };
+    assert!(matches!(browser.receive(), ServerMessage::Error { message } if message.starts_with("Invalid message")));
+
+    // Closing the socket leaves the seat like a dropped TCP connection
+    browser.socket.close(None).unwrap();
+    let ServerMessage::State(state) = receive() else { panic!("expected a state") };
+    assert!(!state.players[0].as_ref().unwrap().is_connected);
+}
+
+#[test]
+fn test_browser_plays_bot() {
+    let game_config = GameConfig::build(2, 3, false).unwrap();
+    let (address, web_address) = start_gateway(game_config.clone());
+    let mut bot = Command::new(ENGINE)
+        .args(["bot", &address.to_string(), "--level", "novice", "--move-time", "20", "--holes", "3", "--stones", "2", "--no-pie"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut browser = Browser::connect(web_address);
+    browser.send(ClientMessage::Hello { name: "Alice".to_string() });
+    let game = loop {
+        match browser.receive() {
+            ServerMessage::Games { games } if !games.is_empty() => break games[0].clone(),
+            _ => {}
+        }
+    };
+    assert_eq!(game.players[0].as_ref().unwrap().name, "Computer (novice)");
+    assert_eq!((game.holes, game.stones, game.pie), (3, 2, false));
+
+    browser.send(ClientMessage::JoinGame { game: game.id });
+    assert!(matches!(browser.receive(), ServerMessage::Welcome { game: id, player: 2, .. } if id == game.id));
+    let state = loop {
+        let state = browser.receive_state();
+        match state.phase {
+            Phase::Finished => break state,
+            Phase::Playing if !state.position.is_player_one_turn => {
+                let board = CompactBoard::from_position(&state.position, false).unwrap();
+                browser.send(ClientMessage::Move { hole: board.legal_moves().next().unwrap() });
+            }
+            _ => {}
+        }
+    };
+    assert_eq!(state.scores[0] + state.scores[1], 2 * 3 * 2);
+    bot.kill().unwrap();
+    bot.wait().unwrap();
+}